@@ -0,0 +1,189 @@
+//! Throughput of the value and event paths and latency of requests, against
+//! a gpio-sim chip. Needs root, skips everything without gpio-sim.
+
+use std::time::{Duration, Instant};
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+#[cfg(feature = "v1")]
+use gpio_cdev_async::line::{EventFlags, EventRequest};
+use gpio_cdev_async::{
+    chip::Chip,
+    event::{EdgeEventRef, LineEvent},
+    line::{HandleFlags, LineHandle, LineRequest},
+    sim::{Bank, Pull, TestChip},
+};
+
+const LINES: u32 = 64;
+/// The events queued per measurement, below the kernel's smallest buffer.
+const BURST: usize = 16;
+
+#[cfg(feature = "v1")]
+const INPUT: HandleFlags = HandleFlags::REQUEST_INPUT;
+#[cfg(feature = "v1")]
+const OUTPUT: HandleFlags = HandleFlags::REQUEST_OUTPUT;
+#[cfg(feature = "v2")]
+const INPUT: HandleFlags = HandleFlags::GPIO_V2_LINE_FLAG_INPUT;
+#[cfg(feature = "v2")]
+const OUTPUT: HandleFlags = HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT;
+
+fn request(flags: HandleFlags, lines: u32) -> LineRequest {
+    LineRequest::builder()
+        .set_flags(flags)
+        .set_consumer("bench")
+        .set_offsets(0..lines)
+        .build()
+        .unwrap()
+}
+
+fn set_all(handle: &LineHandle, value: u8) {
+    #[cfg(feature = "v1")]
+    {
+        let offsets = handle.offsets().to_vec();
+        handle
+            .set_values(offsets.into_iter().filter(|_| value != 0))
+            .unwrap();
+    }
+    #[cfg(feature = "v2")]
+    {
+        let offsets = handle.offsets().to_vec();
+        handle
+            .set_values(offsets.into_iter().map(|offset| (offset, value)))
+            .unwrap();
+    }
+}
+
+fn values(c: &mut Criterion, chip: &Chip) {
+    let mut group = c.benchmark_group("values");
+    for lines in [1, 8, LINES] {
+        group.throughput(Throughput::Elements(lines.into()));
+
+        let handle = chip.get_line(request(INPUT, lines)).unwrap();
+        group.bench_with_input(BenchmarkId::new("get", lines), &handle, |b, handle| {
+            b.iter(|| handle.get_values().unwrap())
+        });
+        drop(handle);
+
+        let handle = chip.get_line(request(OUTPUT, lines)).unwrap();
+        let mut value = 0;
+        group.bench_with_input(BenchmarkId::new("set", lines), &handle, |b, handle| {
+            b.iter(|| {
+                value ^= 1;
+                set_all(handle, value)
+            })
+        });
+    }
+}
+
+fn event_handle(chip: &Chip) -> LineHandle {
+    #[cfg(feature = "v1")]
+    {
+        let flags = EventFlags::REQUEST_RISING_EDGE | EventFlags::REQUEST_FALLING_EDGE;
+        EventRequest::new(0, INPUT, flags, "bench")
+            .request(chip)
+            .unwrap()
+    }
+    #[cfg(feature = "v2")]
+    {
+        let flags = INPUT
+            | HandleFlags::GPIO_V2_LINE_FLAG_EDGE_RISING
+            | HandleFlags::GPIO_V2_LINE_FLAG_EDGE_FALLING;
+        let request = LineRequest::builder()
+            .set_flags(flags)
+            .set_consumer("bench")
+            .set_offsets([0u32])
+            .set_event_buffer_size(BURST as u32)
+            .build()
+            .unwrap();
+        chip.get_line(request).unwrap()
+    }
+}
+
+/// Queues [`BURST`] edges on line 0, ending pulled down.
+fn burst(sim: &TestChip) {
+    for i in 0..BURST {
+        let pull = if i % 2 == 0 { Pull::Up } else { Pull::Down };
+        sim.set_pull(0, 0, pull).unwrap();
+    }
+}
+
+/// Times only the reads of `read`, each draining one burst.
+fn bench_bursts(sim: &TestChip, iters: u64, mut read: impl FnMut()) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        burst(sim);
+        let start = Instant::now();
+        read();
+        total += start.elapsed();
+    }
+    total
+}
+
+fn events(c: &mut Criterion, sim: &TestChip, chip: &Chip) {
+    let mut group = c.benchmark_group("events");
+    group.throughput(Throughput::Elements(BURST as u64));
+    sim.set_pull(0, 0, Pull::Down).unwrap();
+    let handle = event_handle(chip);
+
+    group.bench_function("read/one_by_one", |b| {
+        b.iter_custom(|iters| {
+            bench_bursts(sim, iters, || {
+                let mut buf = [LineEvent::default()];
+                for _ in 0..BURST {
+                    LineEvent::read(&handle, &mut buf).unwrap();
+                }
+            })
+        })
+    });
+
+    let mut reader = handle.event_reader(BURST);
+    let mut events = Vec::with_capacity(BURST);
+    group.bench_function("read/batched", |b| {
+        b.iter_custom(|iters| {
+            bench_bursts(sim, iters, || {
+                events.clear();
+                while events.len() < BURST {
+                    reader.read(&mut events).unwrap();
+                }
+            })
+        })
+    });
+
+    let mut buf = vec![0; BURST * EdgeEventRef::SIZE];
+    group.bench_function("read/zero_copy", |b| {
+        b.iter_custom(|iters| {
+            bench_bursts(sim, iters, || {
+                let mut read = 0;
+                while read < BURST {
+                    read += EdgeEventRef::read(&handle, &mut buf).unwrap().len();
+                }
+            })
+        })
+    });
+}
+
+fn requests(c: &mut Criterion, chip: &Chip) {
+    let mut group = c.benchmark_group("requests");
+    for lines in [1, LINES] {
+        group.bench_with_input(BenchmarkId::new("get_line", lines), &lines, |b, &lines| {
+            b.iter(|| chip.get_line(request(INPUT, lines)).unwrap())
+        });
+    }
+    group.bench_function("get_lineinfo", |b| b.iter(|| chip.get_lineinfo(0).unwrap()));
+}
+
+fn main() {
+    let sim = match TestChip::builder().add_bank(Bank::new(LINES)).build() {
+        Ok(sim) => sim,
+        Err(e) => {
+            eprintln!("skipping the benchmarks, no gpio-sim chip: {e}");
+            return;
+        }
+    };
+    let chip = Chip::new(sim.chip_path(0)).unwrap();
+
+    let mut c = Criterion::default().configure_from_args();
+    values(&mut c, &chip);
+    events(&mut c, &sim, &chip);
+    requests(&mut c, &chip);
+    c.final_summary();
+}
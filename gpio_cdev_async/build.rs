@@ -0,0 +1,29 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=proto");
+
+    #[cfg(feature = "grpc")]
+    {
+        if std::env::var_os("PROTOC").is_none() {
+            let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc");
+            // SAFETY: the build script is single-threaded
+            unsafe { std::env::set_var("PROTOC", protoc) };
+        }
+        tonic_prost_build::compile_protos("proto/gpio.proto").expect("failed to compile protos");
+    }
+
+    #[cfg(feature = "uapi-check")]
+    {
+        let out = std::path::PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+        bindgen::Builder::default()
+            .header_contents("uapi.h", "#include <linux/gpio.h>")
+            .allowlist_type("gpio.*")
+            .layout_tests(false)
+            .derive_debug(false)
+            .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+            .generate()
+            .expect("failed to generate bindings of linux/gpio.h")
+            .write_to_file(out.join("uapi.rs"))
+            .expect("failed to write bindings of linux/gpio.h");
+    }
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use gpio_cdev_async::parse::parse_line_events;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(events) = parse_line_events(data) {
+        for event in events {
+            let _ = format!("{event:?} {:?}", event.event_type());
+        }
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use gpio_cdev_async::parse::parse_line_info;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(info) = parse_line_info(data) {
+        let _ = format!("{info:?}");
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use gpio_cdev_async::parse::parse_line_info_changes;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(changes) = parse_line_info_changes(data) {
+        for change in changes {
+            let _ = format!("{change:?} {:?}", change.event_type());
+        }
+    }
+});
@@ -0,0 +1,67 @@
+/*
+ * C ABI of gpio_cdev_async, built with
+ * `cargo rustc --release --features capi --crate-type cdylib`.
+ *
+ * Functions returning `int` return 0 (or a count) on success and a
+ * negative errno on failure.
+ *
+ * `flags` are GPIOHANDLE_REQUEST_* flags when built with the v1 feature
+ * and GPIO_V2_LINE_FLAG_* flags when built with the v2 feature.
+ */
+
+#ifndef GPIO_CDEV_ASYNC_H
+#define GPIO_CDEV_ASYNC_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define GPIO_CDEV_EDGE_RISING  (1u << 0)
+#define GPIO_CDEV_EDGE_FALLING (1u << 1)
+
+typedef struct gpio_chip gpio_chip;
+typedef struct gpio_line_handle gpio_line_handle;
+
+struct gpio_line_event {
+	/* best estimate of time of event occurrence, in nanoseconds */
+	uint64_t timestamp_ns;
+	/* 1 for a rising edge, 2 for a falling edge */
+	uint32_t event_type;
+	/* the offset of the line that triggered the event */
+	uint32_t offset;
+};
+
+int gpio_chip_open(const char *path, gpio_chip **chip);
+void gpio_chip_close(gpio_chip *chip);
+
+/* `default_values` may be NULL, `consumer` may be NULL */
+int gpio_chip_request_lines(const gpio_chip *chip, const uint32_t *offsets,
+			    const uint8_t *default_values, size_t num_lines,
+			    uint64_t flags, const char *consumer,
+			    gpio_line_handle **handle);
+/* `event_flags` is a non-empty combination of GPIO_CDEV_EDGE_* */
+int gpio_chip_request_event_line(const gpio_chip *chip, uint32_t offset,
+				 uint64_t flags, uint32_t event_flags,
+				 const char *consumer,
+				 gpio_line_handle **handle);
+
+void gpio_line_release(gpio_line_handle *handle);
+/* returns 0 if `handle` is NULL */
+size_t gpio_line_num_lines(const gpio_line_handle *handle);
+/* returns the number of values written */
+int gpio_line_get_values(const gpio_line_handle *handle, uint8_t *values,
+			 size_t num_values);
+int gpio_line_set_values(const gpio_line_handle *handle,
+			 const uint8_t *values, size_t num_values);
+/* blocks until an edge event is available */
+int gpio_line_wait_event(const gpio_line_handle *handle,
+			 struct gpio_line_event *event);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GPIO_CDEV_ASYNC_H */
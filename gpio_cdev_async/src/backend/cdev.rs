@@ -0,0 +1,243 @@
+//! The kernel character device backend, used by [`Chip::new`](crate::chip::Chip::new).
+
+use std::{
+    fs::File,
+    io,
+    os::{
+        fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+        unix::fs::OpenOptionsExt,
+    },
+    path::Path,
+};
+
+use super::{GpioBackend, LineBackend};
+#[cfg(feature = "v1")]
+use crate::line::EventRequest;
+use crate::{
+    chip::{ChipInfo, OpenOptions},
+    event::{read_records, LineEvent, LineInfoChangedEvent},
+    ffi::{self, common::Pod},
+    line::{LineInfo, LineRequest},
+    Result,
+};
+
+/// An open `/dev/gpiochipN`.
+#[derive(Debug)]
+pub(crate) struct Cdev {
+    file: File,
+    /// Whether the fds of the requests are closed on exec, like the chip's.
+    cloexec: bool,
+}
+
+impl Cdev {
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, &OpenOptions::new())
+    }
+
+    pub(crate) fn open_with(path: &Path, options: &OpenOptions) -> Result<Self> {
+        // std always opens with `O_CLOEXEC`
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(match options.nonblocking {
+                true => options.custom_flags | libc::O_NONBLOCK,
+                false => options.custom_flags,
+            })
+            .open(path)?;
+        if !options.cloexec {
+            set_cloexec(file.as_raw_fd(), false)?;
+        }
+        Ok(Self {
+            file,
+            cloexec: options.cloexec,
+        })
+    }
+
+    /// Takes ownership of the fd of a granted request.
+    fn line(&self, fd: RawFd) -> Result<Box<dyn LineBackend>> {
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        // the kernel creates request fds with `O_CLOEXEC`, but older ones did not
+        set_cloexec(fd.as_raw_fd(), self.cloexec)?;
+        Ok(Box::new(CdevLine { fd }))
+    }
+}
+
+fn set_cloexec(fd: RawFd, cloexec: bool) -> io::Result<()> {
+    // SAFETY: `F_GETFD` and `F_SETFD` only change the fd flags
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let new_flags = match cloexec {
+        true => flags | libc::FD_CLOEXEC,
+        false => flags & !libc::FD_CLOEXEC,
+    };
+    if new_flags != flags && unsafe { libc::fcntl(fd, libc::F_SETFD, new_flags) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl GpioBackend for Cdev {
+    fn chip_info(&self) -> Result<ChipInfo> {
+        let mut inner: ffi::common::GpioChipInfo = unsafe { std::mem::zeroed() };
+        ffi::common::gpio_get_chipinfo_ioctl(self.file.as_raw_fd(), &mut inner)?;
+        Ok(ChipInfo { inner })
+    }
+
+    fn line_info(&self, offset: u32) -> Result<LineInfo> {
+        #[cfg(feature = "v2")]
+        {
+            use ffi::v2::GpioV2LineInfo;
+            let mut inner: GpioV2LineInfo = unsafe { std::mem::zeroed() };
+            inner.offset = offset;
+            ffi::v2::gpio_v2_get_lineinfo_ioctl(self.file.as_raw_fd(), &mut inner)?;
+            inner.validate()?;
+            Ok(LineInfo { inner })
+        }
+        #[cfg(feature = "v1")]
+        {
+            use ffi::v1::GpioLineInfo;
+            let mut inner: GpioLineInfo = unsafe { std::mem::zeroed() };
+            inner.line_offset = offset;
+            ffi::v1::gpio_get_lineinfo_ioctl(self.file.as_raw_fd(), &mut inner)?;
+            inner.validate()?;
+            Ok(LineInfo { inner })
+        }
+    }
+
+    fn request_lines(&self, request: LineRequest) -> Result<Box<dyn LineBackend>> {
+        let mut data = request;
+        #[cfg(feature = "v2")]
+        ffi::v2::gpio_v2_get_line_ioctl(self.file.as_raw_fd(), &mut data.inner)?;
+        #[cfg(feature = "v1")]
+        ffi::v1::gpio_get_linehandle_ioctl(self.file.as_raw_fd(), &mut data.inner)?;
+        self.line(data.inner.fd)
+    }
+
+    #[cfg(feature = "v1")]
+    fn request_event_line(&self, request: EventRequest) -> Result<Box<dyn LineBackend>> {
+        let mut data = request;
+        ffi::v1::gpio_get_lineevent_ioctl(self.file.as_raw_fd(), &mut data.inner)?;
+        self.line(data.inner.fd)
+    }
+
+    fn watch_line_info(&self, offset: u32) -> Result<LineInfo> {
+        #[cfg(feature = "v2")]
+        {
+            use ffi::v2::GpioV2LineInfo;
+            let mut inner: GpioV2LineInfo = unsafe { std::mem::zeroed() };
+            inner.offset = offset;
+            ffi::v2::gpio_v2_get_lineinfo_watch_ioctl(self.file.as_raw_fd(), &mut inner)?;
+            inner.validate()?;
+            Ok(LineInfo { inner })
+        }
+        #[cfg(feature = "v1")]
+        {
+            use ffi::v1::GpioLineInfo;
+            let mut inner: GpioLineInfo = unsafe { std::mem::zeroed() };
+            inner.line_offset = offset;
+            ffi::v1::gpio_get_lineinfo_watch_ioctl(self.file.as_raw_fd(), &mut inner)?;
+            inner.validate()?;
+            Ok(LineInfo { inner })
+        }
+    }
+
+    fn unwatch_line_info(&self, offset: u32) -> Result<()> {
+        let mut offset = offset;
+        ffi::common::gpio_get_lineinfo_unwatch_ioctl(self.file.as_raw_fd(), &mut offset)?;
+        Ok(())
+    }
+
+    fn read_line_info_changes(&self, buf: &mut [LineInfoChangedEvent]) -> Result<usize> {
+        read_records(self.file.as_raw_fd(), buf)
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn GpioBackend>> {
+        // `F_DUPFD_CLOEXEC`, the flag is then cleared again if needed
+        let file = self.file.try_clone()?;
+        if !self.cloexec {
+            set_cloexec(file.as_raw_fd(), false)?;
+        }
+        Ok(Box::new(Self {
+            file,
+            cloexec: self.cloexec,
+        }))
+    }
+}
+
+/// A line request fd.
+#[derive(Debug)]
+pub(crate) struct CdevLine {
+    pub(crate) fd: OwnedFd,
+}
+
+impl LineBackend for CdevLine {
+    fn get_values(&self, mask: u64) -> Result<u64> {
+        #[cfg(feature = "v1")]
+        {
+            let _ = mask;
+            let mut data: ffi::v1::GpioHandleData = unsafe { std::mem::zeroed() };
+            ffi::v1::gpiohandle_get_line_values_ioctl(self.fd.as_raw_fd(), &mut data)?;
+            let bits = data
+                .values
+                .iter()
+                .enumerate()
+                .filter(|&(_, &value)| value != 0)
+                .fold(0, |bits, (index, _)| bits | 1 << index);
+            Ok(bits)
+        }
+        #[cfg(feature = "v2")]
+        {
+            let mut data: ffi::v2::GpioV2LineValues = unsafe { std::mem::zeroed() };
+            data.mask = mask as _;
+            ffi::v2::gpio_v2_line_get_values_ioctl(self.fd.as_raw_fd(), &mut data)?;
+            Ok(data.bits & mask)
+        }
+    }
+
+    fn set_values(&self, mask: u64, bits: u64) -> Result<()> {
+        #[cfg(feature = "v1")]
+        {
+            let _ = mask;
+            let mut data: ffi::v1::GpioHandleData = unsafe { std::mem::zeroed() };
+            for (index, value) in data.values.iter_mut().enumerate() {
+                *value = (bits >> index & 1) as u8;
+            }
+            ffi::v1::gpiohandle_set_line_values_ioctl(self.fd.as_raw_fd(), &mut data)?;
+        }
+        #[cfg(feature = "v2")]
+        {
+            let mut data: ffi::v2::GpioV2LineValues = unsafe { std::mem::zeroed() };
+            data.mask = mask as _;
+            data.bits = bits as _;
+            ffi::v2::gpio_v2_line_set_values_ioctl(self.fd.as_raw_fd(), &mut data)?;
+        }
+        Ok(())
+    }
+
+    fn update_config(&self, config: LineRequest) -> Result<()> {
+        #[cfg(feature = "v2")]
+        {
+            let mut data = config.inner.config;
+            ffi::v2::gpio_v2_line_set_config_ioctl(self.fd.as_raw_fd(), &mut data)?;
+        }
+        #[cfg(feature = "v1")]
+        {
+            let mut data = ffi::v1::GpioHandleConfig {
+                flags: config.flags().bits(),
+                default_values: config.inner.default_values,
+                padding: ffi::common::Padding([0; 4]),
+            };
+            ffi::v1::gpiohandle_set_config_ioctl(self.fd.as_raw_fd(), &mut data)?;
+        }
+        Ok(())
+    }
+
+    fn read_events(&self, buf: &mut [LineEvent]) -> Result<usize> {
+        read_records(self.fd.as_raw_fd(), buf)
+    }
+
+    fn as_fd(&self) -> Option<BorrowedFd<'_>> {
+        Some(self.fd.as_fd())
+    }
+}
@@ -0,0 +1,169 @@
+//! The backends behind [`Chip`] and [`LineHandle`].
+//!
+//! [`Chip::new`] opens a kernel character device, [`Chip::from_backend`]
+//! plugs in any other implementation of [`GpioBackend`], e.g. a simulator,
+//! a remote daemon or an I²C expander. Application code keeps using the
+//! [`Chip`] and [`LineHandle`] API either way.
+//!
+//! Values are exchanged as bitmaps: bit `i` of a mask or of the values
+//! corresponds to the `i`th offset of the request, not to a line offset.
+//!
+//! # Examples
+//! ```rust
+//! # use gpio_cdev_async::{backend::{GpioBackend, LineBackend}, chip::{Chip, ChipInfo}, line::{LineInfo, LineFlags, LineRequest}, Result};
+//! /// A chip of 4 lines, all reading high.
+//! #[derive(Debug)]
+//! struct AllHigh;
+//!
+//! #[derive(Debug)]
+//! struct AllHighLines;
+//!
+//! impl GpioBackend for AllHigh {
+//!     fn chip_info(&self) -> Result<ChipInfo> {
+//!         Ok(ChipInfo::new("gpiochip0", "all-high", 4))
+//!     }
+//!
+//!     fn line_info(&self, offset: u32) -> Result<LineInfo> {
+//!         Ok(LineInfo::new(offset, LineFlags::empty(), "", ""))
+//!     }
+//!
+//!     fn request_lines(&self, _request: LineRequest) -> Result<Box<dyn LineBackend>> {
+//!         Ok(Box::new(AllHighLines))
+//!     }
+//! }
+//!
+//! impl LineBackend for AllHighLines {
+//!     fn get_values(&self, mask: u64) -> Result<u64> {
+//!         Ok(mask)
+//!     }
+//!
+//!     fn set_values(&self, _mask: u64, _bits: u64) -> Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let chip = Chip::from_backend("all-high", AllHigh);
+//! assert_eq!(chip.get_chipinfo().unwrap().lines(), 4);
+//!
+//! let request = LineRequest::builder().set_offsets([2u32]).build().unwrap();
+//! let handle = chip.get_line(request).unwrap();
+//! assert_eq!(handle.get_values().unwrap().value_of_offset(2), Some(1));
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{any::Any, fmt::Debug, io, os::fd::BorrowedFd};
+
+#[cfg(feature = "v1")]
+use crate::line::EventRequest;
+#[cfg(doc)]
+use crate::{chip::Chip, line::LineHandle};
+use crate::{
+    chip::ChipInfo,
+    event::{LineEvent, LineInfoChangedEvent},
+    line::{LineInfo, LineRequest},
+    Error, Result,
+};
+
+pub(crate) mod cdev;
+
+/// The operations of a chip, see the [module documentation](self).
+pub trait GpioBackend: Debug + Send + Sync {
+    fn chip_info(&self) -> Result<ChipInfo>;
+
+    fn line_info(&self, offset: u32) -> Result<LineInfo>;
+
+    /// Requests the lines of `request`, as in [`Chip::get_line`].
+    fn request_lines(&self, request: LineRequest) -> Result<Box<dyn LineBackend>>;
+
+    /// Requests a line for edge events, as in [`Chip::get_event_line`].
+    ///
+    /// Unsupported by default.
+    #[cfg(feature = "v1")]
+    fn request_event_line(&self, request: EventRequest) -> Result<Box<dyn LineBackend>> {
+        let _ = request;
+        Err(unsupported())
+    }
+
+    /// Starts watching a line, as in [`Chip::get_lineinfo_watch`].
+    ///
+    /// Unsupported by default.
+    fn watch_line_info(&self, offset: u32) -> Result<LineInfo> {
+        let _ = offset;
+        Err(unsupported())
+    }
+
+    /// Stops watching a line, as in [`Chip::get_lineinfo_unwatch`].
+    ///
+    /// Unsupported by default.
+    fn unwatch_line_info(&self, offset: u32) -> Result<()> {
+        let _ = offset;
+        Err(unsupported())
+    }
+
+    /// Reads the info changes of the watched lines into `buf`, blocking
+    /// until at least one is available. Returning `0` ends
+    /// [`Chip::lineinfo_changes`].
+    ///
+    /// Unsupported by default.
+    fn read_line_info_changes(&self, buf: &mut [LineInfoChangedEvent]) -> Result<usize> {
+        let _ = buf;
+        Err(unsupported())
+    }
+
+    /// Another handle to the same chip, as in [`Chip::try_clone`].
+    ///
+    /// Unsupported by default.
+    fn try_clone(&self) -> Result<Box<dyn GpioBackend>> {
+        Err(unsupported())
+    }
+}
+
+/// The operations of requested lines, see the [module documentation](self).
+pub trait LineBackend: Any + Debug + Send + Sync {
+    /// The values of the lines selected by `mask`, other bits are ignored.
+    fn get_values(&self, mask: u64) -> Result<u64>;
+
+    /// Sets the lines selected by `mask` to `bits`, the others keep their
+    /// values. v1 handles always select all the lines.
+    fn set_values(&self, mask: u64, bits: u64) -> Result<()>;
+
+    /// Reconfigures the lines, as in [`LineHandle::update_config`].
+    ///
+    /// Unsupported by default.
+    fn update_config(&self, config: LineRequest) -> Result<()> {
+        let _ = config;
+        Err(unsupported())
+    }
+
+    /// Reads edge events into `buf`, blocking until at least one is
+    /// available. Returning `0` ends [`LineHandle::events`].
+    ///
+    /// Unsupported by default.
+    fn read_events(&self, buf: &mut [LineEvent]) -> Result<usize> {
+        let _ = buf;
+        Err(unsupported())
+    }
+
+    /// Reads edge events into the first non-empty buffer of `bufs`, see
+    /// [`LineEvent::read_vectored`].
+    ///
+    /// Fills it with [`read_events`](Self::read_events) by default.
+    fn read_events_vectored(&self, bufs: &mut [&mut [LineEvent]]) -> Result<usize> {
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.read_events(buf),
+            None => Ok(0),
+        }
+    }
+
+    /// A file descriptor readable when events are available, for polling.
+    ///
+    /// `None` by default.
+    fn as_fd(&self) -> Option<BorrowedFd<'_>> {
+        None
+    }
+}
+
+fn unsupported() -> Error {
+    io::Error::from(io::ErrorKind::Unsupported).into()
+}
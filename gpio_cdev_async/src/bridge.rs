@@ -0,0 +1,69 @@
+//! The lines of the bridges to other protocols: [`mqtt`](crate::mqtt),
+//! [`websocket`](crate::websocket) and [`rest`](crate::rest).
+
+use std::path::Path;
+
+use crate::{
+    chip::Chip,
+    config::Edge,
+    line::{HandleFlags, LineHandle, LineRequest},
+    Result,
+};
+
+/// The lines served by a bridge.
+#[derive(Debug)]
+pub(crate) struct BridgeLines {
+    /// The handles of the inputs with edge events on both edges, one per
+    /// input on v1, one for all of them on v2.
+    pub(crate) inputs: Vec<LineHandle>,
+    /// The handle of all the outputs, which start low.
+    pub(crate) outputs: Option<LineHandle>,
+}
+
+impl BridgeLines {
+    /// Requests `inputs` and `outputs`, offsets of the chip at `chip`.
+    pub(crate) fn request(
+        chip: &Path,
+        consumer: &str,
+        inputs: &[u32],
+        outputs: &[u32],
+    ) -> Result<Self> {
+        let chip = Chip::new(chip)?;
+
+        let outputs = if outputs.is_empty() {
+            None
+        } else {
+            #[cfg(feature = "v1")]
+            let flags = HandleFlags::REQUEST_OUTPUT;
+            #[cfg(feature = "v2")]
+            let flags = HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT;
+            let request = LineRequest::builder()
+                .set_flags(flags)
+                .set_consumer(consumer)
+                .set_offsets(outputs.iter().copied())
+                .build()?;
+            Some(chip.get_line(request)?)
+        };
+
+        #[cfg(feature = "v1")]
+        let inputs = inputs
+            .iter()
+            .map(|&offset| {
+                chip.request_edge_events(offset, Edge::Both, HandleFlags::REQUEST_INPUT, consumer)
+            })
+            .collect::<Result<_>>()?;
+        #[cfg(feature = "v2")]
+        let inputs = if inputs.is_empty() {
+            Vec::new()
+        } else {
+            let request = LineRequest::builder()
+                .set_flags(HandleFlags::GPIO_V2_LINE_FLAG_INPUT | Edge::Both.flags())
+                .set_consumer(consumer)
+                .set_offsets(inputs.iter().copied())
+                .build()?;
+            vec![chip.get_line(request)?]
+        };
+
+        Ok(Self { inputs, outputs })
+    }
+}
@@ -0,0 +1,756 @@
+//! Protocol and client of the `gpio-cdev-daemon` GPIO broker.
+//!
+//! The daemon owns the line requests and serves them over a unix socket, so
+//! unprivileged processes can use GPIO through a privileged broker.
+//!
+//! # Protocol
+//! Every message is a frame: a little-endian `u32` length followed by that
+//! many bytes of payload. A payload is a tag byte followed by the fields of
+//! the message, integers are little-endian and strings and lists are prefixed
+//! by their `u32` length.
+//!
+//! Every [`Request`] is answered by exactly one [`Response`], except
+//! [`Request::Subscribe`]: once answered, the connection only carries
+//! [`Response::Event`] frames until it is closed.
+//!
+//! Handles are scoped to their connection and released when it is closed.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{broker::Client, line::HandleFlags};
+//! let mut client = Client::connect("/run/gpio-cdev.sock").unwrap();
+//! # #[cfg(feature = "v1")]
+//! # let flags = HandleFlags::REQUEST_OUTPUT;
+//! # #[cfg(feature = "v2")]
+//! # let flags = HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT;
+//! let handle = client
+//!     .request("/dev/gpiochip0", &[6], flags, &[0], "example")
+//!     .unwrap();
+//! client.set(handle, &[1]).unwrap();
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+};
+
+use crate::{line::HandleFlags, Error, Result};
+
+/// Request rising edge events in [`Request::Subscribe`].
+pub const EDGE_RISING: u32 = 1 << 0;
+/// Request falling edge events in [`Request::Subscribe`].
+pub const EDGE_FALLING: u32 = 1 << 1;
+
+/// The largest payload accepted by [`read_frame`].
+pub const MAX_FRAME_LEN: u32 = 1 << 20;
+
+/// A chip known to the daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChipSummary {
+    pub path: String,
+    pub name: String,
+    pub label: String,
+    pub lines: u32,
+}
+
+/// The information of a line, `flags` are the raw kernel line flags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineSummary {
+    pub offset: u32,
+    pub name: String,
+    pub consumer: String,
+    pub flags: u64,
+}
+
+/// An edge event, `event_type` is `1` for a rising edge and `2` for a falling edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeEvent {
+    pub handle: u32,
+    pub offset: u32,
+    pub event_type: u32,
+    pub timestamp_ns: u64,
+}
+
+/// A request sent to the daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// List the chips, answered by [`Response::Chips`].
+    ListChips,
+    /// List the lines of `chip`, answered by [`Response::Lines`].
+    ListLines { chip: String },
+    /// Request lines, answered by [`Response::Handle`].
+    ///
+    /// `values` are the initial values of output lines, or empty.
+    Request {
+        chip: String,
+        offsets: Vec<u32>,
+        flags: u64,
+        values: Vec<u8>,
+        consumer: String,
+    },
+    /// Get the values of a handle in request order, answered by [`Response::Values`].
+    Get { handle: u32 },
+    /// Set the values of a handle in request order, answered by [`Response::Ok`].
+    Set { handle: u32, values: Vec<u8> },
+    /// Release a handle, answered by [`Response::Ok`].
+    Release { handle: u32 },
+    /// Request edge events on a line, answered by [`Response::Handle`].
+    ///
+    /// `edges` is a combination of [`EDGE_RISING`] and [`EDGE_FALLING`].
+    Subscribe {
+        chip: String,
+        offset: u32,
+        flags: u64,
+        edges: u32,
+        consumer: String,
+    },
+}
+
+/// A response sent by the daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    Ok,
+    Chips(Vec<ChipSummary>),
+    Lines(Vec<LineSummary>),
+    Handle(u32),
+    Values(Vec<u8>),
+    Event(EdgeEvent),
+    /// A failed request, `errno` is `0` if it is unknown.
+    Error {
+        errno: i32,
+        message: String,
+    },
+}
+
+impl Response {
+    /// The response reporting `e`.
+    pub fn error(e: &Error) -> Self {
+        Self::Error {
+            errno: e.raw_os_error().unwrap_or_default(),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Reads one frame, returns `None` if the stream is closed before it starts.
+pub fn read_frame(r: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    // only a close before the first byte ends the stream cleanly
+    loop {
+        match r.read(&mut len[..1]) {
+            Ok(0) => return Ok(None),
+            Ok(_) => break,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    r.read_exact(&mut len[1..])?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(invalid_data(format!("frame of {len} bytes is too large")));
+    }
+    let mut payload = vec![0; len as usize];
+    r.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// Writes `payload` as one frame.
+pub fn write_frame(w: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|&len| len <= MAX_FRAME_LEN)
+        .ok_or_else(|| invalid_data("frame is too large"))?;
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(payload);
+    w.write_all(&frame)
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, v: u8) -> &mut Self {
+        self.buf.push(v);
+        self
+    }
+
+    fn u32(&mut self, v: u32) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn i32(&mut self, v: i32) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u64(&mut self, v: u64) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn bytes(&mut self, v: &[u8]) -> &mut Self {
+        self.u32(v.len() as u32);
+        self.buf.extend_from_slice(v);
+        self
+    }
+
+    fn str(&mut self, v: &str) -> &mut Self {
+        self.bytes(v.as_bytes())
+    }
+
+    fn u32s(&mut self, v: &[u32]) -> &mut Self {
+        self.u32(v.len() as u32);
+        v.iter().for_each(|&v| {
+            self.u32(v);
+        });
+        self
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(invalid_data("truncated message"));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn len(&mut self, size: usize) -> io::Result<usize> {
+        let len = self.u32()? as usize;
+        // reject lengths the remaining payload cannot hold before allocating
+        if len.saturating_mul(size) > self.buf.len() {
+            return Err(invalid_data("truncated message"));
+        }
+        Ok(len)
+    }
+
+    fn bytes(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.len(1)?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn str(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?).map_err(|_| invalid_data("invalid utf-8 string"))
+    }
+
+    fn u32s(&mut self) -> io::Result<Vec<u32>> {
+        let len = self.len(4)?;
+        (0..len).map(|_| self.u32()).collect()
+    }
+
+    fn list<T>(&mut self, item: impl Fn(&mut Self) -> io::Result<T>) -> io::Result<Vec<T>> {
+        let len = self.len(1)?;
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn finish<T>(self, v: T) -> io::Result<T> {
+        if self.buf.is_empty() {
+            Ok(v)
+        } else {
+            Err(invalid_data("trailing bytes in message"))
+        }
+    }
+}
+
+impl Request {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut e = Encoder::default();
+        match self {
+            Self::ListChips => e.u8(0),
+            Self::ListLines { chip } => e.u8(1).str(chip),
+            Self::Request {
+                chip,
+                offsets,
+                flags,
+                values,
+                consumer,
+            } => e
+                .u8(2)
+                .str(chip)
+                .u32s(offsets)
+                .u64(*flags)
+                .bytes(values)
+                .str(consumer),
+            Self::Get { handle } => e.u8(3).u32(*handle),
+            Self::Set { handle, values } => e.u8(4).u32(*handle).bytes(values),
+            Self::Release { handle } => e.u8(5).u32(*handle),
+            Self::Subscribe {
+                chip,
+                offset,
+                flags,
+                edges,
+                consumer,
+            } => e
+                .u8(6)
+                .str(chip)
+                .u32(*offset)
+                .u64(*flags)
+                .u32(*edges)
+                .str(consumer),
+        };
+        e.buf
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder { buf: payload };
+        let request = match d.u8()? {
+            0 => Self::ListChips,
+            1 => Self::ListLines { chip: d.str()? },
+            2 => Self::Request {
+                chip: d.str()?,
+                offsets: d.u32s()?,
+                flags: d.u64()?,
+                values: d.bytes()?,
+                consumer: d.str()?,
+            },
+            3 => Self::Get { handle: d.u32()? },
+            4 => Self::Set {
+                handle: d.u32()?,
+                values: d.bytes()?,
+            },
+            5 => Self::Release { handle: d.u32()? },
+            6 => Self::Subscribe {
+                chip: d.str()?,
+                offset: d.u32()?,
+                flags: d.u64()?,
+                edges: d.u32()?,
+                consumer: d.str()?,
+            },
+            tag => return Err(invalid_data(format!("unknown request {tag}"))),
+        };
+        d.finish(request)
+    }
+}
+
+impl Response {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut e = Encoder::default();
+        match self {
+            Self::Ok => e.u8(0),
+            Self::Chips(chips) => {
+                e.u8(1).u32(chips.len() as u32);
+                for chip in chips {
+                    e.str(&chip.path)
+                        .str(&chip.name)
+                        .str(&chip.label)
+                        .u32(chip.lines);
+                }
+                &mut e
+            }
+            Self::Lines(lines) => {
+                e.u8(2).u32(lines.len() as u32);
+                for line in lines {
+                    e.u32(line.offset)
+                        .str(&line.name)
+                        .str(&line.consumer)
+                        .u64(line.flags);
+                }
+                &mut e
+            }
+            Self::Handle(handle) => e.u8(3).u32(*handle),
+            Self::Values(values) => e.u8(4).bytes(values),
+            Self::Event(event) => e
+                .u8(5)
+                .u32(event.handle)
+                .u32(event.offset)
+                .u32(event.event_type)
+                .u64(event.timestamp_ns),
+            Self::Error { errno, message } => e.u8(6).i32(*errno).str(message),
+        };
+        e.buf
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder { buf: payload };
+        let response = match d.u8()? {
+            0 => Self::Ok,
+            1 => Self::Chips(d.list(|d| {
+                Ok(ChipSummary {
+                    path: d.str()?,
+                    name: d.str()?,
+                    label: d.str()?,
+                    lines: d.u32()?,
+                })
+            })?),
+            2 => Self::Lines(d.list(|d| {
+                Ok(LineSummary {
+                    offset: d.u32()?,
+                    name: d.str()?,
+                    consumer: d.str()?,
+                    flags: d.u64()?,
+                })
+            })?),
+            3 => Self::Handle(d.u32()?),
+            4 => Self::Values(d.bytes()?),
+            5 => Self::Event(EdgeEvent {
+                handle: d.u32()?,
+                offset: d.u32()?,
+                event_type: d.u32()?,
+                timestamp_ns: d.u64()?,
+            }),
+            6 => Self::Error {
+                errno: d.i32()?,
+                message: d.str()?,
+            },
+            tag => return Err(invalid_data(format!("unknown response {tag}"))),
+        };
+        d.finish(response)
+    }
+}
+
+/// A connection to the daemon.
+#[derive(Debug)]
+pub struct Client {
+    stream: UnixStream,
+}
+
+impl Client {
+    /// Connects to the daemon listening on `path`.
+    pub fn connect(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            stream: UnixStream::connect(path)?,
+        })
+    }
+
+    /// Sends `request` and waits for its response, a [`Response::Error`] is returned as `Err`.
+    pub fn call(&mut self, request: &Request) -> Result<Response> {
+        write_frame(&mut self.stream, &request.encode())?;
+        match read_response(&mut self.stream)? {
+            Response::Error { errno, message } => Err(remote_error(errno, message)),
+            response => Ok(response),
+        }
+    }
+
+    pub fn list_chips(&mut self) -> Result<Vec<ChipSummary>> {
+        match self.call(&Request::ListChips)? {
+            Response::Chips(chips) => Ok(chips),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    pub fn list_lines(&mut self, chip: &str) -> Result<Vec<LineSummary>> {
+        let request = Request::ListLines { chip: chip.into() };
+        match self.call(&request)? {
+            Response::Lines(lines) => Ok(lines),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Requests lines, returns their handle.
+    ///
+    /// `values` are the initial values of output lines, or empty.
+    pub fn request(
+        &mut self,
+        chip: &str,
+        offsets: &[u32],
+        flags: HandleFlags,
+        values: &[u8],
+        consumer: &str,
+    ) -> Result<u32> {
+        let request = Request::Request {
+            chip: chip.into(),
+            offsets: offsets.into(),
+            flags: flags.bits() as _,
+            values: values.into(),
+            consumer: consumer.into(),
+        };
+        match self.call(&request)? {
+            Response::Handle(handle) => Ok(handle),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Gets the values of the lines of `handle`, in request order.
+    pub fn get(&mut self, handle: u32) -> Result<Vec<u8>> {
+        match self.call(&Request::Get { handle })? {
+            Response::Values(values) => Ok(values),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Sets the values of the lines of `handle`, in request order.
+    pub fn set(&mut self, handle: u32, values: &[u8]) -> Result<()> {
+        let request = Request::Set {
+            handle,
+            values: values.into(),
+        };
+        self.call(&request).and_then(expect_ok)
+    }
+
+    pub fn release(&mut self, handle: u32) -> Result<()> {
+        self.call(&Request::Release { handle }).and_then(expect_ok)
+    }
+
+    /// Requests edge events on the line at `offset`, the connection is then
+    /// dedicated to these events.
+    ///
+    /// `edges` is a combination of [`EDGE_RISING`] and [`EDGE_FALLING`].
+    pub fn subscribe(
+        mut self,
+        chip: &str,
+        offset: u32,
+        flags: HandleFlags,
+        edges: u32,
+        consumer: &str,
+    ) -> Result<EventStream> {
+        let request = Request::Subscribe {
+            chip: chip.into(),
+            offset,
+            flags: flags.bits() as _,
+            edges,
+            consumer: consumer.into(),
+        };
+        match self.call(&request)? {
+            Response::Handle(handle) => Ok(EventStream {
+                stream: self.stream,
+                handle,
+            }),
+            response => Err(unexpected(response)),
+        }
+    }
+}
+
+/// The edge events of a [`Client::subscribe`], a blocking iterator ending
+/// when the daemon closes the connection.
+#[derive(Debug)]
+pub struct EventStream {
+    stream: UnixStream,
+    handle: u32,
+}
+
+impl EventStream {
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = Result<EdgeEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let payload = match read_frame(&mut self.stream) {
+            Ok(Some(payload)) => payload,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e.into())),
+        };
+        Some(match Response::decode(&payload) {
+            Ok(Response::Event(event)) => Ok(event),
+            Ok(Response::Error { errno, message }) => Err(remote_error(errno, message)),
+            Ok(response) => Err(unexpected(response)),
+            Err(e) => Err(e.into()),
+        })
+    }
+}
+
+fn read_response(r: &mut impl Read) -> Result<Response> {
+    let payload = read_frame(r)?.ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    Ok(Response::decode(&payload)?)
+}
+
+fn remote_error(errno: i32, message: String) -> Error {
+    if errno == 0 {
+        io::Error::other(message).into()
+    } else {
+        io::Error::from_raw_os_error(errno).into()
+    }
+}
+
+fn expect_ok(response: Response) -> Result<()> {
+    match response {
+        Response::Ok => Ok(()),
+        response => Err(unexpected(response)),
+    }
+}
+
+fn unexpected(response: Response) -> Error {
+    invalid_data(format!("unexpected response {response:?}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requests() -> Vec<Request> {
+        vec![
+            Request::ListChips,
+            Request::ListLines {
+                chip: "/dev/gpiochip0".into(),
+            },
+            Request::Request {
+                chip: "/dev/gpiochip0".into(),
+                offsets: vec![3, 4, 17],
+                flags: 1 << 40 | 2,
+                values: vec![1, 0, 1],
+                consumer: "test".into(),
+            },
+            Request::Request {
+                chip: String::new(),
+                offsets: vec![],
+                flags: 0,
+                values: vec![],
+                consumer: String::new(),
+            },
+            Request::Get { handle: 7 },
+            Request::Set {
+                handle: u32::MAX,
+                values: vec![0, 1],
+            },
+            Request::Release { handle: 0 },
+            Request::Subscribe {
+                chip: "/dev/gpiochip1".into(),
+                offset: 12,
+                flags: u64::MAX,
+                edges: EDGE_RISING | EDGE_FALLING,
+                consumer: "ünïcode".into(),
+            },
+        ]
+    }
+
+    fn responses() -> Vec<Response> {
+        vec![
+            Response::Ok,
+            Response::Chips(vec![]),
+            Response::Chips(vec![
+                ChipSummary {
+                    path: "/dev/gpiochip0".into(),
+                    name: "gpiochip0".into(),
+                    label: "pinctrl-bcm2711".into(),
+                    lines: 58,
+                },
+                ChipSummary {
+                    path: "/dev/gpiochip1".into(),
+                    name: "gpiochip1".into(),
+                    label: String::new(),
+                    lines: 8,
+                },
+            ]),
+            Response::Lines(vec![LineSummary {
+                offset: 4,
+                name: "GPIO4".into(),
+                consumer: "test".into(),
+                flags: 1 << 33 | 1,
+            }]),
+            Response::Handle(3),
+            Response::Values(vec![1, 0, 0, 1]),
+            Response::Event(EdgeEvent {
+                handle: 2,
+                offset: 17,
+                event_type: EDGE_FALLING,
+                timestamp_ns: u64::MAX - 1,
+            }),
+            Response::Error {
+                errno: -1,
+                message: "failed".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        for request in requests() {
+            assert_eq!(Request::decode(&request.encode()).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        for response in responses() {
+            assert_eq!(Response::decode(&response.encode()).unwrap(), response);
+        }
+    }
+
+    #[test]
+    fn truncated_messages_are_rejected() {
+        for payload in requests().iter().map(Request::encode) {
+            for len in 0..payload.len() {
+                let e = Request::decode(&payload[..len]).unwrap_err();
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            }
+        }
+        for payload in responses().iter().map(Response::encode) {
+            for len in 0..payload.len() {
+                let e = Response::decode(&payload[..len]).unwrap_err();
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_and_unknown_tags_are_rejected() {
+        let mut payload = Request::Get { handle: 1 }.encode();
+        payload.push(0);
+        assert!(Request::decode(&payload).is_err());
+        assert!(Request::decode(&[7]).is_err());
+        assert!(Response::decode(&[7]).is_err());
+    }
+
+    #[test]
+    fn lengths_larger_than_the_payload_are_rejected() {
+        // a list of u32::MAX chips in a few bytes must not allocate them
+        let mut payload = vec![1];
+        payload.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Response::decode(&payload).is_err());
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"one").unwrap();
+        write_frame(&mut buf, b"").unwrap();
+        let mut r = &buf[..];
+        assert_eq!(read_frame(&mut r).unwrap().unwrap(), b"one");
+        assert_eq!(read_frame(&mut r).unwrap().unwrap(), b"");
+        assert!(read_frame(&mut r).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"payload").unwrap();
+        // a partial length is the end of the stream only if it is empty
+        for len in 1..buf.len() {
+            let e = read_frame(&mut &buf[..len]).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let len = (MAX_FRAME_LEN + 1).to_le_bytes();
+        let e = read_frame(&mut &len[..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,291 @@
+//! Playing tones on a buzzer wired to an output.
+//!
+//! Passive buzzers and small speakers sound at the frequency of the square
+//! wave driving them. A [`Buzzer`] toggles an output line from its own
+//! thread to play sequences of [`Note`]s in the background: [`Buzzer::play`]
+//! returns immediately, [`Buzzer::wait`] waits for the end of the sequence.
+//!
+//! The wave is generated in software, the half periods are timed against
+//! absolute deadlines so the pitch does not drift, but the jitter of the
+//! scheduler makes high frequencies rough. A few kHz at most suit it.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use std::time::Duration;
+//! # use gpio_cdev_async::{buzzer::{Buzzer, Note}, chip::Chip, line::LineRequest};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! # let chip = Chip::new("/dev/gpiochip0")?;
+//! # let request = LineRequest::builder().set_offsets([12u32]).build()?;
+//! let buzzer = Buzzer::new(chip.get_line(request)?, 12)?;
+//! let beat = Duration::from_millis(200);
+//! buzzer.play([
+//!     Note::pitch("C5", beat).unwrap(),
+//!     Note::pitch("E5", beat).unwrap(),
+//!     Note::rest(beat),
+//!     Note::pitch("G5", beat * 2).unwrap(),
+//! ]);
+//! buzzer.wait();
+//! // an alert while doing something else
+//! buzzer.play([Note::new(2000.0, Duration::from_millis(100))]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - The line is driven low between notes.
+//! - v1 handles write all their lines, the other lines of the handle are driven low.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    line::{index_of_offset, LineHandle},
+    worker::{self, Worker},
+    Result,
+};
+
+/// A tone of a frequency played for a duration, see [`Buzzer::play`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    /// The frequency in Hz, silence if not positive or too low for its
+    /// period to be timed.
+    pub frequency: f32,
+    pub duration: Duration,
+}
+
+impl Note {
+    pub fn new(frequency: f32, duration: Duration) -> Self {
+        Self {
+            frequency,
+            duration,
+        }
+    }
+
+    /// Silence for `duration`.
+    pub fn rest(duration: Duration) -> Self {
+        Self::new(0.0, duration)
+    }
+
+    /// The note of scientific pitch notation `name`, e.g. `"A4"` (440 Hz),
+    /// `"C#5"` or `"Bb3"`, in equal temperament. `None` if `name` is not a
+    /// note.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use gpio_cdev_async::buzzer::Note;
+    /// let a4 = Note::pitch("A4", Duration::from_millis(100)).unwrap();
+    /// assert_eq!(a4.frequency, 440.0);
+    /// let c5 = Note::pitch("C5", Duration::from_millis(100)).unwrap();
+    /// assert!((c5.frequency - 523.25).abs() < 0.01);
+    /// assert!(Note::pitch("H2", Duration::from_millis(100)).is_none());
+    /// ```
+    pub fn pitch(name: &str, duration: Duration) -> Option<Self> {
+        let mut chars = name.chars();
+        let semitone: i32 = match chars.next()?.to_ascii_uppercase() {
+            'C' => -9,
+            'D' => -7,
+            'E' => -5,
+            'F' => -4,
+            'G' => -2,
+            'A' => 0,
+            'B' => 2,
+            _ => return None,
+        };
+        let rest = chars.as_str();
+        let (semitone, octave) = match rest.strip_prefix('#') {
+            Some(octave) => (semitone + 1, octave),
+            None => match rest.strip_prefix('b') {
+                Some(octave) => (semitone - 1, octave),
+                None => (semitone, rest),
+            },
+        };
+        let octave: i32 = octave.parse().ok().filter(|octave| (0..=9).contains(octave))?;
+        let from_a4 = semitone + (octave - 4) * 12;
+        let frequency = 440.0 * 2f32.powf(from_a4 as f32 / 12.0);
+        Some(Self::new(frequency, duration))
+    }
+}
+
+/// Plays notes on an output line, see the [module documentation](self).
+///
+/// The thread is stopped when the buzzer is dropped.
+#[derive(Debug)]
+pub struct Buzzer {
+    shared: Arc<Shared>,
+    worker: Worker,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when notes are queued or the buzzer stops.
+    wakeup: Condvar,
+    /// Notified when the queue runs out.
+    done: Condvar,
+    /// Bumped to cut the current note short.
+    generation: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    notes: VecDeque<Note>,
+    playing: bool,
+    stop: bool,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Buzzer {
+    /// Moves `handle` to a thread playing notes on the line `offset`.
+    pub fn new(handle: LineHandle, offset: u32) -> Result<Self> {
+        let Some(index) = index_of_offset(handle.offsets(), offset) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the handle does not hold the buzzer line",
+            )
+            .into());
+        };
+        let shared = Arc::new(Shared::default());
+        let worker = Worker::spawn(
+            {
+                let shared = shared.clone();
+                move || run(&handle, 1 << index, &shared)
+            },
+            {
+                let shared = shared.clone();
+                move || {
+                    shared.lock().stop = true;
+                    shared.generation.fetch_add(1, Ordering::Relaxed);
+                    shared.wakeup.notify_one();
+                }
+            },
+        );
+        Ok(Self { shared, worker })
+    }
+
+    /// Plays `notes` in the background, instead of the notes still playing.
+    pub fn play(&self, notes: impl IntoIterator<Item = Note>) {
+        let mut state = self.shared.lock();
+        state.notes = notes.into_iter().collect();
+        state.playing |= !state.notes.is_empty();
+        self.shared.generation.fetch_add(1, Ordering::Relaxed);
+        self.shared.wakeup.notify_one();
+    }
+
+    /// Plays `notes` after the notes still playing.
+    pub fn queue(&self, notes: impl IntoIterator<Item = Note>) {
+        let mut state = self.shared.lock();
+        state.notes.extend(notes);
+        state.playing |= !state.notes.is_empty();
+        self.shared.wakeup.notify_one();
+    }
+
+    /// Cuts the notes playing short.
+    pub fn silence(&self) {
+        self.play([]);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.shared.lock().playing
+    }
+
+    /// Blocks until the notes playing are over.
+    ///
+    /// Returns at once if the thread stopped on an error, see
+    /// [`Buzzer::close`].
+    pub fn wait(&self) {
+        let mut state = self.shared.lock();
+        while state.playing && !state.stop {
+            state = self.shared.done.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Stops the thread and returns its result, e.g. a failed write.
+    pub fn close(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+fn run(handle: &LineHandle, mask: u64, shared: &Shared) -> Result<()> {
+    let result = play_notes(handle, mask, shared);
+    // wake up the waiters, also when stopping on an error
+    let mut state = shared.lock();
+    state.stop = true;
+    state.playing = false;
+    shared.done.notify_all();
+    result
+}
+
+fn play_notes(handle: &LineHandle, mask: u64, shared: &Shared) -> Result<()> {
+    let write_mask = worker::write_mask(handle, mask);
+    let write = |high: bool| handle.backend.set_values(write_mask, if high { mask } else { 0 });
+
+    loop {
+        let mut state = shared.lock();
+        let note = loop {
+            if state.stop {
+                return write(false);
+            }
+            match state.notes.pop_front() {
+                Some(note) => break note,
+                None => {
+                    if std::mem::take(&mut state.playing) {
+                        shared.done.notify_all();
+                    }
+                    state = shared.wakeup.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+            }
+        };
+        let generation = shared.generation.load(Ordering::Relaxed);
+        drop(state);
+
+        let start = Instant::now();
+        let end = start + note.duration;
+        let cut = || shared.generation.load(Ordering::Relaxed) != generation;
+        // a period too long for a duration is a rest
+        let half = match note.frequency > 0.0 {
+            true => Duration::try_from_secs_f32(0.5 / note.frequency).ok(),
+            false => None,
+        };
+        if let Some(half) = half {
+            let mut high = false;
+            let mut edge = start;
+            while edge < end && !cut() {
+                high = !high;
+                write(high)?;
+                // from the previous deadline, so the pitch does not drift
+                edge += half;
+                if let Some(wait) = edge.min(end).checked_duration_since(Instant::now()) {
+                    std::thread::sleep(wait);
+                }
+            }
+            write(false)?;
+        } else {
+            let mut state = shared.lock();
+            while !cut() {
+                let Some(wait) = end.checked_duration_since(Instant::now()) else {
+                    break;
+                };
+                state = shared
+                    .wakeup
+                    .wait_timeout(state, wait)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! C ABI of the safe layer, see `include/gpio_cdev_async.h`.
+//!
+//! Build the shared library with:
+//! ```sh
+//! cargo rustc --release --features capi --crate-type cdylib
+//! ```
+//!
+//! # Notes
+//! - All functions returning `int` return `0` (or a count) on success and a
+//!   negative `errno` on failure.
+//! - `flags` are `GPIOHANDLE_REQUEST_*` flags with the v1 feature and
+//!   `GPIO_V2_LINE_FLAG_*` flags with the v2 feature.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::ffi::{c_char, c_int, CStr};
+
+use crate::{
+    chip::Chip,
+    config::Edge,
+    event::LineEvent,
+    line::{HandleFlags, LineHandle, LineRequest},
+    Error,
+};
+
+/// Request rising edge events in [`gpio_chip_request_event_line`].
+pub const GPIO_CDEV_EDGE_RISING: u32 = 1 << 0;
+/// Request falling edge events in [`gpio_chip_request_event_line`].
+pub const GPIO_CDEV_EDGE_FALLING: u32 = 1 << 1;
+
+/// An edge event, filled by [`gpio_line_wait_event`].
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct gpio_line_event {
+    /// best estimate of time of event occurrence, in nanoseconds.
+    pub timestamp_ns: u64,
+    /// `1` for a rising edge, `2` for a falling edge.
+    pub event_type: u32,
+    /// the offset of the line that triggered the event.
+    pub offset: u32,
+}
+
+fn errno(e: Error) -> c_int {
+    -e.raw_os_error().unwrap_or(libc::EIO)
+}
+
+unsafe fn consumer<'a>(consumer: *const c_char) -> std::borrow::Cow<'a, str> {
+    if consumer.is_null() {
+        Default::default()
+    } else {
+        unsafe { CStr::from_ptr(consumer) }.to_string_lossy()
+    }
+}
+
+/// Opens the GPIO chip at `path`, storing it in `*chip` on success.
+///
+/// # Safety
+/// `path` must be a valid NUL-terminated string and `chip` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_chip_open(path: *const c_char, chip: *mut *mut Chip) -> c_int {
+    if path.is_null() || chip.is_null() {
+        return -libc::EINVAL;
+    }
+    let path = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+    match Chip::new(path.as_ref()) {
+        Ok(c) => {
+            unsafe { *chip = Box::into_raw(Box::new(c)) };
+            0
+        }
+        Err(e) => errno(e),
+    }
+}
+
+/// Closes a chip opened by [`gpio_chip_open`], line handles stay valid.
+///
+/// # Safety
+/// `chip` must be null or returned by [`gpio_chip_open`] and not closed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_chip_close(chip: *mut Chip) {
+    if !chip.is_null() {
+        drop(unsafe { Box::from_raw(chip) });
+    }
+}
+
+/// Requests `num_lines` lines, storing the handle in `*handle` on success.
+///
+/// `default_values` may be null, otherwise it holds `num_lines` output values.
+///
+/// # Safety
+/// `chip` must be an open chip, `offsets` (and `default_values` if not null)
+/// must point to `num_lines` elements, `consumer` must be null or a valid
+/// NUL-terminated string and `handle` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_chip_request_lines(
+    chip: *const Chip,
+    offsets: *const u32,
+    default_values: *const u8,
+    num_lines: usize,
+    flags: u64,
+    consumer: *const c_char,
+    handle: *mut *mut LineHandle,
+) -> c_int {
+    if chip.is_null() || offsets.is_null() || handle.is_null() || num_lines == 0 {
+        return -libc::EINVAL;
+    }
+    let chip = unsafe { &*chip };
+    let offsets = unsafe { std::slice::from_raw_parts(offsets, num_lines) };
+    let default_values = (!default_values.is_null())
+        .then(|| unsafe { std::slice::from_raw_parts(default_values, num_lines) });
+
+    let lines = offsets.iter().enumerate().map(|(index, &offset)| {
+        (offset, default_values.map(|values| values[index]))
+    });
+    let request = LineRequest::builder()
+        .set_flags(HandleFlags::from_bits_retain(flags as _))
+        .set_consumer(unsafe { self::consumer(consumer) })
+        .set_offsets_with_values(lines)
+        .build();
+
+    match request.and_then(|request| chip.get_line(request)) {
+        Ok(h) => {
+            unsafe { *handle = Box::into_raw(Box::new(h)) };
+            0
+        }
+        Err(e) => errno(e),
+    }
+}
+
+/// Requests edge events on the line at `offset`, storing the handle in `*handle` on success.
+///
+/// `event_flags` is a non-empty combination of [`GPIO_CDEV_EDGE_RISING`] and
+/// [`GPIO_CDEV_EDGE_FALLING`].
+///
+/// # Safety
+/// `chip` must be an open chip, `consumer` must be null or a valid
+/// NUL-terminated string and `handle` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_chip_request_event_line(
+    chip: *const Chip,
+    offset: u32,
+    flags: u64,
+    event_flags: u32,
+    consumer: *const c_char,
+    handle: *mut *mut LineHandle,
+) -> c_int {
+    if chip.is_null() || handle.is_null() {
+        return -libc::EINVAL;
+    }
+    let Some(edges) = Edge::from_bits(event_flags) else {
+        return -libc::EINVAL;
+    };
+    let chip = unsafe { &*chip };
+    let consumer = unsafe { self::consumer(consumer) };
+
+    match chip.request_edge_events(
+        offset,
+        edges,
+        HandleFlags::from_bits_retain(flags as _),
+        consumer,
+    ) {
+        Ok(h) => {
+            unsafe { *handle = Box::into_raw(Box::new(h)) };
+            0
+        }
+        Err(e) => errno(e),
+    }
+}
+
+/// Releases the lines of a handle.
+///
+/// # Safety
+/// `handle` must be null or returned by a request function and not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_line_release(handle: *mut LineHandle) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Returns the number of lines of a handle, `0` if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a valid handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_line_num_lines(handle: *const LineHandle) -> usize {
+    if handle.is_null() {
+        return 0;
+    }
+    unsafe { &*handle }.offsets().len()
+}
+
+/// Reads the values of the lines of a handle into `values`, in request order.
+///
+/// Returns the number of values written, at most `num_values`.
+///
+/// # Safety
+/// `handle` must be a valid handle and `values` must point to `num_values` elements.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_line_get_values(
+    handle: *const LineHandle,
+    values: *mut u8,
+    num_values: usize,
+) -> c_int {
+    if handle.is_null() || values.is_null() {
+        return -libc::EINVAL;
+    }
+    let handle = unsafe { &*handle };
+    let values = unsafe { std::slice::from_raw_parts_mut(values, num_values) };
+
+    match handle.get_values() {
+        Ok(line_values) => {
+            let mut n = 0;
+            for (value, item) in values.iter_mut().zip(line_values.values_iter()) {
+                *value = item.value;
+                n += 1;
+            }
+            n
+        }
+        Err(e) => errno(e),
+    }
+}
+
+/// Sets the values of the first `num_values` lines of a handle, in request order.
+///
+/// # Notes
+/// - v1: lines past `num_values` are set to `0`, v2: they keep their values.
+///
+/// # Safety
+/// `handle` must be a valid handle and `values` must point to `num_values` elements.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_line_set_values(
+    handle: *const LineHandle,
+    values: *const u8,
+    num_values: usize,
+) -> c_int {
+    if handle.is_null() || values.is_null() {
+        return -libc::EINVAL;
+    }
+    let handle = unsafe { &*handle };
+    let values = unsafe { std::slice::from_raw_parts(values, num_values) };
+
+    match handle.set_values_in_order(values) {
+        Ok(()) => 0,
+        Err(e) => errno(e),
+    }
+}
+
+/// Blocks until an edge event is available on a handle and stores it in `*event`.
+///
+/// # Safety
+/// `handle` must be a valid handle and `event` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gpio_line_wait_event(
+    handle: *const LineHandle,
+    event: *mut gpio_line_event,
+) -> c_int {
+    if handle.is_null() || event.is_null() {
+        return -libc::EINVAL;
+    }
+    let handle = unsafe { &*handle };
+    let mut buf = [LineEvent::default()];
+
+    match LineEvent::read(handle, &mut buf) {
+        Ok(0) => -libc::EIO,
+        Ok(_) => {
+            let [e] = buf;
+            #[cfg(feature = "v1")]
+            let offset = handle.offsets()[0];
+            #[cfg(feature = "v2")]
+            let offset = e.offset();
+            let event_type = match e.event_type() {
+                Ok(event_type) => event_type,
+                Err(e) => return errno(e),
+            };
+            unsafe {
+                *event = gpio_line_event {
+                    timestamp_ns: e.timestamp_ns() as _,
+                    event_type: event_type as u32,
+                    offset,
+                }
+            };
+            0
+        }
+        Err(e) => errno(e),
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! # Examples
 //! ```rust,no_run
-//! # use gpio_cdev_async::Chip;
+//! # use gpio_cdev_async::chip::Chip;
 //! let chip = Chip::new("/dev/gpiochip0").unwrap();
 //! let chip_info = chip.get_chipinfo().unwrap();
 //!
@@ -14,21 +14,27 @@
 use std::{
     borrow::Cow,
     fmt::Debug,
-    fs::File,
-    os::fd::AsRawFd,
     path::{Path, PathBuf},
 };
 
 use crate::{
-    ffi,
-    line::{LineHandle, LineInfo, LineRequest, PinHandle, PinRequest},
+    backend::{cdev::Cdev, GpioBackend},
+    config::Edge,
+    event::LineInfoChangeIter,
+    ffi::{self, common::CString},
+    line::{HandleFlags, LineHandle, LineInfo, LineRequest, PinHandle, PinRequest},
     Result,
 };
 
+#[cfg(feature = "v1")]
+use crate::line::EventRequest;
+#[cfg(doc)]
+use crate::{event::LineInfoChangedEvent, Error};
+
 /// Represents a GPIO chip.
 #[derive(Debug)]
 pub struct Chip {
-    pub(crate) file: File,
+    pub(crate) backend: Box<dyn GpioBackend>,
     path: PathBuf,
 }
 
@@ -37,20 +43,81 @@ impl Chip {
     ///
     /// # Examples
     /// ```rust,no_run
-    /// # use gpio_cdev_async::Chip;
+    /// # use gpio_cdev_async::chip::Chip;
     /// let _chip = Chip::new("/dev/gpiochip0").unwrap();
     /// ```
     ///
     /// # Notes
     /// - This function does not check if the path is a valid GPIO chip.
+    /// - The chip and the requested lines are closed on exec, see [`Chip::open_with`].
     pub fn new<P>(path: P) -> Result<Self>
     where
         P: AsRef<Path>,
     {
-        let file = File::open(path.as_ref())?;
-        Ok(Self {
-            file,
+        Self::open_with(path, &OpenOptions::new())
+    }
+
+    /// Opens a GPIO chip at the specified path with `options`.
+    ///
+    /// The options also apply to the fds of the lines requested from the chip.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # use gpio_cdev_async::chip::{Chip, OpenOptions};
+    /// // a child process spawned later keeps the chip and its lines open
+    /// let options = OpenOptions::new().set_cloexec(false);
+    /// let _chip = Chip::open_with("/dev/gpiochip0", &options).unwrap();
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display(), ?options))
+    )]
+    pub fn open_with<P>(path: P, options: &OpenOptions) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::from_backend(
+            path.as_ref(),
+            Cdev::open_with(path.as_ref(), options)?,
+        ))
+    }
+
+    /// Uses `backend` instead of a kernel character device, `path` only
+    /// identifies the chip.
+    ///
+    /// See [`backend`](crate::backend) for an example.
+    pub fn from_backend(path: impl AsRef<Path>, backend: impl GpioBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
             path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns another handle to the same chip, e.g. to watch line info
+    /// changes on one thread while requesting lines on another.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # use gpio_cdev_async::chip::Chip;
+    /// let chip = Chip::new("/dev/gpiochip0").unwrap();
+    /// let watcher = chip.try_clone().unwrap();
+    /// std::thread::spawn(move || {
+    ///     watcher.get_lineinfo_watch(6).unwrap();
+    ///     for event in watcher.lineinfo_changes() {
+    ///         println!("{:?}", event.unwrap());
+    ///     }
+    /// });
+    /// let _line_info = chip.get_lineinfo(6).unwrap();
+    /// ```
+    ///
+    /// # Notes
+    /// - A kernel chip is duplicated with `dup(2)`: both handles share the
+    ///   watched lines and the queue of info changes.
+    /// - Fails with [`std::io::ErrorKind::Unsupported`] if the backend cannot be cloned.
+    pub fn try_clone(&self) -> Result<Self> {
+        Ok(Self {
+            backend: self.backend.try_clone()?,
+            path: self.path.clone(),
         })
     }
 
@@ -64,9 +131,7 @@ impl Chip {
     /// # Notes
     /// - This function retrieves the chip information from the kernel every time it is called.
     pub fn get_chipinfo(&self) -> Result<ChipInfo> {
-        let mut inner: ffi::common::GpioChipInfo = unsafe { std::mem::zeroed() };
-        ffi::common::gpio_get_chipinfo_ioctl(self.file.as_raw_fd(), &mut inner)?;
-        Ok(ChipInfo { inner })
+        self.backend.chip_info()
     }
 
     /// Get the information of a GPIO line.
@@ -76,7 +141,7 @@ impl Chip {
     ///
     /// # Examples
     /// ```rust,no_run
-    /// # use gpio_cdev_async::Chip;
+    /// # use gpio_cdev_async::chip::Chip;
     /// let chip = Chip::new("/dev/gpiochip0").unwrap();
     ///
     /// // Get the information of `GPIO6`
@@ -85,22 +150,7 @@ impl Chip {
     /// # Notes
     /// - This function retrieves the chip information from the kernel every time it is called.
     pub fn get_lineinfo(&self, offset: u32) -> Result<LineInfo> {
-        #[cfg(feature = "v2")]
-        {
-            use ffi::v2::GpioV2LineInfo;
-            let mut inner: GpioV2LineInfo = unsafe { std::mem::zeroed() };
-            inner.offset = offset;
-            ffi::v2::gpio_v2_get_lineinfo_ioctl(self.file.as_raw_fd(), &mut inner)?;
-            Ok(LineInfo { inner })
-        }
-        #[cfg(feature = "v1")]
-        {
-            use ffi::v1::GpioLineInfo;
-            let mut inner: GpioLineInfo = unsafe { std::mem::zeroed() };
-            inner.line_offset = offset;
-            ffi::v1::gpio_get_lineinfo_ioctl(self.file.as_raw_fd(), &mut inner)?;
-            Ok(LineInfo { inner })
-        }
+        self.backend.line_info(offset)
     }
 
     /// Get a GPIO line handle.
@@ -117,40 +167,175 @@ impl Chip {
         request.request(self)
     }
 
-    pub fn get_lineinfo_watch(&self, offset: u32) -> Result<LineInfo> {
-        #[cfg(feature = "v2")]
+    /// Get a GPIO line handle with edge events enabled.
+    ///
+    /// See [`EventRequest`] for more information.
+    #[cfg(feature = "v1")]
+    pub fn get_event_line(&self, request: EventRequest) -> Result<LineHandle> {
+        request.request(self)
+    }
+
+    /// Get a handle of the line at `offset` with `edges` events enabled,
+    /// whatever the uAPI version.
+    ///
+    /// `flags` are the other request flags, e.g. the bias of the line.
+    ///
+    /// - v1: requests an [`EventRequest`] of `edges`.
+    /// - v2: requests a [`LineRequest`] with the edge flags of `edges` added
+    ///   to `flags`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # use gpio_cdev_async::{chip::Chip, config::Edge, line::HandleFlags};
+    /// let chip = Chip::new("/dev/gpiochip0").unwrap();
+    /// let button = chip
+    ///     .request_edge_events(6, Edge::Both, HandleFlags::empty(), "button")
+    ///     .unwrap();
+    /// for event in button.events() {
+    ///     println!("{:?}", event.unwrap());
+    /// }
+    /// ```
+    pub fn request_edge_events(
+        &self,
+        offset: u32,
+        edges: Edge,
+        flags: HandleFlags,
+        consumer: impl AsRef<str>,
+    ) -> Result<LineHandle> {
+        #[cfg(feature = "v1")]
         {
-            use ffi::v2::GpioV2LineInfo;
-            let mut inner: GpioV2LineInfo = unsafe { std::mem::zeroed() };
-            inner.offset = offset;
-            ffi::v2::gpio_v2_get_lineinfo_watch_ioctl(self.file.as_raw_fd(), &mut inner)?;
-            Ok(LineInfo { inner })
+            self.get_event_line(EventRequest::new(
+                offset,
+                flags,
+                edges.event_flags(),
+                consumer,
+            ))
         }
-        #[cfg(feature = "v1")]
+        #[cfg(feature = "v2")]
         {
-            use ffi::v1::GpioLineInfo;
-            let mut inner: GpioLineInfo = unsafe { std::mem::zeroed() };
-            inner.line_offset = offset;
-            ffi::v1::gpio_get_lineinfo_watch_ioctl(self.file.as_raw_fd(), &mut inner)?;
-            Ok(LineInfo { inner })
+            let request = LineRequest::builder()
+                .set_flags(flags | edges.flags())
+                .set_consumer(consumer)
+                .set_offsets([offset])
+                .build()?;
+            self.get_line(request)
         }
     }
 
-    pub fn get_lineinfo_unwatch(&self, mut offset: u32) -> Result<()> {
-        ffi::common::gpio_get_lineinfo_unwatch_ioctl(self.file.as_raw_fd(), &mut offset)?;
-        Ok(())
+    /// Start watching a GPIO line for changes to its information.
+    ///
+    /// Returns the current information of the line, subsequent changes
+    /// can be read with [`Chip::lineinfo_changes`] or [`LineInfoChangedEvent::read`](crate::event::LineInfoChangedEvent::read).
+    ///
+    /// # Arguments
+    /// - `offset`: The offset of the GPIO line.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # use gpio_cdev_async::chip::Chip;
+    /// let chip = Chip::new("/dev/gpiochip0").unwrap();
+    ///
+    /// let _line_info = chip.get_lineinfo_watch(6).unwrap();
+    /// for event in chip.lineinfo_changes() {
+    ///     println!("{:?}", event.unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Notes
+    /// - Watching a line that is already watched fails with `EBUSY`.
+    pub fn get_lineinfo_watch(&self, offset: u32) -> Result<LineInfo> {
+        self.backend.watch_line_info(offset)
+    }
+
+    /// Stop watching a GPIO line previously watched with [`Chip::get_lineinfo_watch`].
+    ///
+    /// # Arguments
+    /// - `offset`: The offset of the GPIO line.
+    pub fn get_lineinfo_unwatch(&self, offset: u32) -> Result<()> {
+        self.backend.unwatch_line_info(offset)
+    }
+
+    /// Returns a blocking iterator over the info changes of the watched lines.
+    ///
+    /// On a nonblocking chip it yields a would-block error instead, see
+    /// [`OpenOptions::set_nonblocking`].
+    pub fn lineinfo_changes(&self) -> LineInfoChangeIter<'_> {
+        LineInfoChangeIter::new(self)
     }
 
     // pub fn
 }
 
+/// Options for opening a chip with [`Chip::open_with`].
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    pub(crate) cloexec: bool,
+    pub(crate) nonblocking: bool,
+    pub(crate) custom_flags: i32,
+}
+
+impl OpenOptions {
+    /// The options of [`Chip::new`]: the chip and its lines are closed on exec.
+    pub fn new() -> Self {
+        Self {
+            cloexec: true,
+            nonblocking: false,
+            custom_flags: 0,
+        }
+    }
+
+    /// Whether the fds of the chip and of its requested lines are closed on
+    /// exec. With `false` they are inherited by spawned processes.
+    pub fn set_cloexec(mut self, cloexec: bool) -> Self {
+        self.cloexec = cloexec;
+        self
+    }
+
+    /// Whether the chip is opened with `O_NONBLOCK`, for event loops polling
+    /// the chip.
+    ///
+    /// Reading info changes, e.g. with [`LineInfoChangedEvent::read`], then
+    /// fails with an error whose [`Error::is_would_block`] is `true` instead
+    /// of blocking when none is pending.
+    pub fn set_nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /// Extra `open(2)` flags for the chip, e.g. `libc::O_NOFOLLOW`.
+    ///
+    /// The access mode is always read-only, and `O_CLOEXEC` and `O_NONBLOCK`
+    /// are controlled by [`OpenOptions::set_cloexec`] and
+    /// [`OpenOptions::set_nonblocking`].
+    pub fn set_custom_flags(mut self, flags: i32) -> Self {
+        self.custom_flags = flags & !(libc::O_ACCMODE | libc::O_NONBLOCK);
+        self
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Represents the information of a GPIO chip.
 #[repr(transparent)]
 pub struct ChipInfo {
-    inner: ffi::common::GpioChipInfo,
+    pub(crate) inner: ffi::common::GpioChipInfo,
 }
 
 impl ChipInfo {
+    /// Information for a chip of a [`GpioBackend`], `name` and `label` are
+    /// truncated to 31 bytes like the kernel's.
+    pub fn new(name: impl AsRef<str>, label: impl AsRef<str>, lines: u32) -> Self {
+        let mut inner: ffi::common::GpioChipInfo = unsafe { std::mem::zeroed() };
+        inner.name = CString::truncated(name.as_ref());
+        inner.label = CString::truncated(label.as_ref());
+        inner.lines = lines;
+        Self { inner }
+    }
+
     /// The name of the GPIO chip.
     pub fn name(&self) -> Cow<'_, str> {
         self.inner.name.to_string_lossy()
@@ -0,0 +1,186 @@
+//! Driving outputs to safe states when the application panics.
+//!
+//! A panic in the middle of a control loop leaves the outputs as they were,
+//! e.g. a heater switched on. [`on_unwind`] registers the safe state of a
+//! handle's lines; a panic hook installed with the first registration drives
+//! every registered handle to its safe state before the panic unwinds or
+//! the process aborts, so it also works with `panic = "abort"`.
+//!
+//! [`GuardedOutput`] combines the registration with driving the safe state
+//! when the output is dropped.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use std::sync::Arc;
+//! # use gpio_cdev_async::{chip::Chip, cleanup, line::LineRequest};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! # let chip = Chip::new("/dev/gpiochip0")?;
+//! let request = LineRequest::builder().set_offsets([17u32]).build()?;
+//! let heater = Arc::new(chip.get_line(request)?);
+//! // the heater is switched off if anything panics while `_cleanup` lives
+//! let _cleanup = cleanup::on_unwind(heater.clone(), [(17, 0)]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - The hook runs on every panic, including panics that are caught later.
+//! - v1 handles write all their lines, lines without a safe state are driven low.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex, Once},
+};
+
+use crate::{
+    line::{index_of_offset, LineHandle},
+    Result,
+};
+
+struct Entry {
+    id: u64,
+    handle: Arc<LineHandle>,
+    mask: u64,
+    bits: u64,
+}
+
+struct Registry {
+    next_id: u64,
+    entries: Vec<Entry>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    next_id: 0,
+    entries: Vec::new(),
+});
+static HOOK: Once = Once::new();
+
+/// Registers `safe_state`, pairs of offset and value, to be written to
+/// `handle` when any thread panics, until the returned [`Registration`] is
+/// dropped.
+///
+/// Offsets the handle does not hold are ignored.
+pub fn on_unwind(
+    handle: Arc<LineHandle>,
+    safe_state: impl IntoIterator<Item = (u32, u8)>,
+) -> Registration {
+    let (mask, bits) = state_bits(&handle, safe_state);
+    register(handle, mask, bits)
+}
+
+fn register(handle: Arc<LineHandle>, mask: u64, bits: u64) -> Registration {
+    HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            drive_safe_states();
+            previous(info);
+        }));
+    });
+
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.entries.push(Entry {
+        id,
+        handle,
+        mask,
+        bits,
+    });
+    Registration { id }
+}
+
+/// Drives every registered handle to its safe state now, e.g. from a
+/// shutdown path, ignoring the errors.
+pub fn drive_safe_states() {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    for entry in &registry.entries {
+        let _ = write(&entry.handle, entry.mask, entry.bits);
+    }
+}
+
+/// The mask and values of `state` on the lines of `handle`, by index.
+fn state_bits(handle: &LineHandle, state: impl IntoIterator<Item = (u32, u8)>) -> (u64, u64) {
+    let mut mask = 0;
+    let mut bits = 0;
+    for (offset, value) in state {
+        if let Some(index) = index_of_offset(handle.offsets(), offset) {
+            mask |= 1 << index;
+            if value != 0 {
+                bits |= 1 << index;
+            }
+        }
+    }
+    (mask, bits)
+}
+
+fn write(handle: &LineHandle, mask: u64, bits: u64) -> Result<()> {
+    #[cfg(feature = "v1")]
+    let mask = {
+        let _ = mask;
+        handle.all_mask()
+    };
+    handle.backend.set_values(mask, bits)
+}
+
+/// Keeps a safe state registered with [`on_unwind`], unregisters it when
+/// dropped.
+#[derive(Debug)]
+#[must_use = "the safe state is unregistered when the registration is dropped"]
+pub struct Registration {
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        registry.entries.retain(|entry| entry.id != self.id);
+    }
+}
+
+/// An output handle that is driven to its safe state when it is dropped and
+/// when any thread panics, see the [module documentation](self).
+///
+/// Dereferences to the [`LineHandle`].
+#[derive(Debug)]
+pub struct GuardedOutput {
+    handle: Arc<LineHandle>,
+    mask: u64,
+    bits: u64,
+    _registration: Registration,
+}
+
+impl GuardedOutput {
+    /// Guards `handle` with `safe_state`, pairs of offset and value.
+    pub fn new(handle: LineHandle, safe_state: impl IntoIterator<Item = (u32, u8)>) -> Self {
+        let handle = Arc::new(handle);
+        let (mask, bits) = state_bits(&handle, safe_state);
+        let registration = register(handle.clone(), mask, bits);
+        Self {
+            handle,
+            mask,
+            bits,
+            _registration: registration,
+        }
+    }
+
+    /// Drives the lines to their safe state now.
+    pub fn make_safe(&self) -> Result<()> {
+        write(&self.handle, self.mask, self.bits)
+    }
+}
+
+impl Deref for GuardedOutput {
+    type Target = LineHandle;
+
+    fn deref(&self) -> &LineHandle {
+        &self.handle
+    }
+}
+
+impl Drop for GuardedOutput {
+    fn drop(&mut self) {
+        let _ = self.make_safe();
+    }
+}
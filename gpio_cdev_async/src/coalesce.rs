@@ -0,0 +1,105 @@
+//! Merging the value changes of a tick into a single write.
+//!
+//! Code updating many channels of a handle one by one, e.g. every
+//! period of a software PWM, pays one ioctl per channel with
+//! [`LineHandle::set_values`]. A [`CoalescingWriter`] collects the changes
+//! instead and [`CoalescingWriter::flush`] writes them with one ioctl, or
+//! none if no line ends up changing.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{chip::Chip, coalesce::CoalescingWriter, line::LineRequest};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! # let chip = Chip::new("/dev/gpiochip0")?;
+//! let request = LineRequest::builder().set_offsets([1u32, 2, 3]).build().unwrap();
+//! let handle = chip.get_line(request)?;
+//! let mut writer = CoalescingWriter::new(&handle)?;
+//! for tick in 0..100u32 {
+//!     writer.set(1, (tick % 2) as u8);
+//!     writer.set(2, (tick % 3 == 0) as u8);
+//!     writer.set(3, 1);
+//!     writer.flush()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    line::{index_of_offset, LineHandle},
+    Result,
+};
+
+/// Collects value changes of a [`LineHandle`] and writes them at once.
+///
+/// The writer keeps the values it last wrote, read from the handle when it is
+/// created, so it must be the only writer of the handle: on v1 every flush
+/// writes all the lines.
+#[derive(Debug)]
+pub struct CoalescingWriter<'a> {
+    handle: &'a LineHandle,
+    /// The values of the lines as last written, by index.
+    written: u64,
+    /// The values to write on the next flush, by index.
+    pending: u64,
+}
+
+impl<'a> CoalescingWriter<'a> {
+    /// A writer of `handle`, starting from its current values.
+    pub fn new(handle: &'a LineHandle) -> Result<Self> {
+        let written = handle.backend.get_values(handle.all_mask())?;
+        Ok(Self {
+            handle,
+            written,
+            pending: written,
+        })
+    }
+
+    pub fn handle(&self) -> &'a LineHandle {
+        self.handle
+    }
+
+    /// Sets the line at `offset` to `value` on the next flush, later changes
+    /// of the same line within the tick win.
+    ///
+    /// Returns `false` if the handle does not hold `offset`.
+    pub fn set(&mut self, offset: u32, value: u8) -> bool {
+        let Some(index) = index_of_offset(self.handle.offsets(), offset) else {
+            return false;
+        };
+        let flag = 1 << index;
+        if value != 0 {
+            self.pending |= flag;
+        } else {
+            self.pending &= !flag;
+        }
+        true
+    }
+
+    /// The number of lines the next flush changes.
+    pub fn pending(&self) -> usize {
+        (self.pending ^ self.written).count_ones() as usize
+    }
+
+    /// Drops the changes since the last flush.
+    pub fn discard(&mut self) {
+        self.pending = self.written;
+    }
+
+    /// Writes the changes since the last flush with a single ioctl.
+    ///
+    /// Returns whether anything was written, nothing is if no line changes.
+    /// The changes are kept on error, so the next flush retries them.
+    pub fn flush(&mut self) -> Result<bool> {
+        let changed = self.pending ^ self.written;
+        if changed == 0 {
+            return Ok(false);
+        }
+        #[cfg(feature = "v1")]
+        let mask = self.handle.all_mask();
+        #[cfg(feature = "v2")]
+        let mask = changed;
+        self.handle.backend.set_values(mask, self.pending)?;
+        self.written = self.pending;
+        Ok(true)
+    }
+}
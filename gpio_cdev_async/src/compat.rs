@@ -0,0 +1,516 @@
+//! A migration layer mirroring the API of the
+//! [`gpio-cdev`](https://docs.rs/gpio-cdev/0.6) crate.
+//!
+//! Replacing `use gpio_cdev::...` with `use gpio_cdev_async::compat::...`
+//! keeps most call sites compiling, so projects can switch incrementally.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::compat::{Chip, EventRequestFlags, LineRequestFlags};
+//! let mut chip = Chip::new("/dev/gpiochip0").unwrap();
+//!
+//! let output = chip.get_line(4).unwrap();
+//! let handle = output.request(LineRequestFlags::OUTPUT, 1, "compat").unwrap();
+//! handle.set_value(0).unwrap();
+//!
+//! let input = chip.get_line(6).unwrap();
+//! for event in input
+//!     .events(LineRequestFlags::INPUT, EventRequestFlags::BOTH_EDGES, "compat")
+//!     .unwrap()
+//! {
+//!     println!("{:?}", event.unwrap());
+//! }
+//! ```
+//!
+//! # Notes
+//! - Errors are this crate's [`Error`](crate::Error), not `gpio_cdev::Error`.
+//! - The flags keep the values of `gpio-cdev` and are translated to the
+//!   v2 flags when the v2 feature is enabled.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    io,
+    os::fd::{AsRawFd, RawFd},
+    path::Path,
+    sync::Arc,
+};
+
+use bitflags::bitflags;
+
+use crate::{chip, config::Edge, event, line, permissions, Result};
+
+bitflags! {
+    /// Line request flags, see `gpio_cdev::LineRequestFlags`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct LineRequestFlags: u32 {
+        const INPUT       = 1 << 0;
+        const OUTPUT      = 1 << 1;
+        const ACTIVE_LOW  = 1 << 2;
+        const OPEN_DRAIN  = 1 << 3;
+        const OPEN_SOURCE = 1 << 4;
+    }
+}
+
+bitflags! {
+    /// Event request flags, see `gpio_cdev::EventRequestFlags`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct EventRequestFlags: u32 {
+        const RISING_EDGE  = 1 << 0;
+        const FALLING_EDGE = 1 << 1;
+        const BOTH_EDGES   = Self::RISING_EDGE.bits() | Self::FALLING_EDGE.bits();
+    }
+}
+
+impl LineRequestFlags {
+    fn to_handle_flags(self) -> line::HandleFlags {
+        #[cfg(feature = "v1")]
+        {
+            line::HandleFlags::from_bits_retain(self.bits())
+        }
+        #[cfg(feature = "v2")]
+        {
+            use line::HandleFlags as F;
+            [
+                (Self::INPUT, F::GPIO_V2_LINE_FLAG_INPUT),
+                (Self::OUTPUT, F::GPIO_V2_LINE_FLAG_OUTPUT),
+                (Self::ACTIVE_LOW, F::GPIO_V2_LINE_FLAG_ACTIVE_LOW),
+                (Self::OPEN_DRAIN, F::GPIO_V2_LINE_FLAG_OPEN_DRAIN),
+                (Self::OPEN_SOURCE, F::GPIO_V2_LINE_FLAG_OPEN_SOURCE),
+            ]
+            .into_iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .fold(F::empty(), |acc, (_, f)| acc | f)
+        }
+    }
+}
+
+/// Iterates over all the GPIO chips of the system in the order of their
+/// numbers, see `gpio_cdev::chips`.
+pub fn chips() -> Result<impl Iterator<Item = Result<Chip>>> {
+    let paths = permissions::chip_paths()?;
+    Ok(paths.into_iter().map(Chip::new))
+}
+
+/// A GPIO chip, see `gpio_cdev::Chip`.
+#[derive(Debug, Clone)]
+pub struct Chip {
+    inner: Arc<chip::Chip>,
+}
+
+impl Chip {
+    pub fn new<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self {
+            inner: Arc::new(chip::Chip::new(path)?),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    pub fn name(&self) -> Result<String> {
+        Ok(self.inner.get_chipinfo()?.name().into_owned())
+    }
+
+    pub fn label(&self) -> Result<String> {
+        Ok(self.inner.get_chipinfo()?.label().into_owned())
+    }
+
+    pub fn num_lines(&self) -> Result<u32> {
+        Ok(self.inner.get_chipinfo()?.lines())
+    }
+
+    pub fn get_line(&mut self, offset: u32) -> Result<Line> {
+        Ok(Line {
+            chip: self.inner.clone(),
+            offset,
+        })
+    }
+
+    pub fn get_lines(&mut self, offsets: &[u32]) -> Result<Lines> {
+        let lines = offsets
+            .iter()
+            .map(|&offset| self.get_line(offset))
+            .collect::<Result<_>>()?;
+        Ok(Lines { lines })
+    }
+
+    pub fn get_all_lines(&mut self) -> Result<Lines> {
+        let offsets: Vec<u32> = (0..self.num_lines()?).collect();
+        self.get_lines(&offsets)
+    }
+}
+
+/// A single line of a chip, see `gpio_cdev::Line`.
+#[derive(Debug, Clone)]
+pub struct Line {
+    chip: Arc<chip::Chip>,
+    offset: u32,
+}
+
+impl Line {
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn chip(&self) -> Chip {
+        Chip {
+            inner: self.chip.clone(),
+        }
+    }
+
+    pub fn info(&self) -> Result<LineInfo> {
+        let info = self.chip.get_lineinfo(self.offset)?;
+        let flags = info.flags();
+
+        use line::LineFlags as F;
+        // used, output, active low, open drain, open source
+        #[cfg(feature = "v1")]
+        let bits = [
+            F::KERNEL,
+            F::IS_OUT,
+            F::ACTIVE_LOW,
+            F::OPEN_DRAIN,
+            F::OPEN_SOURCE,
+        ];
+        #[cfg(feature = "v2")]
+        let bits = [
+            F::GPIO_V2_LINE_FLAG_USED,
+            F::GPIO_V2_LINE_FLAG_OUTPUT,
+            F::GPIO_V2_LINE_FLAG_ACTIVE_LOW,
+            F::GPIO_V2_LINE_FLAG_OPEN_DRAIN,
+            F::GPIO_V2_LINE_FLAG_OPEN_SOURCE,
+        ];
+        let [used, output, active_low, open_drain, open_source] = bits.map(|f| flags.contains(f));
+
+        let non_empty = |s: std::borrow::Cow<'_, str>| (!s.is_empty()).then(|| s.into_owned());
+        Ok(LineInfo {
+            line: self.clone(),
+            name: non_empty(info.name()),
+            consumer: non_empty(info.consumer()),
+            used,
+            output,
+            active_low,
+            open_drain,
+            open_source,
+        })
+    }
+
+    pub fn request(
+        &self,
+        flags: LineRequestFlags,
+        default: u8,
+        consumer: &str,
+    ) -> Result<LineHandle> {
+        let handle = request_lines(&self.chip, &[self.offset], flags, &[default], consumer)?;
+        Ok(LineHandle {
+            line: self.clone(),
+            flags,
+            fd: fd_of(&handle)?,
+            handle,
+        })
+    }
+
+    pub fn events(
+        &self,
+        handle_flags: LineRequestFlags,
+        event_flags: EventRequestFlags,
+        consumer: &str,
+    ) -> Result<LineEventHandle> {
+        let edges = Edge::from_bits(event_flags.bits()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no edge to request events of")
+        })?;
+        let handle = self.chip.request_edge_events(
+            self.offset,
+            edges,
+            handle_flags.to_handle_flags(),
+            consumer,
+        )?;
+        Ok(LineEventHandle {
+            line: self.clone(),
+            fd: fd_of(&handle)?,
+            handle,
+        })
+    }
+}
+
+fn request_lines(
+    chip: &chip::Chip,
+    offsets: &[u32],
+    flags: LineRequestFlags,
+    default: &[u8],
+    consumer: &str,
+) -> Result<line::LineHandle> {
+    // missing values are `0`, as in `gpio-cdev`
+    let lines = offsets.iter().enumerate().map(|(index, &offset)| {
+        (offset, Some(default.get(index).copied().unwrap_or_default()))
+    });
+    let request = line::LineRequest::builder()
+        .set_flags(flags.to_handle_flags())
+        .set_consumer(consumer)
+        .set_offsets_with_values(lines)
+        .build()?;
+    chip.get_line(request)
+}
+
+/// The fd of `handle` for [`AsRawFd`], only kernel handles have one.
+fn fd_of(handle: &line::LineHandle) -> Result<RawFd> {
+    handle
+        .fd()
+        .map(|fd| fd.as_raw_fd())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "the handle has no fd").into())
+}
+
+fn get_values(handle: &line::LineHandle) -> Result<Vec<u8>> {
+    Ok(handle
+        .get_values()?
+        .values_iter()
+        .map(|item| item.value)
+        .collect())
+}
+
+/// The direction of a line, see `gpio_cdev::LineDirection`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineDirection {
+    In,
+    Out,
+}
+
+/// The information of a line, see `gpio_cdev::LineInfo`.
+#[derive(Debug, Clone)]
+pub struct LineInfo {
+    line: Line,
+    name: Option<String>,
+    consumer: Option<String>,
+    used: bool,
+    output: bool,
+    active_low: bool,
+    open_drain: bool,
+    open_source: bool,
+}
+
+impl LineInfo {
+    pub fn line(&self) -> &Line {
+        &self.line
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn consumer(&self) -> Option<&str> {
+        self.consumer.as_deref()
+    }
+
+    pub fn direction(&self) -> LineDirection {
+        if self.output {
+            LineDirection::Out
+        } else {
+            LineDirection::In
+        }
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.used
+    }
+
+    pub fn is_active_low(&self) -> bool {
+        self.active_low
+    }
+
+    pub fn is_open_drain(&self) -> bool {
+        self.open_drain
+    }
+
+    pub fn is_open_source(&self) -> bool {
+        self.open_source
+    }
+}
+
+/// A requested line, see `gpio_cdev::LineHandle`.
+#[derive(Debug)]
+pub struct LineHandle {
+    line: Line,
+    flags: LineRequestFlags,
+    handle: line::LineHandle,
+    fd: RawFd,
+}
+
+impl LineHandle {
+    pub fn get_value(&self) -> Result<u8> {
+        Ok(get_values(&self.handle)?[0])
+    }
+
+    pub fn set_value(&self, value: u8) -> Result<()> {
+        self.handle.set_values_in_order(&[value])
+    }
+
+    pub fn line(&self) -> &Line {
+        &self.line
+    }
+
+    pub fn flags(&self) -> LineRequestFlags {
+        self.flags
+    }
+}
+
+impl AsRawFd for LineHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+/// Several lines of a chip, see `gpio_cdev::Lines`.
+#[derive(Debug, Clone)]
+pub struct Lines {
+    lines: Vec<Line>,
+}
+
+impl Lines {
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn request(
+        &self,
+        flags: LineRequestFlags,
+        default: &[u8],
+        consumer: &str,
+    ) -> Result<MultiLineHandle> {
+        let offsets: Vec<u32> = self.lines.iter().map(Line::offset).collect();
+        let handle = match self.lines.first() {
+            Some(line) => request_lines(&line.chip, &offsets, flags, default, consumer)?,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "no lines to request",
+                )
+                .into());
+            }
+        };
+        Ok(MultiLineHandle {
+            lines: self.clone(),
+            fd: fd_of(&handle)?,
+            handle,
+        })
+    }
+}
+
+impl std::ops::Index<usize> for Lines {
+    type Output = Line;
+
+    fn index(&self, index: usize) -> &Line {
+        &self.lines[index]
+    }
+}
+
+/// Several requested lines, see `gpio_cdev::MultiLineHandle`.
+#[derive(Debug)]
+pub struct MultiLineHandle {
+    lines: Lines,
+    handle: line::LineHandle,
+    fd: RawFd,
+}
+
+impl MultiLineHandle {
+    pub fn get_values(&self) -> Result<Vec<u8>> {
+        get_values(&self.handle)
+    }
+
+    pub fn set_values(&self, values: &[u8]) -> Result<()> {
+        self.handle.set_values_in_order(values)
+    }
+
+    pub fn num_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn lines(&self) -> &Lines {
+        &self.lines
+    }
+}
+
+impl AsRawFd for MultiLineHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+/// The edge of a [`LineEvent`], see `gpio_cdev::EventType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    RisingEdge,
+    FallingEdge,
+}
+
+/// An edge event, see `gpio_cdev::LineEvent`.
+#[derive(Debug, Clone, Copy)]
+pub struct LineEvent {
+    timestamp: u64,
+    event_type: EventType,
+}
+
+impl LineEvent {
+    /// Best estimate of time of event occurrence, in nanoseconds.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn event_type(&self) -> EventType {
+        self.event_type
+    }
+}
+
+/// A line requested for edge events, see `gpio_cdev::LineEventHandle`.
+#[derive(Debug)]
+pub struct LineEventHandle {
+    line: Line,
+    handle: line::LineHandle,
+    fd: RawFd,
+}
+
+impl LineEventHandle {
+    /// Blocks until an edge event is available.
+    pub fn get_event(&mut self) -> Result<LineEvent> {
+        let mut buf = [event::LineEvent::default()];
+        event::LineEvent::read(&self.handle, &mut buf)?;
+        let [e] = buf;
+        Ok(LineEvent {
+            timestamp: e.timestamp_ns() as _,
+            event_type: match e.event_type()? {
+                event::LineEventType::RisingEdge => EventType::RisingEdge,
+                event::LineEventType::FallingEdge => EventType::FallingEdge,
+            },
+        })
+    }
+
+    pub fn get_value(&self) -> Result<u8> {
+        Ok(get_values(&self.handle)?[0])
+    }
+
+    pub fn line(&self) -> &Line {
+        &self.line
+    }
+}
+
+impl AsRawFd for LineEventHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Iterator for LineEventHandle {
+    type Item = Result<LineEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.get_event())
+    }
+}
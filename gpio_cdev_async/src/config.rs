@@ -0,0 +1,1056 @@
+//! Textual line configuration, following the conventions of the libgpiod v2 tools.
+//!
+//! A configuration is a comma separated list of settings, e.g.
+//! `"input,active-low,pull-up,both-edges,debounce=5ms"`:
+//! - direction: `input`, `output` or `direction=<input|output>`
+//! - polarity: `active-low`, `active-high`
+//! - drive: `push-pull`, `open-drain`, `open-source` or `drive=<...>`
+//! - bias: `pull-up`, `pull-down`, `bias-disabled`, `as-is` or `bias=<pull-up|pull-down|disabled|as-is>`
+//! - edges: `rising-edge`, `falling-edge`, `both-edges` or `edges=<rising|falling|both|none>`
+//! - `debounce=<period>`: a period with a `us`, `ms` or `s` suffix, milliseconds without suffix
+//! - `event-clock=<monotonic|realtime|hte>`
+//! - `value=<0|1|inactive|active>`: the initial value of an output
+//!
+//! A kind of setting may be repeated with the same value only, e.g.
+//! `"input,output"` is rejected, and inputs take no drive or value.
+//!
+//! # Examples
+//! ```rust
+//! # use gpio_cdev_async::config::{Bias, Direction, Edge, LineConfig};
+//! let config: LineConfig = "input,pull-up,debounce=5ms".parse().unwrap();
+//! assert_eq!(config.direction, Some(Direction::Input));
+//! assert_eq!(config.bias, Some(Bias::PullUp));
+//! assert_eq!(config.to_string(), "input,pull-up,debounce=5ms");
+//! // the settings parse on their own too
+//! assert_eq!("both-edges".parse(), Ok(Edge::Both));
+//! assert_eq!("pull-up".parse::<Bias>().unwrap().to_string(), "pull-up");
+//! ```
+//!
+//! The settings and [`LineConfig`] serialize to these strings with the
+//! `serde` feature.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+#[cfg(feature = "v1")]
+use crate::line::EventFlags;
+#[cfg(feature = "v2")]
+use crate::line::PinAttribute;
+use crate::line::{HandleFlags, PinConfig};
+
+/// The direction of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+/// The drive of an output line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drive {
+    PushPull,
+    OpenDrain,
+    OpenSource,
+}
+
+/// The bias of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bias {
+    /// Leave the bias as it is.
+    AsIs,
+    PullUp,
+    PullDown,
+    Disabled,
+}
+
+/// The edges generating events on an input line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl Edge {
+    /// The edges of `bits`, bit 0 for rising and bit 1 for falling edges as
+    /// in the v1 `GPIOEVENT_REQUEST_*` flags. `None` if neither is set.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use gpio_cdev_async::config::Edge;
+    /// assert_eq!(Edge::from_bits(0b11), Some(Edge::Both));
+    /// assert_eq!(Edge::from_bits(0b10), Some(Edge::Falling));
+    /// assert_eq!(Edge::from_bits(0), None);
+    /// ```
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        match (bits & 1 != 0, bits & 2 != 0) {
+            (true, true) => Some(Self::Both),
+            (true, false) => Some(Self::Rising),
+            (false, true) => Some(Self::Falling),
+            (false, false) => None,
+        }
+    }
+
+    /// The event flags of an [`EventRequest`](crate::line::EventRequest) for these edges.
+    #[cfg(feature = "v1")]
+    pub(crate) fn event_flags(self) -> EventFlags {
+        match self {
+            Self::Rising => EventFlags::REQUEST_RISING_EDGE,
+            Self::Falling => EventFlags::REQUEST_FALLING_EDGE,
+            Self::Both => EventFlags::REQUEST_BOTH_EDGES,
+        }
+    }
+
+    /// The line flags enabling these edges.
+    #[cfg(feature = "v2")]
+    pub(crate) fn flags(self) -> HandleFlags {
+        let mut flags = HandleFlags::empty();
+        flags.set(
+            HandleFlags::GPIO_V2_LINE_FLAG_EDGE_RISING,
+            matches!(self, Self::Rising | Self::Both),
+        );
+        flags.set(
+            HandleFlags::GPIO_V2_LINE_FLAG_EDGE_FALLING,
+            matches!(self, Self::Falling | Self::Both),
+        );
+        flags
+    }
+}
+
+/// The clock used for the timestamps of edge events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventClock {
+    Monotonic,
+    Realtime,
+    Hte,
+}
+
+/// The configuration of a line, see the [module documentation](self) for its textual form.
+///
+/// Settings left as `None` are not requested from the kernel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineConfig {
+    pub direction: Option<Direction>,
+    pub active_low: bool,
+    pub drive: Option<Drive>,
+    pub bias: Option<Bias>,
+    pub edge: Option<Edge>,
+    /// v2 only.
+    pub debounce: Option<Duration>,
+    /// v2 only.
+    pub event_clock: Option<EventClock>,
+    pub output_value: Option<u8>,
+}
+
+impl LineConfig {
+    /// The request flags of this configuration.
+    ///
+    /// # Notes
+    /// - v1: edges are requested separately, see [`LineConfig::event_flags`].
+    pub fn flags(&self) -> HandleFlags {
+        let mut flags = HandleFlags::empty();
+        #[cfg(feature = "v1")]
+        {
+            use HandleFlags as F;
+            flags.set(F::REQUEST_INPUT, self.direction == Some(Direction::Input));
+            flags.set(F::REQUEST_OUTPUT, self.direction == Some(Direction::Output));
+            flags.set(F::REQUEST_ACTIVE_LOW, self.active_low);
+            flags.set(F::REQUEST_OPEN_DRAIN, self.drive == Some(Drive::OpenDrain));
+            flags.set(
+                F::REQUEST_OPEN_SOURCE,
+                self.drive == Some(Drive::OpenSource),
+            );
+            flags.set(F::REQUEST_BIAS_PULL_UP, self.bias == Some(Bias::PullUp));
+            flags.set(F::REQUEST_BIAS_PULL_DOWN, self.bias == Some(Bias::PullDown));
+            flags.set(F::REQUEST_BIAS_DISABLE, self.bias == Some(Bias::Disabled));
+        }
+        #[cfg(feature = "v2")]
+        {
+            use HandleFlags as F;
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_INPUT,
+                self.direction == Some(Direction::Input),
+            );
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_OUTPUT,
+                self.direction == Some(Direction::Output),
+            );
+            flags.set(F::GPIO_V2_LINE_FLAG_ACTIVE_LOW, self.active_low);
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_OPEN_DRAIN,
+                self.drive == Some(Drive::OpenDrain),
+            );
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_OPEN_SOURCE,
+                self.drive == Some(Drive::OpenSource),
+            );
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_BIAS_PULL_UP,
+                self.bias == Some(Bias::PullUp),
+            );
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_BIAS_PULL_DOWN,
+                self.bias == Some(Bias::PullDown),
+            );
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_BIAS_DISABLED,
+                self.bias == Some(Bias::Disabled),
+            );
+            flags |= self.edge.map_or(F::empty(), Edge::flags);
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_EVENT_CLOCK_REALTIME,
+                self.event_clock == Some(EventClock::Realtime),
+            );
+            flags.set(
+                F::GPIO_V2_LINE_FLAG_EVENT_CLOCK_HTE,
+                self.event_clock == Some(EventClock::Hte),
+            );
+        }
+        flags
+    }
+
+    /// The event flags of this configuration, for an [`EventRequest`](crate::line::EventRequest).
+    #[cfg(feature = "v1")]
+    pub fn event_flags(&self) -> EventFlags {
+        self.edge.map_or(EventFlags::empty(), Edge::event_flags)
+    }
+
+    /// The per-line configuration of the line at `offset`, for
+    /// [`LineRequestBuilder::set_offsets`](crate::line::LineRequestBuilder::set_offsets).
+    ///
+    /// # Notes
+    /// - v1: only the output value is per-line, pass [`LineConfig::flags`] to
+    ///   [`LineRequestBuilder::set_flags`](crate::line::LineRequestBuilder::set_flags).
+    /// - v2: the flags, output value and debounce period are all per-line.
+    pub fn pin_config(&self, offset: u32) -> PinConfig {
+        #[cfg(feature = "v1")]
+        {
+            PinConfig::with_value(offset, self.output_value)
+        }
+        #[cfg(feature = "v2")]
+        {
+            let mut attrs = vec![PinAttribute::Flags(self.flags())];
+            if let Some(value) = self.output_value {
+                attrs.push(PinAttribute::Value(value));
+            }
+            if let Some(debounce) = self.debounce {
+                let us = debounce.as_micros().try_into().unwrap_or(u32::MAX);
+                attrs.push(PinAttribute::DebouncePeriodUs(us));
+            }
+            PinConfig::from((offset, attrs))
+        }
+    }
+}
+
+/// The parameters of a line in a v1 handle or event request, as the raw
+/// `GPIOHANDLE_REQUEST_*` and `GPIOEVENT_REQUEST_*` values of the uAPI.
+///
+/// Converts to and from a [`LineConfig`], whose [`LineConfig::to_v2_flags`]
+/// then gives the v2 flags, so configurations stored by v1 code can be
+/// migrated. Available under both v1 and v2 features.
+///
+/// # Examples
+/// ```rust
+/// # use gpio_cdev_async::config::{Bias, LineConfig, V1LineParams};
+/// // GPIOHANDLE_REQUEST_INPUT | GPIOHANDLE_REQUEST_BIAS_PULL_UP, both edges
+/// let v1 = V1LineParams { handle_flags: 0x21, event_flags: 0x3, default_value: 0 };
+/// let config = LineConfig::try_from(v1).unwrap();
+/// assert_eq!(config.bias, Some(Bias::PullUp));
+/// assert_eq!(config.to_string(), "input,pull-up,both-edges");
+/// // GPIO_V2_LINE_FLAG_INPUT | EDGE_RISING | EDGE_FALLING | BIAS_PULL_UP
+/// assert_eq!(config.to_v2_flags(), 0x134);
+/// assert_eq!(V1LineParams::try_from(&config), Ok(v1));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct V1LineParams {
+    pub handle_flags: u32,
+    /// The edges of an event request, `0` for a handle request.
+    pub event_flags: u32,
+    /// The initial value of an output.
+    pub default_value: u8,
+}
+
+/// An error returned when converting between [`LineConfig`] and raw uAPI
+/// flags.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConvertConfigError {
+    /// Bits that are not flags of the uAPI version.
+    #[error("unknown flags {0:#x}")]
+    UnknownFlags(u64),
+    /// Flags that exclude each other, e.g. input and output.
+    #[error("conflicting flags {0:#x}")]
+    Conflict(u64),
+    /// A setting of the configuration that v1 does not have.
+    #[error("{0} is not supported by v1")]
+    NotInV1(&'static str),
+}
+
+/// The bits of the settings shared by the flags of both uAPI versions.
+struct FlagBits {
+    input: u64,
+    output: u64,
+    active_low: u64,
+    open_drain: u64,
+    open_source: u64,
+    pull_up: u64,
+    pull_down: u64,
+    bias_disabled: u64,
+}
+
+impl FlagBits {
+    const V1: Self = Self {
+        input: 1 << 0,
+        output: 1 << 1,
+        active_low: 1 << 2,
+        open_drain: 1 << 3,
+        open_source: 1 << 4,
+        pull_up: 1 << 5,
+        pull_down: 1 << 6,
+        bias_disabled: 1 << 7,
+    };
+
+    const V2: Self = Self {
+        input: 1 << 2,
+        output: 1 << 3,
+        active_low: 1 << 1,
+        open_drain: 1 << 6,
+        open_source: 1 << 7,
+        pull_up: 1 << 8,
+        pull_down: 1 << 9,
+        bias_disabled: 1 << 10,
+    };
+
+    fn all(&self) -> u64 {
+        self.input
+            | self.output
+            | self.active_low
+            | self.open_drain
+            | self.open_source
+            | self.pull_up
+            | self.pull_down
+            | self.bias_disabled
+    }
+
+    /// Decodes the shared settings, the other bits of `flags` are ignored.
+    fn decode(&self, flags: u64) -> Result<LineConfig, ConvertConfigError> {
+        Ok(LineConfig {
+            direction: one_of(
+                flags,
+                [(self.input, Direction::Input), (self.output, Direction::Output)],
+            )?,
+            active_low: flags & self.active_low != 0,
+            drive: one_of(
+                flags,
+                [(self.open_drain, Drive::OpenDrain), (self.open_source, Drive::OpenSource)],
+            )?,
+            bias: one_of(
+                flags,
+                [
+                    (self.pull_up, Bias::PullUp),
+                    (self.pull_down, Bias::PullDown),
+                    (self.bias_disabled, Bias::Disabled),
+                ],
+            )?,
+            ..LineConfig::default()
+        })
+    }
+
+    /// Encodes the shared settings of `config`, push-pull and as-is have no bits.
+    fn encode(&self, config: &LineConfig) -> u64 {
+        let mut flags = 0;
+        let mut set = |bit, on| {
+            if on {
+                flags |= bit
+            }
+        };
+        set(self.input, config.direction == Some(Direction::Input));
+        set(self.output, config.direction == Some(Direction::Output));
+        set(self.active_low, config.active_low);
+        set(self.open_drain, config.drive == Some(Drive::OpenDrain));
+        set(self.open_source, config.drive == Some(Drive::OpenSource));
+        set(self.pull_up, config.bias == Some(Bias::PullUp));
+        set(self.pull_down, config.bias == Some(Bias::PullDown));
+        set(self.bias_disabled, config.bias == Some(Bias::Disabled));
+        flags
+    }
+}
+
+const V1_RISING_EDGE: u32 = 1 << 0;
+const V1_FALLING_EDGE: u32 = 1 << 1;
+
+const V2_USED: u64 = 1 << 0;
+const V2_EDGE_RISING: u64 = 1 << 4;
+const V2_EDGE_FALLING: u64 = 1 << 5;
+const V2_EVENT_CLOCK_REALTIME: u64 = 1 << 11;
+const V2_EVENT_CLOCK_HTE: u64 = 1 << 12;
+
+/// The choice whose bit is set in `flags`, if any.
+fn one_of<T, const N: usize>(
+    flags: u64,
+    choices: [(u64, T); N],
+) -> Result<Option<T>, ConvertConfigError> {
+    let set = choices.iter().fold(0, |set, (bit, _)| set | (flags & bit));
+    if set.count_ones() > 1 {
+        return Err(ConvertConfigError::Conflict(set));
+    }
+    Ok(choices
+        .into_iter()
+        .find_map(|(bit, choice)| (flags & bit != 0).then_some(choice)))
+}
+
+/// The edge of a pair of rising and falling flags.
+fn edge_of(rising: bool, falling: bool) -> Option<Edge> {
+    match (rising, falling) {
+        (true, true) => Some(Edge::Both),
+        (true, false) => Some(Edge::Rising),
+        (false, true) => Some(Edge::Falling),
+        (false, false) => None,
+    }
+}
+
+impl TryFrom<V1LineParams> for LineConfig {
+    type Error = ConvertConfigError;
+
+    fn try_from(params: V1LineParams) -> Result<Self, Self::Error> {
+        let flags = u64::from(params.handle_flags);
+        let unknown = flags & !FlagBits::V1.all();
+        if unknown != 0 {
+            return Err(ConvertConfigError::UnknownFlags(unknown));
+        }
+        let unknown = params.event_flags & !(V1_RISING_EDGE | V1_FALLING_EDGE);
+        if unknown != 0 {
+            return Err(ConvertConfigError::UnknownFlags(unknown.into()));
+        }
+
+        let mut config = FlagBits::V1.decode(flags)?;
+        config.edge = edge_of(
+            params.event_flags & V1_RISING_EDGE != 0,
+            params.event_flags & V1_FALLING_EDGE != 0,
+        );
+        if config.direction == Some(Direction::Output) {
+            config.output_value = Some(u8::from(params.default_value != 0));
+        }
+        Ok(config)
+    }
+}
+
+impl TryFrom<&LineConfig> for V1LineParams {
+    type Error = ConvertConfigError;
+
+    /// Fails if `config` has a debounce period or a non-monotonic event clock.
+    fn try_from(config: &LineConfig) -> Result<Self, Self::Error> {
+        if config.debounce.is_some() {
+            return Err(ConvertConfigError::NotInV1("debounce"));
+        }
+        if matches!(config.event_clock, Some(EventClock::Realtime | EventClock::Hte)) {
+            return Err(ConvertConfigError::NotInV1("event clock"));
+        }
+        let event_flags = match config.edge {
+            None => 0,
+            Some(Edge::Rising) => V1_RISING_EDGE,
+            Some(Edge::Falling) => V1_FALLING_EDGE,
+            Some(Edge::Both) => V1_RISING_EDGE | V1_FALLING_EDGE,
+        };
+        Ok(Self {
+            handle_flags: FlagBits::V1.encode(config) as u32,
+            event_flags,
+            default_value: config.output_value.unwrap_or_default(),
+        })
+    }
+}
+
+impl LineConfig {
+    /// Decodes raw `GPIO_V2_LINE_FLAG_*` flags, e.g. of a stored v2 config
+    /// or of line info. `GPIO_V2_LINE_FLAG_USED` is ignored.
+    ///
+    /// Available under both v1 and v2 features, see [`V1LineParams`].
+    pub fn from_v2_flags(flags: u64) -> Result<Self, ConvertConfigError> {
+        let known = FlagBits::V2.all()
+            | V2_USED
+            | V2_EDGE_RISING
+            | V2_EDGE_FALLING
+            | V2_EVENT_CLOCK_REALTIME
+            | V2_EVENT_CLOCK_HTE;
+        if flags & !known != 0 {
+            return Err(ConvertConfigError::UnknownFlags(flags & !known));
+        }
+
+        let mut config = FlagBits::V2.decode(flags)?;
+        config.edge = edge_of(flags & V2_EDGE_RISING != 0, flags & V2_EDGE_FALLING != 0);
+        config.event_clock = match (
+            flags & V2_EVENT_CLOCK_REALTIME != 0,
+            flags & V2_EVENT_CLOCK_HTE != 0,
+        ) {
+            (true, true) => {
+                return Err(ConvertConfigError::Conflict(
+                    V2_EVENT_CLOCK_REALTIME | V2_EVENT_CLOCK_HTE,
+                ));
+            }
+            (true, false) => Some(EventClock::Realtime),
+            (false, true) => Some(EventClock::Hte),
+            (false, false) => None,
+        };
+        Ok(config)
+    }
+
+    /// The raw `GPIO_V2_LINE_FLAG_*` flags of this configuration, like
+    /// [`LineConfig::flags`] under the v2 feature.
+    ///
+    /// Available under both v1 and v2 features, see [`V1LineParams`].
+    pub fn to_v2_flags(&self) -> u64 {
+        let mut flags = FlagBits::V2.encode(self);
+        if matches!(self.edge, Some(Edge::Rising | Edge::Both)) {
+            flags |= V2_EDGE_RISING;
+        }
+        if matches!(self.edge, Some(Edge::Falling | Edge::Both)) {
+            flags |= V2_EDGE_FALLING;
+        }
+        match self.event_clock {
+            Some(EventClock::Realtime) => flags |= V2_EVENT_CLOCK_REALTIME,
+            Some(EventClock::Hte) => flags |= V2_EVENT_CLOCK_HTE,
+            Some(EventClock::Monotonic) | None => {}
+        }
+        flags
+    }
+}
+
+/// An error returned when parsing a [`LineConfig`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid line config setting {setting:?}")]
+pub struct ParseConfigError {
+    setting: String,
+}
+
+impl ParseConfigError {
+    fn new(setting: &str) -> Self {
+        Self {
+            setting: setting.to_owned(),
+        }
+    }
+
+    /// The setting that failed to parse.
+    pub fn setting(&self) -> &str {
+        &self.setting
+    }
+}
+
+impl FromStr for LineConfig {
+    type Err = ParseConfigError;
+
+    /// Fails on unknown settings, on settings of the same kind with
+    /// different values, e.g. `"input,output"`, and on a drive or an output
+    /// value for an input.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Self::default();
+        let mut active_low = None;
+        let mut edge = None;
+        // the first drive or value setting, which inputs do not take
+        let mut output_only = None;
+
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let err = || ParseConfigError::new(setting);
+            let (key, value) = match setting.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (setting, None),
+            };
+            if matches!(
+                key,
+                "push-pull" | "open-drain" | "open-source" | "drive" | "value"
+            ) {
+                output_only.get_or_insert(setting);
+            }
+
+            match (key, value) {
+                ("input" | "output", None) => {
+                    set_once(&mut config.direction, key.parse()?, setting)?
+                }
+                ("direction", Some(direction)) => {
+                    set_once(&mut config.direction, direction.parse()?, setting)?
+                }
+                ("active-low", None) => set_once(&mut active_low, true, setting)?,
+                ("active-high", None) => set_once(&mut active_low, false, setting)?,
+                ("push-pull" | "open-drain" | "open-source", None) => {
+                    set_once(&mut config.drive, key.parse()?, setting)?
+                }
+                ("drive", Some(drive)) => set_once(&mut config.drive, drive.parse()?, setting)?,
+                ("as-is" | "pull-up" | "pull-down" | "bias-disabled", None) => {
+                    set_once(&mut config.bias, key.parse()?, setting)?
+                }
+                ("bias", Some(bias)) => set_once(&mut config.bias, bias.parse()?, setting)?,
+                ("rising-edge" | "falling-edge" | "both-edges", None) => {
+                    set_once(&mut edge, Some(key.parse()?), setting)?
+                }
+                ("edges", Some("none")) => set_once(&mut edge, None, setting)?,
+                ("edges", Some(edges)) => set_once(&mut edge, Some(edges.parse()?), setting)?,
+                ("debounce", Some(period)) => set_once(
+                    &mut config.debounce,
+                    parse_period(period).ok_or_else(err)?,
+                    setting,
+                )?,
+                ("event-clock", Some(clock)) => {
+                    set_once(&mut config.event_clock, clock.parse()?, setting)?
+                }
+                ("value", Some(value)) => {
+                    let value = match value {
+                        "0" | "inactive" => 0,
+                        "1" | "active" => 1,
+                        _ => return Err(err()),
+                    };
+                    set_once(&mut config.output_value, value, setting)?
+                }
+                _ => return Err(err()),
+            }
+        }
+
+        if config.direction == Some(Direction::Input)
+            && let Some(setting) = output_only
+        {
+            return Err(ParseConfigError::new(setting));
+        }
+        config.active_low = active_low.unwrap_or(false);
+        config.edge = edge.flatten();
+        Ok(config)
+    }
+}
+
+/// Sets `slot` to `value` for `setting`, fails if it holds another value.
+fn set_once<T: PartialEq>(
+    slot: &mut Option<T>,
+    value: T,
+    setting: &str,
+) -> Result<(), ParseConfigError> {
+    match slot {
+        Some(current) if *current != value => Err(ParseConfigError::new(setting)),
+        _ => {
+            *slot = Some(value);
+            Ok(())
+        }
+    }
+}
+
+/// Parses a period with a `us`, `ms` or `s` suffix, milliseconds without suffix.
+fn parse_period(s: &str) -> Option<Duration> {
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(digits);
+    let value: u64 = value.parse().ok()?;
+    match unit {
+        "us" => Some(Duration::from_micros(value)),
+        "" | "ms" => Some(Duration::from_millis(value)),
+        "s" => Some(Duration::from_secs(value)),
+        _ => None,
+    }
+}
+
+impl Display for LineConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut settings: Vec<String> = Vec::new();
+        if let Some(direction) = self.direction {
+            settings.push(direction.to_string());
+        }
+        if self.active_low {
+            settings.push("active-low".into());
+        }
+        if let Some(drive) = self.drive {
+            settings.push(drive.to_string());
+        }
+        if let Some(bias) = self.bias {
+            settings.push(bias.to_string());
+        }
+        if let Some(edge) = self.edge {
+            settings.push(edge.to_string());
+        }
+        if let Some(debounce) = self.debounce {
+            let us = debounce.as_micros();
+            settings.push(if us % 1_000_000 == 0 {
+                format!("debounce={}s", us / 1_000_000)
+            } else if us % 1_000 == 0 {
+                format!("debounce={}ms", us / 1_000)
+            } else {
+                format!("debounce={us}us")
+            });
+        }
+        if let Some(clock) = self.event_clock {
+            settings.push(format!("event-clock={clock}"));
+        }
+        if let Some(value) = self.output_value {
+            settings.push(format!("value={}", u8::from(value != 0)));
+        }
+
+        write!(f, "{}", settings.join(","))
+    }
+}
+
+/// Implements [`FromStr`] and [`Display`] for a setting enum, the first
+/// name of a variant is the one displayed.
+macro_rules! setting_names {
+    ($ty:ident { $($variant:ident => $name:literal $(| $alias:literal)*,)* }) => {
+        impl FromStr for $ty {
+            type Err = ParseConfigError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s.trim() {
+                    $($name $(| $alias)* => Ok(Self::$variant),)*
+                    _ => Err(ParseConfigError::new(s)),
+                }
+            }
+        }
+
+        impl Display for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(match self {
+                    $(Self::$variant => $name,)*
+                })
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+setting_names!(Direction {
+    Input => "input",
+    Output => "output",
+});
+
+setting_names!(Drive {
+    PushPull => "push-pull",
+    OpenDrain => "open-drain",
+    OpenSource => "open-source",
+});
+
+setting_names!(Bias {
+    AsIs => "as-is",
+    PullUp => "pull-up",
+    PullDown => "pull-down",
+    Disabled => "bias-disabled" | "disabled",
+});
+
+setting_names!(Edge {
+    Rising => "rising-edge" | "rising",
+    Falling => "falling-edge" | "falling",
+    Both => "both-edges" | "both",
+});
+
+setting_names!(EventClock {
+    Monotonic => "monotonic",
+    Realtime => "realtime",
+    Hte => "hte",
+});
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LineConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LineConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parses the request flags of a configuration, e.g. `"input,pull-up"`.
+///
+/// Settings that are not flags, the output value and the debounce period,
+/// and on v1 the edges, are rejected.
+///
+/// # Examples
+/// ```rust
+/// # use gpio_cdev_async::line::HandleFlags;
+/// let flags: HandleFlags = "output,open-drain".parse().unwrap();
+/// # #[cfg(feature = "v1")]
+/// let expected = HandleFlags::REQUEST_OUTPUT | HandleFlags::REQUEST_OPEN_DRAIN;
+/// # #[cfg(feature = "v2")]
+/// # let expected = HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT | HandleFlags::GPIO_V2_LINE_FLAG_OPEN_DRAIN;
+/// assert_eq!(flags.bits(), expected.bits());
+/// assert!("output,value=1".parse::<HandleFlags>().is_err());
+/// ```
+impl FromStr for HandleFlags {
+    type Err = ParseConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let config: LineConfig = setting.parse()?;
+            let not_flags = config.output_value.is_some() || config.debounce.is_some();
+            #[cfg(feature = "v1")]
+            let not_flags = not_flags || config.edge.is_some();
+            if not_flags {
+                return Err(ParseConfigError::new(setting));
+            }
+        }
+        Ok(s.parse::<LineConfig>()?.flags())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configs() -> Vec<LineConfig> {
+        vec![
+            LineConfig::default(),
+            LineConfig {
+                direction: Some(Direction::Input),
+                active_low: true,
+                bias: Some(Bias::PullUp),
+                edge: Some(Edge::Both),
+                debounce: Some(Duration::from_millis(5)),
+                event_clock: Some(EventClock::Realtime),
+                ..LineConfig::default()
+            },
+            LineConfig {
+                direction: Some(Direction::Input),
+                bias: Some(Bias::Disabled),
+                edge: Some(Edge::Falling),
+                debounce: Some(Duration::from_micros(1500)),
+                event_clock: Some(EventClock::Hte),
+                ..LineConfig::default()
+            },
+            LineConfig {
+                direction: Some(Direction::Output),
+                drive: Some(Drive::OpenDrain),
+                bias: Some(Bias::AsIs),
+                output_value: Some(1),
+                ..LineConfig::default()
+            },
+            LineConfig {
+                direction: Some(Direction::Output),
+                drive: Some(Drive::OpenSource),
+                debounce: Some(Duration::from_secs(2)),
+                output_value: Some(0),
+                ..LineConfig::default()
+            },
+            LineConfig {
+                edge: Some(Edge::Rising),
+                event_clock: Some(EventClock::Monotonic),
+                ..LineConfig::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for config in configs() {
+            let s = config.to_string();
+            assert_eq!(s.parse::<LineConfig>(), Ok(config), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parses_the_long_forms() {
+        let config: LineConfig = "direction=output, drive=open-drain, bias=disabled, value=active"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            config.to_string(),
+            "output,open-drain,bias-disabled,value=1"
+        );
+        let config: LineConfig = "edges=both,event-clock=hte".parse().unwrap();
+        assert_eq!(config.to_string(), "both-edges,event-clock=hte");
+        assert_eq!("edges=none".parse(), Ok(LineConfig::default()));
+        assert_eq!("".parse(), Ok(LineConfig::default()));
+    }
+
+    #[test]
+    fn accepts_repeated_settings_with_the_same_value() {
+        for s in [
+            "input,direction=input",
+            "pull-up,bias=pull-up",
+            "active-low,active-low",
+            "both-edges,edges=both",
+            "edges=none,edges=none",
+            "output,value=1,value=active",
+            "open-drain,drive=open-drain",
+        ] {
+            assert!(s.parse::<LineConfig>().is_ok(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_conflicting_settings() {
+        for (s, setting) in [
+            ("input,output", "output"),
+            ("direction=output,input", "input"),
+            ("pull-up,pull-down", "pull-down"),
+            ("open-drain,push-pull", "push-pull"),
+            ("active-low,active-high", "active-high"),
+            ("edges=none,rising-edge", "rising-edge"),
+            ("rising-edge,falling-edge", "falling-edge"),
+            ("debounce=5ms,debounce=6ms", "debounce=6ms"),
+            ("event-clock=realtime,event-clock=hte", "event-clock=hte"),
+            ("output,value=0,value=1", "value=1"),
+        ] {
+            let err = s.parse::<LineConfig>().unwrap_err();
+            assert_eq!(err.setting(), setting, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_output_settings_on_inputs() {
+        for (s, setting) in [
+            ("input,value=1", "value=1"),
+            ("value=0,input", "value=0"),
+            ("input,open-drain", "open-drain"),
+            ("drive=push-pull,direction=input", "drive=push-pull"),
+        ] {
+            let err = s.parse::<LineConfig>().unwrap_err();
+            assert_eq!(err.setting(), setting, "{s:?}");
+        }
+        // without a direction the value is kept for an output requested later
+        assert!("value=1".parse::<LineConfig>().is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        for s in [
+            "sideways",
+            "direction=sideways",
+            "value=2",
+            "input=1",
+            "debounce",
+            "debounce=5m",
+            "edges=up",
+            "event-clock=tai",
+        ] {
+            assert!(s.parse::<LineConfig>().is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parses_periods() {
+        assert_eq!(parse_period("5"), Some(Duration::from_millis(5)));
+        assert_eq!(parse_period("5ms"), Some(Duration::from_millis(5)));
+        assert_eq!(parse_period("250us"), Some(Duration::from_micros(250)));
+        assert_eq!(parse_period("2s"), Some(Duration::from_secs(2)));
+        for s in [
+            "",
+            "ms",
+            "-1ms",
+            "1.5ms",
+            "5m",
+            "5 ms",
+            "99999999999999999999",
+        ] {
+            assert_eq!(parse_period(s), None, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parses_handle_flags() {
+        let flags: HandleFlags = "input,active-low,pull-down".parse().unwrap();
+        let config: LineConfig = "input,active-low,pull-down".parse().unwrap();
+        assert_eq!(flags.bits(), config.flags().bits());
+        assert!("output,value=1".parse::<HandleFlags>().is_err());
+        assert!("input,debounce=1ms".parse::<HandleFlags>().is_err());
+        assert!("input,output".parse::<HandleFlags>().is_err());
+        #[cfg(feature = "v1")]
+        assert!("input,both-edges".parse::<HandleFlags>().is_err());
+        #[cfg(feature = "v2")]
+        assert!("input,both-edges".parse::<HandleFlags>().is_ok());
+    }
+
+    /// `config` as raw flags keep it: push-pull and as-is have no bits.
+    fn without_flagless(config: LineConfig) -> LineConfig {
+        LineConfig {
+            drive: config.drive.filter(|&drive| drive != Drive::PushPull),
+            bias: config.bias.filter(|&bias| bias != Bias::AsIs),
+            ..config
+        }
+    }
+
+    #[test]
+    fn v1_params_round_trip() {
+        for config in configs() {
+            if config.debounce.is_some()
+                || matches!(
+                    config.event_clock,
+                    Some(EventClock::Realtime | EventClock::Hte)
+                )
+            {
+                assert!(V1LineParams::try_from(&config).is_err(), "{config}");
+                continue;
+            }
+            let params = V1LineParams::try_from(&config).unwrap();
+            let back = LineConfig::try_from(params).unwrap();
+            // monotonic is the only clock of v1
+            let expected = LineConfig {
+                event_clock: None,
+                ..without_flagless(config)
+            };
+            assert_eq!(back, expected);
+        }
+    }
+
+    #[test]
+    fn v2_flags_round_trip() {
+        for config in configs() {
+            let back = LineConfig::from_v2_flags(config.to_v2_flags()).unwrap();
+            // the flags have no value nor debounce period, and monotonic is
+            // the default clock
+            let expected = LineConfig {
+                debounce: None,
+                output_value: None,
+                event_clock: config
+                    .event_clock
+                    .filter(|&clock| clock != EventClock::Monotonic),
+                ..without_flagless(config)
+            };
+            assert_eq!(back, expected);
+        }
+        let used = LineConfig::from_v2_flags(V2_USED | FlagBits::V2.input).unwrap();
+        assert_eq!(used.direction, Some(Direction::Input));
+    }
+
+    #[test]
+    fn rejects_invalid_raw_flags() {
+        let v1 = |handle_flags, event_flags| V1LineParams {
+            handle_flags,
+            event_flags,
+            default_value: 0,
+        };
+        assert_eq!(
+            LineConfig::try_from(v1(0b11, 0)),
+            Err(ConvertConfigError::Conflict(0b11))
+        );
+        assert_eq!(
+            LineConfig::try_from(v1(1 << 8, 0)),
+            Err(ConvertConfigError::UnknownFlags(1 << 8))
+        );
+        assert_eq!(
+            LineConfig::try_from(v1(0, 1 << 2)),
+            Err(ConvertConfigError::UnknownFlags(1 << 2))
+        );
+        assert_eq!(
+            LineConfig::from_v2_flags(V2_EVENT_CLOCK_REALTIME | V2_EVENT_CLOCK_HTE),
+            Err(ConvertConfigError::Conflict(
+                V2_EVENT_CLOCK_REALTIME | V2_EVENT_CLOCK_HTE
+            ))
+        );
+        assert_eq!(
+            LineConfig::from_v2_flags(1 << 13),
+            Err(ConvertConfigError::UnknownFlags(1 << 13))
+        );
+        let debounced: LineConfig = "input,debounce=1ms".parse().unwrap();
+        assert_eq!(
+            V1LineParams::try_from(&debounced),
+            Err(ConvertConfigError::NotInV1("debounce"))
+        );
+    }
+}
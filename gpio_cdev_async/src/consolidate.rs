@@ -0,0 +1,234 @@
+//! Requesting many single-line requirements with as few requests as possible.
+//!
+//! Components of an application often need one line each. Requesting every
+//! line separately costs a file descriptor and an ioctl per line, while the
+//! kernel accepts up to 64 lines per request. [`consolidate`] groups the
+//! requirements with compatible configurations into shared requests and
+//! hands every requirement a [`SharedLine`] reading and writing its own line
+//! only.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{chip::Chip, config::LineConfig, consolidate};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let chip = Chip::new("/dev/gpiochip0")?;
+//! let requirements = [
+//!     (17, "output,value=0".parse::<LineConfig>()?),
+//!     (18, "output,value=1".parse()?),
+//!     (23, "input,pull-up".parse()?),
+//! ];
+//! // one request on v2, two on v1
+//! let [led, relay, button] = consolidate::consolidate(&chip, "app", &requirements)?
+//!     .try_into()
+//!     .unwrap();
+//! led.set_value(1)?;
+//! # let _ = (relay, button);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - v1: lines are grouped when their flags are equal, lines with edges are
+//!   requested alone as event lines.
+//! - v2: lines are grouped as long as their distinct flags, debounce periods
+//!   and output values fit the 10 attributes of a request.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{io, sync::Arc};
+
+#[cfg(feature = "v1")]
+use std::sync::Mutex;
+
+#[cfg(feature = "v1")]
+use crate::line::EventRequest;
+use crate::{
+    chip::Chip,
+    config::LineConfig,
+    line::{index_of_offset, LineHandle, LineRequest},
+    Result,
+};
+
+/// Groups `requirements`, pairs of offset and configuration, into requests.
+///
+/// Returns the indices into `requirements` of the lines of every request.
+/// Lines are added to the first request they are compatible with.
+pub fn plan(requirements: &[(u32, LineConfig)]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for index in 0..requirements.len() {
+        let fits = |group: &Vec<usize>| {
+            let lines: Vec<_> = group.iter().chain([&index]).map(|&i| &requirements[i]).collect();
+            fits(&lines)
+        };
+        match groups.iter_mut().find(|group| fits(group)) {
+            Some(group) => group.push(index),
+            None => groups.push(vec![index]),
+        }
+    }
+    groups
+}
+
+/// Whether `lines` can be requested together, with the flags of the first.
+#[cfg(feature = "v1")]
+fn fits(lines: &[&(u32, LineConfig)]) -> bool {
+    let flags = lines[0].1.flags();
+    // event requests hold a single line
+    lines
+        .iter()
+        .all(|(_, config)| config.flags().bits() == flags.bits() && config.edge.is_none())
+        && request("", lines).is_ok_and(|request| request.offsets().len() == lines.len())
+}
+
+/// Whether the attributes of `lines` fit in one request.
+#[cfg(feature = "v2")]
+fn fits(lines: &[&(u32, LineConfig)]) -> bool {
+    // the builder leaves out the lines whose attributes no longer fit
+    request("", lines).is_ok_and(|request| request.offsets().len() == lines.len())
+}
+
+/// The request of `lines`, on v1 with the flags of the first.
+fn request(consumer: &str, lines: &[&(u32, LineConfig)]) -> Result<LineRequest> {
+    let builder = LineRequest::builder().set_consumer(consumer);
+    #[cfg(feature = "v1")]
+    let builder = builder.set_flags(lines[0].1.flags());
+    builder
+        .set_offsets(lines.iter().map(|(offset, config)| config.pin_config(*offset)))
+        .build()
+}
+
+/// Requests the lines of `requirements`, pairs of offset and configuration,
+/// from `chip` with as few requests as [`plan`] allows.
+///
+/// Returns the line of every requirement, in the same order. Fails if an
+/// offset appears twice, the requests made so far are released.
+pub fn consolidate(
+    chip: &Chip,
+    consumer: &str,
+    requirements: &[(u32, LineConfig)],
+) -> Result<Vec<SharedLine>> {
+    for (i, (offset, _)) in requirements.iter().enumerate() {
+        if requirements[..i].iter().any(|(other, _)| other == offset) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("line {offset} is required twice"),
+            )
+            .into());
+        }
+    }
+
+    let mut lines: Vec<Option<SharedLine>> = (0..requirements.len()).map(|_| None).collect();
+    for group in plan(requirements) {
+        let lines_of_group: Vec<_> = group.iter().map(|&i| &requirements[i]).collect();
+        #[cfg(feature = "v1")]
+        let handle = match lines_of_group[0] {
+            (offset, config) if config.edge.is_some() => chip.get_event_line(EventRequest::new(
+                *offset,
+                config.flags(),
+                config.event_flags(),
+                consumer,
+            ))?,
+            _ => chip.get_line(request(consumer, &lines_of_group)?)?,
+        };
+        #[cfg(feature = "v2")]
+        let handle = chip.get_line(request(consumer, &lines_of_group)?)?;
+
+        let shared = Arc::new(Shared {
+            handle,
+            #[cfg(feature = "v1")]
+            lock: Mutex::new(()),
+        });
+        for i in group {
+            let offset = requirements[i].0;
+            let index = index_of_offset(shared.handle.offsets(), offset).unwrap();
+            lines[i] = Some(SharedLine {
+                shared: shared.clone(),
+                offset,
+                index,
+            });
+        }
+    }
+    Ok(lines.into_iter().map(Option::unwrap).collect())
+}
+
+#[derive(Debug)]
+struct Shared {
+    handle: LineHandle,
+    /// Serializes the read-modify-write of v1 values.
+    #[cfg(feature = "v1")]
+    lock: Mutex<()>,
+}
+
+/// One line of a request shared by several requirements, see
+/// [`consolidate`].
+///
+/// The request is released when the last of its lines is dropped.
+#[derive(Debug, Clone)]
+pub struct SharedLine {
+    shared: Arc<Shared>,
+    offset: u32,
+    index: usize,
+}
+
+impl SharedLine {
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// The shared handle, also holding the lines of other requirements.
+    pub fn handle(&self) -> &LineHandle {
+        &self.shared.handle
+    }
+
+    pub fn get_value(&self) -> Result<u8> {
+        let mask = 1 << self.index;
+        let bits = self.shared.handle.backend.get_values(mask)?;
+        Ok((bits & mask != 0) as u8)
+    }
+
+    /// Sets the value of the line, the other lines of the request keep theirs.
+    pub fn set_value(&self, value: u8) -> Result<()> {
+        #[cfg(feature = "v1")]
+        let _lock = self.shared.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.shared.handle.set_value(self.offset, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirements<S: AsRef<str>>(configs: impl IntoIterator<Item = S>) -> Vec<(u32, LineConfig)> {
+        configs
+            .into_iter()
+            .enumerate()
+            .map(|(offset, config)| (offset as u32, config.as_ref().parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_more_than_64_lines() {
+        let requirements = requirements(["input"; 65]);
+        assert_eq!(plan(&requirements), [(0..64).collect(), vec![64]]);
+    }
+
+    #[cfg(feature = "v2")]
+    #[test]
+    fn starts_a_new_group_when_the_attributes_overflow() {
+        // the input flags take one attribute, every debounce period another
+        let requirements = requirements((1..=12).map(|ms| format!("input,debounce={ms}ms")));
+        assert_eq!(plan(&requirements), [(0..9).collect(), vec![9, 10, 11]]);
+    }
+
+    #[cfg(feature = "v1")]
+    #[test]
+    fn never_merges_edge_lines() {
+        let requirements = requirements([
+            "input,both-edges",
+            "input,both-edges",
+            "input",
+            "input,rising-edge",
+            "input",
+        ]);
+        assert_eq!(plan(&requirements), [vec![0], vec![1], vec![2, 4], vec![3]]);
+    }
+}
@@ -6,6 +6,25 @@ pub enum Error {
     Io(#[from] std::io::Error),
 }
 
+impl Error {
+    /// Returns the OS error code of this error, if it has one.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Self::Ioctl { source, .. } => Some(*source as i32),
+            Self::Io(e) => e.raw_os_error(),
+        }
+    }
+
+    /// Returns whether a nonblocking read found nothing to read, see
+    /// [`OpenOptions::set_nonblocking`](crate::chip::OpenOptions::set_nonblocking).
+    pub fn is_would_block(&self) -> bool {
+        match self {
+            Self::Ioctl { source, .. } => *source == nix::Error::EAGAIN,
+            Self::Io(e) => e.kind() == std::io::ErrorKind::WouldBlock,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IoctlKind {
     GetChipInfo,
@@ -1,12 +1,33 @@
-use std::os::fd::AsRawFd;
+use std::{
+    os::fd::{AsRawFd, RawFd},
+    sync::{Condvar, Mutex, MutexGuard},
+};
 
-use crate::{chip::Chip, ffi, line::LineInfo, Result};
+use crate::{
+    chip::Chip,
+    ffi::{self, common::Pod},
+    line::{LineHandle, LineInfo},
+    parse::{record_count, ParseError},
+    ring::{Backpressure, EventRing},
+    Result,
+};
+
+#[cfg(feature = "v2")]
+use crate::line::index_of_offset;
 
 #[cfg(feature = "v1")]
 pub use ffi::v1::GpioLineChangedType as LineChangedType;
 #[cfg(feature = "v2")]
 pub use ffi::v2::GpioV2LineChangedType as LineChangedType;
 
+#[cfg(feature = "v1")]
+pub use ffi::v1::GpioEventType as LineEventType;
+#[cfg(feature = "v2")]
+pub use ffi::v2::GpioV2LineEventId as LineEventType;
+
+/// A change in the status of a watched GPIO line.
+///
+/// See [`Chip::get_lineinfo_watch`].
 #[derive(Debug)]
 #[repr(transparent)]
 pub struct LineInfoChangedEvent {
@@ -17,10 +38,12 @@ pub struct LineInfoChangedEvent {
 }
 
 impl LineInfoChangedEvent {
+    /// The type of the change.
     pub fn event_type(&self) -> LineChangedType {
         self.inner.event_type.into()
     }
 
+    /// The updated information of the line.
     pub fn lineinfo(&self) -> &LineInfo {
         #[cfg(feature = "v2")]
         {
@@ -32,7 +55,8 @@ impl LineInfoChangedEvent {
         }
     }
 
-    pub fn timestamp_ns(&self) -> libc::c_ulong {
+    /// Estimate of time of status change occurrence, in nanoseconds.
+    pub fn timestamp_ns(&self) -> u64 {
         #[cfg(feature = "v2")]
         {
             self.inner.timestamp_ns
@@ -43,21 +67,47 @@ impl LineInfoChangedEvent {
         }
     }
 
-    pub fn read(chip: &Chip, buf: &mut [LineInfoChangedEvent]) -> Result<usize> {
-        const T_LEN: usize = std::mem::size_of::<LineInfoChangedEvent>();
-        let ptr = std::ptr::addr_of_mut!(*buf) as *mut LineInfoChangedEvent as *mut libc::c_void;
-        match unsafe { libc::read(chip.file.as_raw_fd(), ptr, T_LEN * buf.len()) } {
-            -1 => Err(crate::error::ioctl_error(
-                crate::IoctlKind::GetLineEvent,
-                nix::Error::last(),
-            )),
-            n => {
-                debug_assert!(n > 0);
-                let n = n.unsigned_abs();
-                debug_assert!(n % T_LEN == 0);
-                Ok(n / T_LEN)
-            }
+    /// A change of a line of a [`GpioBackend`](crate::backend::GpioBackend).
+    pub fn new(info: LineInfo, event_type: LineChangedType, timestamp_ns: u64) -> Self {
+        let mut event = Self::default();
+        event.inner.info = info.inner;
+        event.inner.event_type = event_type as u32;
+        #[cfg(feature = "v1")]
+        {
+            event.inner.timestamp = timestamp_ns;
         }
+        #[cfg(feature = "v2")]
+        {
+            event.inner.timestamp_ns = timestamp_ns;
+        }
+        event
+    }
+
+    /// Decodes an event from the bytes of a read on a [`Chip`].
+    ///
+    /// Returns `None` if `bytes` is not exactly one valid event long, see
+    /// [`crate::parse`] for why.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        <Self as Pod>::from_bytes(bytes).filter(|event| event.validate().is_ok())
+    }
+
+    /// Reads line info changes of the watched lines of `chip` into `buf`,
+    /// returning the number of events read.
+    ///
+    /// # Notes
+    /// - This function blocks until at least one event is available, unless
+    ///   the chip is nonblocking: it then fails with an error whose
+    ///   [`Error::is_would_block`](crate::Error::is_would_block) is `true`.
+    /// - Returns `0` if `buf` is empty.
+    pub fn read(chip: &Chip, buf: &mut [LineInfoChangedEvent]) -> Result<usize> {
+        chip.backend.read_line_info_changes(buf)
+    }
+}
+
+// SAFETY: `repr(transparent)` over a `Pod` uapi struct
+unsafe impl Pod for LineInfoChangedEvent {
+    fn validate(&self) -> std::result::Result<(), ParseError> {
+        self.inner.validate()
     }
 }
 
@@ -67,10 +117,20 @@ impl Default for LineInfoChangedEvent {
     }
 }
 
+/// An iterator over the line info changes of a [`Chip`].
+///
+/// See [`Chip::lineinfo_changes`].
+#[derive(Debug)]
 pub struct LineInfoChangeIter<'a> {
     chip: &'a Chip,
 }
 
+impl<'a> LineInfoChangeIter<'a> {
+    pub(crate) fn new(chip: &'a Chip) -> Self {
+        Self { chip }
+    }
+}
+
 impl Iterator for LineInfoChangeIter<'_> {
     type Item = Result<LineInfoChangedEvent>;
 
@@ -79,6 +139,7 @@ impl Iterator for LineInfoChangeIter<'_> {
         let mut buf = [LineInfoChangedEvent::default(); BUF_SIZE];
 
         match LineInfoChangedEvent::read(self.chip, &mut buf) {
+            Ok(0) => None,
             Ok(_len) => {
                 debug_assert_eq!(_len, BUF_SIZE);
                 Some(Ok(buf.into_iter().next().unwrap()))
@@ -91,3 +152,612 @@ impl Iterator for LineInfoChangeIter<'_> {
         (usize::MAX, None)
     }
 }
+
+/// An edge event read from a [`LineHandle`] with edge detection enabled.
+///
+/// - v1: the handle must come from an [`EventRequest`](crate::line::EventRequest).
+/// - v2: the handle must be requested with `GPIO_V2_LINE_FLAG_EDGE_RISING` and/or
+///   `GPIO_V2_LINE_FLAG_EDGE_FALLING` set.
+#[derive(Debug, Clone)]
+#[repr(transparent)]
+pub struct LineEvent {
+    #[cfg(feature = "v2")]
+    inner: ffi::v2::GpioV2LineEvent,
+    #[cfg(feature = "v1")]
+    inner: ffi::v1::GpioEventData,
+}
+
+impl LineEvent {
+    /// The edge that triggered the event.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidData`] on an unknown event id,
+    /// e.g. of a default event never filled by a read.
+    pub fn event_type(&self) -> Result<LineEventType> {
+        Ok(LineEventType::try_from(self.inner.id)?)
+    }
+
+    /// Best estimate of time of event occurrence, in nanoseconds.
+    pub fn timestamp_ns(&self) -> u64 {
+        #[cfg(feature = "v2")]
+        {
+            self.inner.timestamp_ns
+        }
+        #[cfg(feature = "v1")]
+        {
+            self.inner.timestamp
+        }
+    }
+
+    /// The offset of the line that triggered the event.
+    #[cfg(feature = "v2")]
+    pub fn offset(&self) -> u32 {
+        self.inner.offset
+    }
+
+    /// The sequence number of the event among all the lines of the request.
+    #[cfg(feature = "v2")]
+    pub fn seqno(&self) -> u32 {
+        self.inner.seqno
+    }
+
+    /// The sequence number of the event on this particular line.
+    #[cfg(feature = "v2")]
+    pub fn line_seqno(&self) -> u32 {
+        self.inner.line_seqno
+    }
+
+    /// An event of a [`LineBackend`](crate::backend::LineBackend), `offset`
+    /// and the sequence numbers are dropped on v1.
+    pub fn new(
+        offset: u32,
+        event_type: LineEventType,
+        timestamp_ns: u64,
+        seqno: u32,
+        line_seqno: u32,
+    ) -> Self {
+        let mut event = Self::default();
+        event.inner.id = event_type as u32;
+        #[cfg(feature = "v1")]
+        {
+            let _ = (offset, seqno, line_seqno);
+            event.inner.timestamp = timestamp_ns;
+        }
+        #[cfg(feature = "v2")]
+        {
+            event.inner.timestamp_ns = timestamp_ns;
+            event.inner.offset = offset;
+            event.inner.seqno = seqno;
+            event.inner.line_seqno = line_seqno;
+        }
+        event
+    }
+
+    /// Decodes an event from the bytes of a read on a [`LineHandle`].
+    ///
+    /// Returns `None` if `bytes` is not exactly one valid event long, see
+    /// [`crate::parse`] for why.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        <Self as Pod>::from_bytes(bytes).filter(|event| event.validate().is_ok())
+    }
+
+    /// Reads edge events from `handle` into `buf`, returning the number of events read.
+    ///
+    /// # Notes
+    /// - This function blocks until at least one event is available.
+    /// - Returns `0` if `buf` is empty.
+    pub fn read(handle: &LineHandle, buf: &mut [LineEvent]) -> Result<usize> {
+        handle.backend.read_events(buf)
+    }
+
+    /// Reads edge events from `handle` into the first non-empty buffer of
+    /// `bufs`, with a single read, returning the number of events read.
+    ///
+    /// The kernel fills a `readv(2)` one buffer at a time with a read each,
+    /// blocking on a later buffer once events were read, hence the single read.
+    ///
+    /// # Notes
+    /// - This function blocks until at least one event is available.
+    /// - Returns `0` if all of `bufs` are empty.
+    pub fn read_vectored(handle: &LineHandle, bufs: &mut [&mut [LineEvent]]) -> Result<usize> {
+        handle.backend.read_events_vectored(bufs)
+    }
+}
+
+impl LineEvent {
+    /// Writes the event to `bytes` in the uapi layout, with zeroed padding.
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes.fill(0);
+        let mut put = |at: usize, field: &[u8]| bytes[at..at + field.len()].copy_from_slice(field);
+        #[cfg(feature = "v2")]
+        {
+            use ffi::v2::GpioV2LineEvent as E;
+            put(
+                std::mem::offset_of!(E, timestamp_ns),
+                &self.inner.timestamp_ns.to_ne_bytes(),
+            );
+            put(std::mem::offset_of!(E, id), &self.inner.id.to_ne_bytes());
+            put(
+                std::mem::offset_of!(E, offset),
+                &self.inner.offset.to_ne_bytes(),
+            );
+            put(
+                std::mem::offset_of!(E, seqno),
+                &self.inner.seqno.to_ne_bytes(),
+            );
+            put(
+                std::mem::offset_of!(E, line_seqno),
+                &self.inner.line_seqno.to_ne_bytes(),
+            );
+        }
+        #[cfg(feature = "v1")]
+        {
+            use ffi::v1::GpioEventData as E;
+            put(
+                std::mem::offset_of!(E, timestamp),
+                &self.inner.timestamp.to_ne_bytes(),
+            );
+            put(std::mem::offset_of!(E, id), &self.inner.id.to_ne_bytes());
+        }
+    }
+}
+
+// SAFETY: `repr(transparent)` over a `Pod` uapi struct
+unsafe impl Pod for LineEvent {
+    fn validate(&self) -> std::result::Result<(), ParseError> {
+        self.inner.validate()
+    }
+}
+
+impl Default for LineEvent {
+    fn default() -> Self {
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+/// Drains the edge events of a [`LineHandle`] with one large read per call
+/// instead of one read per event.
+///
+/// Up to `batch` events are read at once, through
+/// [`LineBackend::read_events`](crate::backend::LineBackend::read_events).
+///
+/// See [`LineHandle::event_reader`].
+#[derive(Debug)]
+pub struct EventReader<'a> {
+    handle: &'a LineHandle,
+    /// Storage of `batch` events.
+    buf: Vec<LineEvent>,
+}
+
+impl<'a> EventReader<'a> {
+    /// # Panics
+    /// If `batch` is `0`.
+    pub(crate) fn new(handle: &'a LineHandle, batch: usize) -> Self {
+        assert!(batch > 0, "the batch of an event reader must not be empty");
+        Self {
+            handle,
+            buf: (0..batch).map(|_| LineEvent::default()).collect(),
+        }
+    }
+
+    /// Appends the events of one read to `out`, returning their number.
+    ///
+    /// # Notes
+    /// - This function blocks until at least one event is available.
+    /// - Returns `0` at the end of the events, as [`LineEvent::read`] does.
+    pub fn read(&mut self, out: &mut Vec<LineEvent>) -> Result<usize> {
+        let len = self.handle.backend.read_events(&mut self.buf)?;
+        out.extend(self.buf[..len].iter_mut().map(std::mem::take));
+        Ok(len)
+    }
+}
+
+/// A view of an edge event in a caller-owned byte buffer, decoded field by
+/// field on access rather than copied into a [`LineEvent`].
+///
+/// The buffer needs no particular alignment, and the ids are checked when
+/// the buffer is read, see [`crate::parse`].
+///
+/// # Examples
+/// ```rust,no_run
+/// # use gpio_cdev_async::{event::EdgeEventRef, line::LineHandle};
+/// # fn capture(handle: &LineHandle) -> gpio_cdev_async::Result<()> {
+/// let mut buf = vec![0; 256 * EdgeEventRef::SIZE];
+/// loop {
+///     for event in EdgeEventRef::read(handle, &mut buf)? {
+///         println!("{:?} at {}", event.event_type(), event.timestamp_ns());
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct EdgeEventRef<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> EdgeEventRef<'a> {
+    /// The size of an event in bytes, the buffers passed to
+    /// [`EdgeEventRef::read`] are best a multiple of it.
+    pub const SIZE: usize = std::mem::size_of::<LineEvent>();
+
+    /// Reads edge events from `handle` into `buf` with a single read on
+    /// kernel handles, returning views over the events read.
+    ///
+    /// # Notes
+    /// - This function blocks until at least one event is available.
+    /// - Yields nothing if `buf` is shorter than one event.
+    pub fn read(handle: &LineHandle, buf: &'a mut [u8]) -> Result<EdgeEventRefs<'a>> {
+        let whole = buf.len() / Self::SIZE * Self::SIZE;
+        let buf = &mut buf[..whole];
+        let len = if buf.is_empty() {
+            0
+        } else if let Some(fd) = handle.kernel_fd() {
+            // SAFETY: the kernel writes at most `buf.len()` bytes
+            match unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } {
+                -1 => return Err(std::io::Error::last_os_error().into()),
+                n => record_count::<LineEvent>(n.unsigned_abs())?,
+            }
+        } else {
+            let mut events: Vec<_> = (0..buf.len() / Self::SIZE)
+                .map(|_| LineEvent::default())
+                .collect();
+            let len = handle.backend.read_events(&mut events)?;
+            for (event, bytes) in events[..len].iter().zip(buf.chunks_exact_mut(Self::SIZE)) {
+                event.encode(bytes);
+            }
+            len
+        };
+
+        let bytes = &buf[..len * Self::SIZE];
+        let refs = EdgeEventRefs {
+            chunks: bytes.chunks_exact(Self::SIZE),
+        };
+        refs.clone().try_for_each(|event| match event.id() {
+            1..=2 => Ok(()),
+            id => Err(ParseError::EventType(id)),
+        })?;
+        Ok(refs)
+    }
+
+    /// The edge that triggered the event.
+    ///
+    /// The ids are checked when the events are read, so this does not fail.
+    pub fn event_type(&self) -> LineEventType {
+        LineEventType::try_from(self.id()).expect("the event ids are checked on read")
+    }
+
+    /// Best estimate of time of event occurrence, in nanoseconds.
+    pub fn timestamp_ns(&self) -> u64 {
+        #[cfg(feature = "v2")]
+        let at = std::mem::offset_of!(ffi::v2::GpioV2LineEvent, timestamp_ns);
+        #[cfg(feature = "v1")]
+        let at = std::mem::offset_of!(ffi::v1::GpioEventData, timestamp);
+        u64::from_ne_bytes(self.bytes[at..at + 8].try_into().unwrap())
+    }
+
+    /// The offset of the line that triggered the event.
+    #[cfg(feature = "v2")]
+    pub fn offset(&self) -> u32 {
+        self.u32_at(std::mem::offset_of!(ffi::v2::GpioV2LineEvent, offset))
+    }
+
+    /// The sequence number of the event among all the lines of the request.
+    #[cfg(feature = "v2")]
+    pub fn seqno(&self) -> u32 {
+        self.u32_at(std::mem::offset_of!(ffi::v2::GpioV2LineEvent, seqno))
+    }
+
+    /// The sequence number of the event on this particular line.
+    #[cfg(feature = "v2")]
+    pub fn line_seqno(&self) -> u32 {
+        self.u32_at(std::mem::offset_of!(ffi::v2::GpioV2LineEvent, line_seqno))
+    }
+
+    /// The raw bytes of the event, in the uapi layout.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Copies the event out of the buffer.
+    pub fn to_event(&self) -> LineEvent {
+        crate::parse::parse_line_event(self.bytes).expect("the event is checked on read")
+    }
+
+    fn id(&self) -> u32 {
+        #[cfg(feature = "v2")]
+        let at = std::mem::offset_of!(ffi::v2::GpioV2LineEvent, id);
+        #[cfg(feature = "v1")]
+        let at = std::mem::offset_of!(ffi::v1::GpioEventData, id);
+        self.u32_at(at)
+    }
+
+    fn u32_at(&self, at: usize) -> u32 {
+        u32::from_ne_bytes(self.bytes[at..at + 4].try_into().unwrap())
+    }
+}
+
+impl std::fmt::Debug for EdgeEventRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut temp = f.debug_struct("EdgeEventRef");
+        temp.field("event_type", &self.event_type());
+        temp.field("timestamp_ns", &self.timestamp_ns());
+        #[cfg(feature = "v2")]
+        {
+            temp.field("offset", &self.offset());
+            temp.field("seqno", &self.seqno());
+            temp.field("line_seqno", &self.line_seqno());
+        }
+        temp.finish()
+    }
+}
+
+/// The events of one [`EdgeEventRef::read`], in the order they were read.
+#[derive(Debug, Clone)]
+pub struct EdgeEventRefs<'a> {
+    chunks: std::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> Iterator for EdgeEventRefs<'a> {
+    type Item = EdgeEventRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(|bytes| EdgeEventRef { bytes })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl ExactSizeIterator for EdgeEventRefs<'_> {}
+
+/// An iterator over the edge events of a [`LineHandle`].
+///
+/// See [`LineHandle::events`].
+#[derive(Debug)]
+pub struct LineEventIter<'a> {
+    handle: &'a LineHandle,
+}
+
+impl<'a> LineEventIter<'a> {
+    pub(crate) fn new(handle: &'a LineHandle) -> Self {
+        Self { handle }
+    }
+}
+
+impl Iterator for LineEventIter<'_> {
+    type Item = Result<LineEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = [LineEvent::default()];
+
+        match LineEvent::read(self.handle, &mut buf) {
+            Ok(0) => None,
+            Ok(_len) => {
+                debug_assert_eq!(_len, 1);
+                Some(Ok(buf.into_iter().next().unwrap()))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// An iterator over the edge events of some lines of a [`LineHandle`].
+///
+/// See [`LineHandle::events_for`].
+#[derive(Debug)]
+pub struct LineEventFilter<'a> {
+    handle: &'a LineHandle,
+    /// The key of the queue of the iterator in [`Subscriptions`].
+    id: u64,
+    /// The indices of the selected lines, as in [`LineHandle::offsets`].
+    mask: u64,
+}
+
+impl<'a> LineEventFilter<'a> {
+    /// The most events queued for an iterator, its oldest events are
+    /// dropped when it falls further behind.
+    pub const CAPACITY: usize = 64;
+
+    pub(crate) fn new(handle: &'a LineHandle, mask: u64) -> Self {
+        let mut state = handle.subscriptions.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.queues.push(Queue {
+            id,
+            mask,
+            events: EventRing::new(Self::CAPACITY, Backpressure::DropOldest),
+        });
+        Self { handle, id, mask }
+    }
+}
+
+impl Iterator for LineEventFilter<'_> {
+    type Item = Result<LineEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.mask == 0 {
+            return None;
+        }
+        let subscriptions = &self.handle.subscriptions;
+        let mut state = subscriptions.lock();
+        loop {
+            if let Some(event) = state
+                .queue(self.id)
+                .and_then(|queue| queue.events.try_pop())
+            {
+                return Some(Ok(event));
+            }
+            if state.reading {
+                state = subscriptions
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
+
+            state.reading = true;
+            drop(state);
+            let mut buf = [LineEvent::default()];
+            let read = LineEvent::read(self.handle, &mut buf);
+            state = subscriptions.lock();
+            state.reading = false;
+            subscriptions.changed.notify_all();
+
+            match read {
+                Ok(0) => return None,
+                Ok(_) => {
+                    let [event] = buf;
+                    let Some(index) = index_of_event(self.handle.offsets(), &event) else {
+                        continue;
+                    };
+                    for queue in &state.queues {
+                        if queue.mask & 1 << index != 0 {
+                            queue.events.push(event.clone());
+                        }
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl Drop for LineEventFilter<'_> {
+    fn drop(&mut self) {
+        let mut state = self.handle.subscriptions.lock();
+        state.queues.retain(|queue| queue.id != self.id);
+    }
+}
+
+/// The queues of the [`LineEventFilter`]s of a handle.
+///
+/// The iterators take turns reading the handle: the one reading queues the
+/// events for every iterator selecting their line, the others wait for
+/// their queue to fill or for the read to end.
+#[derive(Debug, Default)]
+pub(crate) struct Subscriptions {
+    state: Mutex<SubscriptionState>,
+    /// Notified when a read ends.
+    changed: Condvar,
+}
+
+impl Subscriptions {
+    fn lock(&self) -> MutexGuard<'_, SubscriptionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug, Default)]
+struct SubscriptionState {
+    /// Whether an iterator is reading the handle.
+    reading: bool,
+    next_id: u64,
+    queues: Vec<Queue>,
+}
+
+impl SubscriptionState {
+    fn queue(&self, id: u64) -> Option<&Queue> {
+        self.queues.iter().find(|queue| queue.id == id)
+    }
+}
+
+#[derive(Debug)]
+struct Queue {
+    id: u64,
+    mask: u64,
+    events: EventRing<LineEvent>,
+}
+
+/// The index of the line of `event` in `offsets`.
+fn index_of_event(offsets: &[u32], event: &LineEvent) -> Option<usize> {
+    // v1 event handles hold a single line
+    #[cfg(feature = "v1")]
+    let index = Some(0);
+    #[cfg(feature = "v2")]
+    let index = index_of_offset(offsets, event.offset());
+    index
+}
+
+/// Reads as many whole `T` records from `fd` as fit in `buf`,
+/// returning the number of records read.
+///
+/// A partial or invalid record is [`std::io::ErrorKind::InvalidData`],
+/// see [`crate::parse`].
+pub(crate) fn read_records<T: Pod>(fd: RawFd, buf: &mut [T]) -> Result<usize> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    #[cfg(feature = "tracing")]
+    let start = std::time::Instant::now();
+    let ptr = buf.as_mut_ptr() as *mut libc::c_void;
+    // SAFETY: the kernel writes at most `size_of_val(buf)` bytes,
+    // and any bit pattern is valid for `T`
+    let res = match unsafe { libc::read(fd, ptr, std::mem::size_of_val(buf)) } {
+        -1 => Err(std::io::Error::last_os_error().into()),
+        n => record_count::<T>(n.unsigned_abs())
+            .and_then(|len| buf[..len].iter().try_for_each(Pod::validate).map(|()| len))
+            .map_err(Into::into),
+    };
+    #[cfg(feature = "tracing")]
+    {
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        match &res {
+            Ok(records) => tracing::trace!(fd, records, elapsed_us, "event read"),
+            Err(e) => tracing::debug!(fd, elapsed_us, error = %e, "event read failed"),
+        }
+    }
+    res
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{config::Edge, line::HandleFlags, mock::MockChip};
+
+    fn timestamps(events: &mut LineEventFilter<'_>) -> Vec<u64> {
+        events.map(|event| event.unwrap().timestamp_ns()).collect()
+    }
+
+    #[cfg(feature = "v2")]
+    #[test]
+    fn subscriptions_get_the_events_of_their_lines() {
+        use crate::line::LineRequest;
+
+        let mock = MockChip::new(8);
+        let chip = Chip::from_backend("mock", mock.clone());
+        let request = LineRequest::builder()
+            .set_offsets([3u32, 5, 6])
+            .build()
+            .unwrap();
+        let handle = chip.get_line(request).unwrap();
+        let mut three = handle.events_for([3]);
+        let mut five = handle.events_for([5]);
+        for (offset, timestamp_ns) in [(3, 1), (6, 2), (5, 3), (3, 4)] {
+            mock.inject_event(offset, LineEventType::RisingEdge, timestamp_ns);
+        }
+
+        // reads and queues the event of line 3, discards that of line 6
+        assert_eq!(five.next().unwrap().unwrap().timestamp_ns(), 3);
+        assert_eq!(timestamps(&mut three), [1, 4]);
+        assert!(five.next().is_none());
+    }
+
+    #[test]
+    fn subscriptions_of_a_line_all_get_its_events() {
+        let mock = MockChip::new(8);
+        let chip = Chip::from_backend("mock", mock.clone());
+        let handle = chip
+            .request_edge_events(3, Edge::Both, HandleFlags::empty(), "test")
+            .unwrap();
+        let mut first = handle.events_for([3]);
+        let mut second = handle.events_for([3]);
+        mock.inject_event(3, LineEventType::RisingEdge, 1);
+        mock.inject_event(3, LineEventType::FallingEdge, 2);
+
+        assert_eq!(timestamps(&mut first), [1, 2]);
+        drop(first);
+        assert_eq!(timestamps(&mut second), [1, 2]);
+        assert!(handle.events_for([4]).next().is_none());
+    }
+}
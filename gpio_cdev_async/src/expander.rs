@@ -0,0 +1,913 @@
+//! I²C GPIO expanders presenting as a [`Chip`].
+//!
+//! An [`Expander`] drives an MCP23017 or a PCF8574 over an [`I2cBus`], either
+//! a Linux `/dev/i2c-N` adapter ([`LinuxI2c`]) or two lines of another chip
+//! ([`BitBangI2c`]). It is a [`GpioBackend`], so its lines are requested, read
+//! and written through [`Chip::from_backend`] like kernel lines.
+//!
+//! Edge events need the INT pin of the expander wired to a line of a kernel
+//! chip, see [`Expander::set_interrupt`]. On each interrupt the pins are read
+//! and compared to their previous levels, so pulses shorter than an I²C read
+//! are missed. Events carry the timestamp of the interrupt.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{chip::Chip, expander::{Expander, LinuxI2c}, line::{HandleFlags, LineRequest}};
+//! let bus = LinuxI2c::open("/dev/i2c-1").unwrap();
+//! let expander = Expander::mcp23017(bus, 0x20).unwrap();
+//! // INTA of the expander on line 4 of the SoC
+//! expander
+//!     .set_interrupt(&Chip::new("/dev/gpiochip0").unwrap(), 4)
+//!     .unwrap();
+//! let chip = Chip::from_backend("mcp23017@0x20", expander);
+//!
+//! # #[cfg(feature = "v1")]
+//! # let flags = HandleFlags::REQUEST_OUTPUT;
+//! # #[cfg(feature = "v2")]
+//! # let flags = HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT;
+//! let request = LineRequest::builder()
+//!     .set_flags(flags)
+//!     .set_offsets([8u32])
+//!     .build()
+//!     .unwrap();
+//! // GPB0
+//! let handle = chip.get_line(request).unwrap();
+//! ```
+//!
+//! # Notes
+//! - The INT pin is configured active-low and open-drain, the interrupt line
+//!   needs a pull-up. v2 requests the bias, v1 relies on an external one.
+//! - MCP23017 lines have no pull-downs and PCF8574 lines are always pulled up,
+//!   open-drain and open-source outputs are unsupported.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Debug,
+    fs::{File, OpenOptions},
+    hint,
+    io::{self, Read, Write},
+    os::fd::AsRawFd,
+    path::Path,
+    sync::{Arc, Condvar, Mutex, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "v2")]
+use crate::line::PinAttribute;
+#[cfg(feature = "v1")]
+use crate::line::{EventFlags, EventRequest};
+use crate::{
+    backend::{GpioBackend, LineBackend},
+    chip::{Chip, ChipInfo},
+    error::ioctl_error,
+    event::{LineEvent, LineEventType},
+    line::{HandleFlags, LineFlags, LineHandle, LineInfo, LineRequest},
+    IoctlKind, Result,
+};
+
+/// The `I2C_SLAVE` ioctl of i2c-dev, selecting the address of the transfers.
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+
+/// How many events a handle buffers before dropping new ones, like the kernel.
+const EVENT_BACKLOG: usize = 256;
+
+nix::ioctl_write_int_bad!(i2c_slave, I2C_SLAVE);
+
+/// A bus to I²C devices.
+pub trait I2cBus: Debug + Send {
+    /// Writes `bytes` to the device at the 7-bit `address`.
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<()>;
+
+    /// Reads `buf.len()` bytes from the device at the 7-bit `address`.
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<()>;
+}
+
+/// An I²C adapter of the kernel, e.g. `/dev/i2c-1`.
+#[derive(Debug)]
+pub struct LinuxI2c {
+    file: File,
+    /// The address selected by the last `I2C_SLAVE`.
+    address: Option<u8>,
+}
+
+impl LinuxI2c {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self {
+            file,
+            address: None,
+        })
+    }
+
+    fn select(&mut self, address: u8) -> Result<()> {
+        if self.address != Some(address) {
+            unsafe { i2c_slave(self.file.as_raw_fd(), address.into()) }.map_err(io::Error::from)?;
+            self.address = Some(address);
+        }
+        Ok(())
+    }
+}
+
+impl I2cBus for LinuxI2c {
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<()> {
+        self.select(address)?;
+        self.file.write_all(bytes)?;
+        Ok(())
+    }
+
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<()> {
+        self.select(address)?;
+        self.file.read_exact(buf)?;
+        Ok(())
+    }
+}
+
+/// An I²C bus bit-banged on two open-drain lines of a chip.
+///
+/// SDA and SCL need pull-ups. Clock stretching by the devices is honoured.
+#[derive(Debug)]
+pub struct BitBangI2c {
+    /// SDA at index 0, SCL at index 1.
+    handle: LineHandle,
+    sda: bool,
+    scl: bool,
+    half_period: Duration,
+}
+
+impl BitBangI2c {
+    /// How long SCL may be stretched before a transfer fails.
+    const STRETCH_TIMEOUT: Duration = Duration::from_millis(10);
+
+    /// Requests the `sda` and `scl` lines of `chip`, released high.
+    ///
+    /// The bus runs at up to 100 kHz, see [`BitBangI2c::set_half_period`].
+    pub fn new(chip: &Chip, sda: u32, scl: u32) -> Result<Self> {
+        #[cfg(feature = "v1")]
+        let request = LineRequest::builder()
+            .set_flags(HandleFlags::REQUEST_OUTPUT | HandleFlags::REQUEST_OPEN_DRAIN)
+            .set_offsets([(sda, 1u8), (scl, 1u8)]);
+        #[cfg(feature = "v2")]
+        let request = LineRequest::builder()
+            .set_flags(
+                HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT | HandleFlags::GPIO_V2_LINE_FLAG_OPEN_DRAIN,
+            )
+            .set_offsets([
+                (sda, [PinAttribute::Value(1)]),
+                (scl, [PinAttribute::Value(1)]),
+            ]);
+        let request = request.set_consumer("i2c-bitbang").build()?;
+        Ok(Self {
+            handle: chip.get_line(request)?,
+            sda: true,
+            scl: true,
+            half_period: Duration::from_micros(5),
+        })
+    }
+
+    /// The time SCL stays low and high, 5 µs by default.
+    pub fn set_half_period(mut self, half_period: Duration) -> Self {
+        self.half_period = half_period;
+        self
+    }
+
+    fn drive(&mut self, sda: bool, scl: bool) -> Result<()> {
+        self.sda = sda;
+        self.scl = scl;
+        let offsets = self.handle.offsets();
+        #[cfg(feature = "v1")]
+        self.handle.set_values(
+            [(offsets[0], sda), (offsets[1], scl)]
+                .into_iter()
+                .filter_map(|(offset, high)| high.then_some(offset)),
+        )?;
+        #[cfg(feature = "v2")]
+        self.handle
+            .set_values([(offsets[0], sda), (offsets[1], scl)])?;
+        Ok(())
+    }
+
+    fn sda_high(&self) -> Result<bool> {
+        let offset = self.handle.offsets()[0];
+        Ok(self.handle.get_values()?.value_of_offset(offset) == Some(1))
+    }
+
+    fn delay(&self) {
+        let start = Instant::now();
+        while start.elapsed() < self.half_period {
+            hint::spin_loop();
+        }
+    }
+
+    /// Releases SCL and waits for the devices to stop stretching it.
+    fn release_scl(&mut self) -> Result<()> {
+        self.drive(self.sda, true)?;
+        let scl = self.handle.offsets()[1];
+        let start = Instant::now();
+        while self.handle.get_values()?.value_of_offset(scl) != Some(1) {
+            if start.elapsed() > Self::STRETCH_TIMEOUT {
+                return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+            }
+            thread::yield_now();
+        }
+        self.delay();
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<()> {
+        self.drive(true, self.scl)?;
+        self.release_scl()?;
+        self.drive(false, true)?;
+        self.delay();
+        self.drive(false, false)?;
+        self.delay();
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.drive(false, false)?;
+        self.delay();
+        self.release_scl()?;
+        self.drive(true, true)?;
+        self.delay();
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<()> {
+        self.drive(bit, false)?;
+        self.delay();
+        self.release_scl()?;
+        self.drive(bit, false)
+    }
+
+    fn read_bit(&mut self) -> Result<bool> {
+        self.drive(true, false)?;
+        self.delay();
+        self.release_scl()?;
+        let bit = self.sda_high()?;
+        self.drive(true, false)?;
+        Ok(bit)
+    }
+
+    /// Writes a byte, failing with `ENXIO` if it is not acknowledged.
+    fn write_byte(&mut self, byte: u8) -> Result<()> {
+        for i in (0..8).rev() {
+            self.write_bit(byte >> i & 1 != 0)?;
+        }
+        if self.read_bit()? {
+            return Err(io::Error::from_raw_os_error(libc::ENXIO).into());
+        }
+        Ok(())
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = byte << 1 | u8::from(self.read_bit()?);
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    /// Runs `transfer` between a start and a stop condition, the stop is
+    /// sent even if `transfer` fails.
+    fn transaction(&mut self, transfer: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        self.start()?;
+        let result = transfer(self);
+        let stop = self.stop();
+        result.and(stop)
+    }
+}
+
+impl I2cBus for BitBangI2c {
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<()> {
+        self.transaction(|bus| {
+            bus.write_byte(address << 1)?;
+            bytes.iter().try_for_each(|&byte| bus.write_byte(byte))
+        })
+    }
+
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<()> {
+        self.transaction(|bus| {
+            bus.write_byte(address << 1 | 1)?;
+            let len = buf.len();
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte = bus.read_byte(i + 1 < len)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The supported expanders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Model {
+    Mcp23017,
+    Pcf8574,
+}
+
+impl Model {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mcp23017 => "mcp23017",
+            Self::Pcf8574 => "pcf8574",
+        }
+    }
+
+    fn lines(self) -> u32 {
+        match self {
+            Self::Mcp23017 => 16,
+            Self::Pcf8574 => 8,
+        }
+    }
+
+    fn line_name(self, offset: u32) -> String {
+        match self {
+            Self::Mcp23017 => {
+                let port = if offset < 8 { 'A' } else { 'B' };
+                format!("GP{port}{}", offset % 8)
+            }
+            Self::Pcf8574 => format!("P{offset}"),
+        }
+    }
+}
+
+/// The MCP23017 registers, in the pairs of ports A and B of `IOCON.BANK = 0`.
+mod mcp23017 {
+    pub(super) const IODIR: u8 = 0x00;
+    pub(super) const GPINTEN: u8 = 0x04;
+    pub(super) const INTCON: u8 = 0x08;
+    pub(super) const IOCON: u8 = 0x0a;
+    pub(super) const GPPU: u8 = 0x0c;
+    pub(super) const GPIO: u8 = 0x12;
+    pub(super) const OLAT: u8 = 0x14;
+
+    /// INTA and INTB mirrored, open-drain.
+    pub(super) const IOCON_MIRROR_ODR: u8 = 1 << 6 | 1 << 2;
+}
+
+/// How a requested line is configured.
+#[derive(Debug, Clone, Copy, Default)]
+struct LineConfig {
+    output: bool,
+    active_low: bool,
+    pull_up: bool,
+    rising: bool,
+    falling: bool,
+}
+
+impl LineConfig {
+    fn new(flags: HandleFlags, kind: IoctlKind) -> Result<Self> {
+        #[cfg(feature = "v1")]
+        let (config, unsupported) = (
+            Self {
+                output: flags.contains(HandleFlags::REQUEST_OUTPUT),
+                active_low: flags.contains(HandleFlags::REQUEST_ACTIVE_LOW),
+                pull_up: flags.contains(HandleFlags::REQUEST_BIAS_PULL_UP),
+                rising: false,
+                falling: false,
+            },
+            HandleFlags::REQUEST_OPEN_DRAIN
+                | HandleFlags::REQUEST_OPEN_SOURCE
+                | HandleFlags::REQUEST_BIAS_PULL_DOWN,
+        );
+        #[cfg(feature = "v2")]
+        let (config, unsupported) = (
+            Self {
+                output: flags.contains(HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT),
+                active_low: flags.contains(HandleFlags::GPIO_V2_LINE_FLAG_ACTIVE_LOW),
+                pull_up: flags.contains(HandleFlags::GPIO_V2_LINE_FLAG_BIAS_PULL_UP),
+                rising: flags.contains(HandleFlags::GPIO_V2_LINE_FLAG_EDGE_RISING),
+                falling: flags.contains(HandleFlags::GPIO_V2_LINE_FLAG_EDGE_FALLING),
+            },
+            HandleFlags::GPIO_V2_LINE_FLAG_OPEN_DRAIN
+                | HandleFlags::GPIO_V2_LINE_FLAG_OPEN_SOURCE
+                | HandleFlags::GPIO_V2_LINE_FLAG_BIAS_PULL_DOWN,
+        );
+        if flags.intersects(unsupported) || config.output && config.edges() {
+            return Err(ioctl_error(kind, nix::Error::EINVAL));
+        }
+        Ok(config)
+    }
+
+    fn edges(&self) -> bool {
+        self.rising || self.falling
+    }
+
+    fn flags(&self) -> LineFlags {
+        let mut flags = LineFlags::empty();
+        #[cfg(feature = "v1")]
+        {
+            flags.set(LineFlags::IS_OUT, self.output);
+            flags.set(LineFlags::ACTIVE_LOW, self.active_low);
+            flags.set(LineFlags::BIAS_PULL_UP, self.pull_up);
+        }
+        #[cfg(feature = "v2")]
+        {
+            flags.set(LineFlags::GPIO_V2_LINE_FLAG_OUTPUT, self.output);
+            flags.set(LineFlags::GPIO_V2_LINE_FLAG_INPUT, !self.output);
+            flags.set(LineFlags::GPIO_V2_LINE_FLAG_ACTIVE_LOW, self.active_low);
+            flags.set(LineFlags::GPIO_V2_LINE_FLAG_BIAS_PULL_UP, self.pull_up);
+            flags.set(LineFlags::GPIO_V2_LINE_FLAG_EDGE_RISING, self.rising);
+            flags.set(LineFlags::GPIO_V2_LINE_FLAG_EDGE_FALLING, self.falling);
+        }
+        flags
+    }
+}
+
+/// The edge events of a handle not read yet.
+#[derive(Debug, Default)]
+struct EventQueue {
+    state: Mutex<Events>,
+    ready: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Events {
+    events: VecDeque<LineEvent>,
+    /// The sequence numbers of the last event, of the handle and per line.
+    seqno: u32,
+    line_seqnos: BTreeMap<u32, u32>,
+    /// Set once the interrupt is no longer watched.
+    closed: bool,
+}
+
+impl EventQueue {
+    fn push(&self, offset: u32, event_type: LineEventType, timestamp_ns: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.events.len() >= EVENT_BACKLOG {
+            return;
+        }
+        state.seqno += 1;
+        let line_seqno = state.line_seqnos.entry(offset).or_default();
+        *line_seqno += 1;
+        let line_seqno = *line_seqno;
+        let event = LineEvent::new(offset, event_type, timestamp_ns, state.seqno, line_seqno);
+        state.events.push_back(event);
+        self.ready.notify_all();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+#[derive(Debug)]
+struct Line {
+    consumer: String,
+    config: LineConfig,
+    events: Arc<EventQueue>,
+}
+
+/// The expander and the shadows of its registers, one bit per line.
+#[derive(Debug)]
+struct Inner {
+    model: Model,
+    address: u8,
+    bus: Box<dyn I2cBus>,
+    /// Set for inputs, as `IODIR`.
+    inputs: u16,
+    /// The driven levels of the outputs.
+    latch: u16,
+    pull_ups: u16,
+    interrupts: u16,
+    /// The pin levels as of the last interrupt.
+    levels: u16,
+    /// Whether the INT pin is watched.
+    watched: bool,
+    lines: BTreeMap<u32, Line>,
+}
+
+impl Inner {
+    fn read_pins(&mut self) -> Result<u16> {
+        match self.model {
+            Model::Mcp23017 => self.read_register(mcp23017::GPIO),
+            Model::Pcf8574 => {
+                let mut buf = [0];
+                self.bus.read(self.address, &mut buf)?;
+                Ok(buf[0].into())
+            }
+        }
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u16> {
+        let mut buf = [0; 2];
+        self.bus.write(self.address, &[register])?;
+        self.bus.read(self.address, &mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn write_register(&mut self, register: u8, value: u16) -> Result<()> {
+        let [a, b] = value.to_le_bytes();
+        self.bus.write(self.address, &[register, a, b])
+    }
+
+    /// Writes the latch, inputs of a PCF8574 are written high.
+    fn write_latch(&mut self) -> Result<()> {
+        match self.model {
+            Model::Mcp23017 => self.write_register(mcp23017::OLAT, self.latch),
+            Model::Pcf8574 => {
+                let port = (self.latch | self.inputs) as u8;
+                self.bus.write(self.address, &[port])
+            }
+        }
+    }
+
+    fn write_config(&mut self) -> Result<()> {
+        if self.model == Model::Mcp23017 {
+            // outputs start at their latched values
+            self.write_register(mcp23017::OLAT, self.latch)?;
+            self.write_register(mcp23017::GPPU, self.pull_ups)?;
+            self.write_register(mcp23017::GPINTEN, self.interrupts)?;
+            self.write_register(mcp23017::IODIR, self.inputs)
+        } else {
+            self.write_latch()
+        }
+    }
+
+    fn configure(&mut self, offset: u32, config: LineConfig, value: u8) {
+        let bit = 1 << offset;
+        let set = |register: &mut u16, on: bool| {
+            if on {
+                *register |= bit;
+            } else {
+                *register &= !bit;
+            }
+        };
+        set(&mut self.inputs, !config.output);
+        set(&mut self.pull_ups, config.pull_up);
+        set(&mut self.interrupts, config.edges());
+        if config.output {
+            set(&mut self.latch, (value != 0) != config.active_low);
+        }
+    }
+}
+
+/// An I²C GPIO expander, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Expander {
+    label: String,
+    shared: Arc<Mutex<Inner>>,
+}
+
+impl Expander {
+    /// An MCP23017 at `address`, 16 lines: port A at offsets 0 to 7, port B
+    /// at 8 to 15.
+    ///
+    /// The lines keep their current configuration until requested.
+    pub fn mcp23017(bus: impl I2cBus + 'static, address: u8) -> Result<Self> {
+        let mut inner = Self::inner(Model::Mcp23017, Box::new(bus), address);
+        inner
+            .bus
+            .write(address, &[mcp23017::IOCON, mcp23017::IOCON_MIRROR_ODR])?;
+        // interrupts on any change
+        inner.write_register(mcp23017::INTCON, 0)?;
+        inner.write_register(mcp23017::GPINTEN, 0)?;
+        inner.inputs = inner.read_register(mcp23017::IODIR)?;
+        inner.latch = inner.read_register(mcp23017::OLAT)?;
+        inner.pull_ups = inner.read_register(mcp23017::GPPU)?;
+        Ok(Self::new(inner))
+    }
+
+    /// A PCF8574 at `address`, 8 lines.
+    ///
+    /// Its outputs cannot be read back, all the lines are released high.
+    pub fn pcf8574(bus: impl I2cBus + 'static, address: u8) -> Result<Self> {
+        let mut inner = Self::inner(Model::Pcf8574, Box::new(bus), address);
+        inner.inputs = 0xff;
+        inner.pull_ups = 0xff;
+        inner.write_latch()?;
+        Ok(Self::new(inner))
+    }
+
+    fn inner(model: Model, bus: Box<dyn I2cBus>, address: u8) -> Inner {
+        Inner {
+            model,
+            address,
+            bus,
+            inputs: 0,
+            latch: 0,
+            pull_ups: 0,
+            interrupts: 0,
+            levels: 0,
+            watched: false,
+            lines: BTreeMap::new(),
+        }
+    }
+
+    fn new(inner: Inner) -> Self {
+        Self {
+            label: inner.model.name().into(),
+            shared: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Watches the INT pin of the expander, wired to the line at `offset` of
+    /// `chip`, to deliver edge events. INTA and INTB of an MCP23017 are
+    /// mirrored, either works.
+    ///
+    /// The line is read by a thread until the next interrupt after the
+    /// expander is dropped.
+    pub fn set_interrupt(&self, chip: &Chip, offset: u32) -> Result<()> {
+        let mut inner = self.shared.lock().unwrap();
+        if inner.watched {
+            return Err(
+                io::Error::new(io::ErrorKind::AlreadyExists, "interrupt already set").into(),
+            );
+        }
+        let consumer = format!("{}-int", inner.model.name());
+
+        #[cfg(feature = "v1")]
+        let int = chip.get_event_line(EventRequest::new(
+            offset,
+            HandleFlags::REQUEST_INPUT,
+            EventFlags::REQUEST_FALLING_EDGE,
+            &consumer,
+        ))?;
+        #[cfg(feature = "v2")]
+        let int = chip.get_line(
+            LineRequest::builder()
+                .set_flags(
+                    HandleFlags::GPIO_V2_LINE_FLAG_INPUT
+                        | HandleFlags::GPIO_V2_LINE_FLAG_EDGE_FALLING
+                        | HandleFlags::GPIO_V2_LINE_FLAG_BIAS_PULL_UP,
+                )
+                .set_consumer(&consumer)
+                .set_offsets([offset])
+                .build()?,
+        )?;
+
+        // reading the pins also clears a pending interrupt
+        inner.levels = inner.read_pins()?;
+        inner.watched = true;
+        let shared = Arc::downgrade(&self.shared);
+        thread::spawn(move || watch_interrupt(&shared, &int));
+        Ok(())
+    }
+
+    /// The label of the chip, the name of the expander by default.
+    pub fn set_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    fn request(
+        &self,
+        kind: IoctlKind,
+        offsets: &[u32],
+        consumer: String,
+        configs: Vec<(LineConfig, u8)>,
+    ) -> Result<ExpanderLine> {
+        let mut inner = self.shared.lock().unwrap();
+        let unique: BTreeSet<_> = offsets.iter().collect();
+        if offsets.is_empty()
+            || unique.len() != offsets.len()
+            || offsets.iter().any(|&offset| offset >= inner.model.lines())
+        {
+            return Err(ioctl_error(kind, nix::Error::EINVAL));
+        }
+        if offsets
+            .iter()
+            .any(|offset| inner.lines.contains_key(offset))
+        {
+            return Err(ioctl_error(kind, nix::Error::EBUSY));
+        }
+        if !inner.watched && configs.iter().any(|(config, _)| config.edges()) {
+            return Err(no_interrupt());
+        }
+
+        for (&offset, &(config, value)) in offsets.iter().zip(&configs) {
+            inner.configure(offset, config, value);
+        }
+        inner.write_config()?;
+
+        let events = Arc::new(EventQueue::default());
+        for (&offset, &(config, _)) in offsets.iter().zip(&configs) {
+            let line = Line {
+                consumer: consumer.clone(),
+                config,
+                events: events.clone(),
+            };
+            inner.lines.insert(offset, line);
+        }
+        Ok(ExpanderLine {
+            offsets: offsets.into(),
+            shared: self.shared.clone(),
+            events,
+        })
+    }
+}
+
+fn no_interrupt() -> crate::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "edge events need the INT pin, see Expander::set_interrupt",
+    )
+    .into()
+}
+
+/// The configurations and initial values of the lines of `request`.
+fn line_configs(request: &LineRequest, kind: IoctlKind) -> Result<Vec<(LineConfig, u8)>> {
+    request
+        .offsets()
+        .iter()
+        .map(|&offset| {
+            #[cfg(feature = "v1")]
+            let flags = request.flags();
+            #[cfg(feature = "v2")]
+            let flags = request.flags_of_offset(offset).unwrap_or(request.flags());
+            let value = request.default_value_of_offset(offset).unwrap_or(0);
+            Ok((LineConfig::new(flags, kind)?, value))
+        })
+        .collect()
+}
+
+/// Turns the interrupts of the expander into edge events, until the
+/// expander is dropped or the interrupt line fails.
+fn watch_interrupt(shared: &Weak<Mutex<Inner>>, int: &LineHandle) {
+    for event in int.events() {
+        let (Some(shared), Ok(event)) = (shared.upgrade(), event) else {
+            break;
+        };
+        let mut inner = shared.lock().unwrap();
+        // a missed read leaves the INT pin asserted, no interrupt would follow
+        let Ok(levels) = inner.read_pins() else {
+            break;
+        };
+        let changed = levels ^ inner.levels;
+        inner.levels = levels;
+
+        let timestamp_ns = event.timestamp_ns();
+        for (&offset, line) in &inner.lines {
+            if changed >> offset & 1 == 0 {
+                continue;
+            }
+            let active = (levels >> offset & 1 != 0) != line.config.active_low;
+            if active && line.config.rising {
+                line.events
+                    .push(offset, LineEventType::RisingEdge, timestamp_ns);
+            } else if !active && line.config.falling {
+                line.events
+                    .push(offset, LineEventType::FallingEdge, timestamp_ns);
+            }
+        }
+    }
+
+    if let Some(shared) = shared.upgrade() {
+        let mut inner = shared.lock().unwrap();
+        inner.watched = false;
+        for line in inner.lines.values() {
+            line.events.close();
+        }
+    }
+}
+
+impl GpioBackend for Expander {
+    fn chip_info(&self) -> Result<ChipInfo> {
+        let inner = self.shared.lock().unwrap();
+        let name = format!("{}@{:#04x}", inner.model.name(), inner.address);
+        Ok(ChipInfo::new(&name, &self.label, inner.model.lines()))
+    }
+
+    fn line_info(&self, offset: u32) -> Result<LineInfo> {
+        let inner = self.shared.lock().unwrap();
+        if offset >= inner.model.lines() {
+            return Err(ioctl_error(IoctlKind::GetLineInfo, nix::Error::EINVAL));
+        }
+        let name = inner.model.line_name(offset);
+        let info = match inner.lines.get(&offset) {
+            Some(line) => {
+                let mut flags = line.config.flags();
+                #[cfg(feature = "v1")]
+                flags.insert(LineFlags::KERNEL);
+                #[cfg(feature = "v2")]
+                flags.insert(LineFlags::GPIO_V2_LINE_FLAG_USED);
+                LineInfo::new(offset, flags, name, &line.consumer)
+            }
+            None => {
+                let config = LineConfig {
+                    output: inner.inputs >> offset & 1 == 0,
+                    pull_up: inner.pull_ups >> offset & 1 != 0,
+                    ..Default::default()
+                };
+                LineInfo::new(offset, config.flags(), name, "")
+            }
+        };
+        Ok(info)
+    }
+
+    fn request_lines(&self, request: LineRequest) -> Result<Box<dyn LineBackend>> {
+        let configs = line_configs(&request, IoctlKind::GetLine)?;
+        let consumer = request.consumer().into_owned();
+        let line = self.request(IoctlKind::GetLine, request.offsets(), consumer, configs)?;
+        Ok(Box::new(line))
+    }
+
+    #[cfg(feature = "v1")]
+    fn request_event_line(&self, request: EventRequest) -> Result<Box<dyn LineBackend>> {
+        let kind = IoctlKind::GetLineEvent;
+        let mut config = LineConfig::new(request.handle_flags(), kind)?;
+        if config.output {
+            return Err(ioctl_error(kind, nix::Error::EINVAL));
+        }
+        let edges = request.event_flags();
+        config.rising = edges.contains(EventFlags::REQUEST_RISING_EDGE);
+        config.falling = edges.contains(EventFlags::REQUEST_FALLING_EDGE);
+        let consumer = request.consumer().into_owned();
+        let line = self.request(kind, &[request.offset()], consumer, vec![(config, 0)])?;
+        Ok(Box::new(line))
+    }
+}
+
+/// Lines requested from an [`Expander`], released when dropped.
+#[derive(Debug)]
+struct ExpanderLine {
+    offsets: Vec<u32>,
+    shared: Arc<Mutex<Inner>>,
+    events: Arc<EventQueue>,
+}
+
+impl LineBackend for ExpanderLine {
+    fn get_values(&self, mask: u64) -> Result<u64> {
+        let mut inner = self.shared.lock().unwrap();
+        let levels = inner.read_pins()?;
+        let mut bits = 0;
+        for (index, offset) in self.offsets.iter().enumerate() {
+            let active_low = inner.lines[offset].config.active_low;
+            if mask >> index & 1 != 0 && (levels >> offset & 1 != 0) != active_low {
+                bits |= 1 << index;
+            }
+        }
+        Ok(bits)
+    }
+
+    fn set_values(&self, mask: u64, bits: u64) -> Result<()> {
+        let mut inner = self.shared.lock().unwrap();
+        let mut latch = inner.latch;
+        for (index, offset) in self.offsets.iter().enumerate() {
+            if mask >> index & 1 == 0 {
+                continue;
+            }
+            let config = inner.lines[offset].config;
+            if !config.output {
+                return Err(ioctl_error(IoctlKind::SetValues, nix::Error::EPERM));
+            }
+            if (bits >> index & 1 != 0) != config.active_low {
+                latch |= 1 << offset;
+            } else {
+                latch &= !(1 << offset);
+            }
+        }
+        inner.latch = latch;
+        inner.write_latch()
+    }
+
+    fn update_config(&self, config: LineRequest) -> Result<()> {
+        let kind = IoctlKind::SetLineConfig;
+        let configs = line_configs(&config, kind)?;
+        let mut inner = self.shared.lock().unwrap();
+        if !inner.watched && configs.iter().any(|(config, _)| config.edges()) {
+            return Err(no_interrupt());
+        }
+        for (offset, (config, value)) in config.offsets().iter().zip(configs) {
+            let Some(line) = inner.lines.get_mut(offset) else {
+                return Err(ioctl_error(kind, nix::Error::EINVAL));
+            };
+            line.config = config;
+            inner.configure(*offset, config, value);
+        }
+        inner.write_config()
+    }
+
+    fn read_events(&self, buf: &mut [LineEvent]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.events.state.lock().unwrap();
+        while state.events.is_empty() && !state.closed {
+            state = self.events.ready.wait(state).unwrap();
+        }
+        let len = buf.len().min(state.events.len());
+        for (slot, event) in buf.iter_mut().zip(state.events.drain(..len)) {
+            *slot = event;
+        }
+        Ok(len)
+    }
+}
+
+impl Drop for ExpanderLine {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock().unwrap();
+        for offset in &self.offsets {
+            inner.lines.remove(offset);
+            inner.interrupts &= !(1 << offset);
+        }
+        if inner.model == Model::Mcp23017 {
+            let interrupts = inner.interrupts;
+            let _ = inner.write_register(mcp23017::GPINTEN, interrupts);
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Passing line handles between processes over unix sockets.
+//!
+//! The request fd travels as `SCM_RIGHTS` ancillary data, the rest of the
+//! handle as a [`HandleDescriptor`] in the payload, after its length as a
+//! little-endian `u32`. This lets a privileged
+//! helper request lines and hand them to a sandboxed worker without access
+//! to the chip.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use std::os::unix::net::UnixStream;
+//! # use gpio_cdev_async::{chip::Chip, config::Direction, fdpass, line::{HandleFlags, LineRequest}};
+//! let (helper, worker) = UnixStream::pair().unwrap();
+//!
+//! // in the privileged helper
+//! # #[cfg(feature = "v1")]
+//! # let flags = HandleFlags::REQUEST_OUTPUT;
+//! # #[cfg(feature = "v2")]
+//! # let flags = HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT;
+//! let chip = Chip::new("/dev/gpiochip0").unwrap();
+//! let request = LineRequest::builder()
+//!     .set_flags(flags)
+//!     .set_offsets([6u32])
+//!     .build()
+//!     .unwrap();
+//! let handle = chip.get_line(request).unwrap();
+//! fdpass::send_handle(&helper, &handle).unwrap();
+//! drop(handle);
+//!
+//! // in the worker
+//! let handle = fdpass::recv_handle(&worker).unwrap();
+//! assert_eq!(handle.offsets(), [6]);
+//! assert_eq!(handle.config().lines[0].direction, Some(Direction::Output));
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    ffi::OsStr,
+    io::{self, IoSlice, IoSliceMut, Read},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::{ffi::OsStrExt, net::UnixStream},
+    },
+    path::PathBuf,
+};
+
+use nix::sys::socket::{self, ControlMessage, ControlMessageOwned, MsgFlags};
+
+use crate::{
+    config::LineConfig,
+    line::{HandleConfig, LineHandle},
+    Error, Result,
+};
+
+/// The most offsets of a handle, as bounded by the kernel.
+const MAX_OFFSETS: usize = 64;
+/// The longest chip path of a descriptor, `PATH_MAX`.
+const MAX_PATH_LEN: usize = 4096;
+/// The longest line configuration of a descriptor.
+const MAX_CONFIG_LEN: usize = 256;
+/// The largest payload of a descriptor.
+const MAX_PAYLOAD_LEN: usize =
+    1 + 4 + 4 * MAX_OFFSETS + 4 + MAX_PATH_LEN + MAX_OFFSETS * (4 + MAX_CONFIG_LEN);
+
+/// Everything of a handle but its fd, as sent by [`send_handle`].
+///
+/// # Encoding
+/// Integers are little-endian, byte strings prefixed by their `u32` length:
+/// - the `u8` version of the encoding, [`HandleDescriptor::VERSION`]
+/// - the `u32` number of lines, 1 to 64, followed by their `u32` offsets
+/// - the path of the chip, at most 4096 bytes, empty if unknown
+/// - the configuration of every line in the order of the offsets, in the
+///   textual form of [`config`](crate::config), at most 256 bytes each
+///
+/// Decoding rejects other versions, so a change of the encoding bumps it.
+///
+/// # Examples
+/// ```rust
+/// # use gpio_cdev_async::{fdpass::HandleDescriptor, line::HandleConfig};
+/// let descriptor = HandleDescriptor {
+///     offsets: vec![6, 7],
+///     config: HandleConfig {
+///         chip: Some("/dev/gpiochip0".into()),
+///         lines: vec!["output,value=1".parse().unwrap(), "input,pull-up".parse().unwrap()],
+///     },
+/// };
+/// let payload = descriptor.encode().unwrap();
+/// assert_eq!(HandleDescriptor::decode(&payload).unwrap(), descriptor);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandleDescriptor {
+    pub offsets: Vec<u32>,
+    pub config: HandleConfig,
+}
+
+impl HandleDescriptor {
+    /// The version of the encoding.
+    pub const VERSION: u8 = 1;
+
+    /// Encodes the descriptor, fails with [`io::ErrorKind::InvalidInput`]
+    /// if it exceeds the limits of the encoding.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let invalid =
+            |message| -> Error { io::Error::new(io::ErrorKind::InvalidInput, message).into() };
+        if self.offsets.is_empty() || self.offsets.len() > MAX_OFFSETS {
+            return Err(invalid("a handle has 1 to 64 lines"));
+        }
+        if self.config.lines.len() != self.offsets.len() {
+            return Err(invalid("expected one line configuration per offset"));
+        }
+        let path = self
+            .config
+            .chip
+            .as_deref()
+            .map_or(&[][..], |path| path.as_os_str().as_bytes());
+        if path.len() > MAX_PATH_LEN {
+            return Err(invalid("the chip path is too long"));
+        }
+
+        let mut payload = vec![Self::VERSION];
+        payload.extend_from_slice(&(self.offsets.len() as u32).to_le_bytes());
+        for offset in &self.offsets {
+            payload.extend_from_slice(&offset.to_le_bytes());
+        }
+        put_bytes(&mut payload, path);
+        for line in &self.config.lines {
+            let line = line.to_string();
+            if line.len() > MAX_CONFIG_LEN {
+                return Err(invalid("a line configuration is too long"));
+            }
+            put_bytes(&mut payload, line.as_bytes());
+        }
+        Ok(payload)
+    }
+
+    /// Decodes a descriptor, fails with [`io::ErrorKind::InvalidData`] if
+    /// `payload` is not one of this version.
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+        let mut decoder = Decoder(payload);
+        if decoder.u8()? != Self::VERSION {
+            return Err(invalid("unsupported descriptor version"));
+        }
+        let count = decoder.u32()? as usize;
+        if count == 0 || count > MAX_OFFSETS {
+            return Err(invalid("malformed offsets"));
+        }
+        let offsets = (0..count)
+            .map(|_| decoder.u32())
+            .collect::<Result<Vec<_>>>()?;
+        let path = decoder.bytes(MAX_PATH_LEN)?;
+        let chip = (!path.is_empty()).then(|| PathBuf::from(OsStr::from_bytes(path)));
+        let lines = (0..count)
+            .map(|_| {
+                let line = std::str::from_utf8(decoder.bytes(MAX_CONFIG_LEN)?)
+                    .map_err(|_| invalid("malformed line configuration"))?;
+                line.parse::<LineConfig>()
+                    .map_err(|_| invalid("malformed line configuration"))
+            })
+            .collect::<Result<_>>()?;
+        if !decoder.0.is_empty() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(Self {
+            offsets,
+            config: HandleConfig { chip, lines },
+        })
+    }
+}
+
+fn put_bytes(payload: &mut Vec<u8>, bytes: &[u8]) {
+    payload.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    payload.extend_from_slice(bytes);
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid("truncated message"));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Length-prefixed bytes of at most `max` bytes.
+    fn bytes(&mut self, max: usize) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        if len > max {
+            return Err(invalid("oversized field"));
+        }
+        self.take(len)
+    }
+}
+
+/// Sends `handle` to the peer of `socket`, `handle` stays usable.
+///
+/// Fails with [`io::ErrorKind::Unsupported`] if `handle` is not a kernel
+/// line request, see [`LineHandle::into_parts`].
+pub fn send_handle(socket: &UnixStream, handle: &LineHandle) -> Result<()> {
+    let Some(fd) = handle.kernel_fd() else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "only kernel line requests can be sent",
+        )
+        .into());
+    };
+    let descriptor = HandleDescriptor {
+        offsets: handle.offsets().to_vec(),
+        config: handle.config(),
+    }
+    .encode()?;
+    let mut payload = Vec::with_capacity(4 + descriptor.len());
+    payload.extend_from_slice(&(descriptor.len() as u32).to_le_bytes());
+    payload.extend_from_slice(&descriptor);
+
+    let fds = [fd];
+    socket::sendmsg::<()>(
+        socket.as_raw_fd(),
+        &[IoSlice::new(&payload)],
+        &[ControlMessage::ScmRights(&fds)],
+        MsgFlags::MSG_NOSIGNAL,
+        None,
+    )
+    .map_err(io::Error::from)?;
+    Ok(())
+}
+
+/// Receives a handle sent by [`send_handle`] from the peer of `socket`.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the message is not a
+/// handle. The socket is still usable unless the message was longer than
+/// any descriptor.
+pub fn recv_handle(socket: &UnixStream) -> Result<LineHandle> {
+    // the fd comes with the first bytes of the message
+    let mut len = [0u8; 4];
+    let mut cmsgs = nix::cmsg_space!(RawFd);
+    let mut iov = [IoSliceMut::new(&mut len)];
+    let msg = socket::recvmsg::<()>(
+        socket.as_raw_fd(),
+        &mut iov,
+        Some(&mut cmsgs),
+        MsgFlags::MSG_CMSG_CLOEXEC,
+    )
+    .map_err(io::Error::from)?;
+
+    let read = msg.bytes;
+    let fds_truncated = msg.flags.contains(MsgFlags::MSG_CTRUNC);
+    let mut fds = Vec::new();
+    for cmsg in msg.cmsgs().map_err(io::Error::from)? {
+        if let ControlMessageOwned::ScmRights(received) = cmsg {
+            fds.extend(received);
+        }
+    }
+    // SAFETY: the received fds are new and owned by this process
+    let mut fds: Vec<_> = fds
+        .into_iter()
+        .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+        .collect();
+
+    if read == 0 {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    (&*socket).read_exact(&mut len[read..])?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_PAYLOAD_LEN {
+        return Err(invalid("oversized message"));
+    }
+    let mut payload = vec![0u8; len];
+    (&*socket).read_exact(&mut payload)?;
+
+    let (Some(fd), true, false) = (fds.pop(), fds.is_empty(), fds_truncated) else {
+        return Err(invalid("expected exactly one fd"));
+    };
+    let HandleDescriptor { offsets, config } = HandleDescriptor::decode(&payload)?;
+    Ok(LineHandle::from_parts(fd, offsets, config))
+}
+
+fn invalid(message: &str) -> Error {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Write};
+
+    use super::*;
+
+    fn descriptor() -> HandleDescriptor {
+        HandleDescriptor {
+            offsets: vec![17, 3],
+            config: HandleConfig {
+                chip: Some("/dev/gpiochip1".into()),
+                lines: vec![
+                    "output,open-drain,value=1".parse().unwrap(),
+                    "input,active-low,pull-up,both-edges".parse().unwrap(),
+                ],
+            },
+        }
+    }
+
+    fn null_fd() -> OwnedFd {
+        File::open("/dev/null").unwrap().into()
+    }
+
+    /// Sends `descriptor` with `fds` as `send_handle` does.
+    fn send_raw(socket: &UnixStream, descriptor: &[u8], fds: &[RawFd]) {
+        let len = (descriptor.len() as u32).to_le_bytes();
+        let cmsgs = [ControlMessage::ScmRights(fds)];
+        let cmsgs = if fds.is_empty() { &[][..] } else { &cmsgs[..] };
+        socket::sendmsg::<()>(
+            socket.as_raw_fd(),
+            &[IoSlice::new(&len), IoSlice::new(descriptor)],
+            cmsgs,
+            MsgFlags::empty(),
+            None,
+        )
+        .unwrap();
+    }
+
+    fn is_invalid_data(e: &Error) -> bool {
+        matches!(e, Error::Io(e) if e.kind() == io::ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn descriptors_round_trip() {
+        let mut descriptors = vec![descriptor()];
+        descriptors.push(HandleDescriptor {
+            offsets: (0..64).collect(),
+            config: HandleConfig {
+                chip: None,
+                lines: vec![LineConfig::default(); 64],
+            },
+        });
+        descriptors.push(HandleDescriptor {
+            offsets: vec![u32::MAX],
+            config: HandleConfig {
+                // not UTF-8
+                chip: Some(PathBuf::from(OsStr::from_bytes(b"/dev/gpio\xff"))),
+                lines: vec![
+                    "edges=both,debounce=1500us,event-clock=hte"
+                        .parse()
+                        .unwrap(),
+                ],
+            },
+        });
+        for descriptor in descriptors {
+            let payload = descriptor.encode().unwrap();
+            assert_eq!(payload[0], HandleDescriptor::VERSION);
+            assert_eq!(HandleDescriptor::decode(&payload).unwrap(), descriptor);
+        }
+    }
+
+    #[test]
+    fn encode_rejects_what_does_not_fit() {
+        let mut empty = descriptor();
+        empty.offsets.clear();
+        empty.config.lines.clear();
+        let mut too_many = descriptor();
+        too_many.offsets = (0..65).collect();
+        too_many.config.lines = vec![LineConfig::default(); 65];
+        let mut missing_config = descriptor();
+        missing_config.config.lines.pop();
+        let mut long_path = descriptor();
+        long_path.config.chip = Some("/".repeat(MAX_PATH_LEN + 1).into());
+        for descriptor in [empty, too_many, missing_config, long_path] {
+            assert!(descriptor.encode().is_err(), "{descriptor:?}");
+        }
+    }
+
+    #[test]
+    fn decode_rejects_malformed_payloads() {
+        let payload = descriptor().encode().unwrap();
+        for len in 0..payload.len() {
+            let e = HandleDescriptor::decode(&payload[..len]).unwrap_err();
+            assert!(is_invalid_data(&e), "{len}: {e}");
+        }
+
+        let mut trailing = payload.clone();
+        trailing.push(0);
+        let mut version = payload.clone();
+        version[0] = HandleDescriptor::VERSION + 1;
+        let mut no_lines = payload.clone();
+        no_lines[1..5].copy_from_slice(&0u32.to_le_bytes());
+        let mut too_many = payload.clone();
+        too_many[1..5].copy_from_slice(&65u32.to_le_bytes());
+        // the count claims fewer lines than the payload holds
+        let mut short_count = payload.clone();
+        short_count[1..5].copy_from_slice(&1u32.to_le_bytes());
+        let mut long_path = payload.clone();
+        long_path[13..17].copy_from_slice(&(MAX_PATH_LEN as u32 + 1).to_le_bytes());
+        let mut bad_config = payload.clone();
+        let at = payload.len() - 1;
+        bad_config[at] = b'?';
+        let mut not_utf8 = payload;
+        not_utf8[at] = 0xff;
+        for payload in [
+            trailing,
+            version,
+            no_lines,
+            too_many,
+            short_count,
+            long_path,
+            bad_config,
+            not_utf8,
+        ] {
+            let e = HandleDescriptor::decode(&payload).unwrap_err();
+            assert!(is_invalid_data(&e), "{payload:?}: {e}");
+        }
+    }
+
+    #[test]
+    fn handles_round_trip() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let descriptor = descriptor();
+        let handle =
+            LineHandle::from_parts(null_fd(), &descriptor.offsets, descriptor.config.clone());
+        send_handle(&a, &handle).unwrap();
+        let received = recv_handle(&b).unwrap();
+        assert_eq!(received.offsets(), descriptor.offsets);
+        assert_eq!(received.config(), descriptor.config);
+    }
+
+    #[test]
+    fn recv_rejects_malformed_messages() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let payload = descriptor().encode().unwrap();
+        let fd = null_fd();
+
+        // no fd
+        send_raw(&a, &payload, &[]);
+        assert!(is_invalid_data(&recv_handle(&b).unwrap_err()));
+        // two fds
+        send_raw(&a, &payload, &[fd.as_raw_fd(), fd.as_raw_fd()]);
+        assert!(is_invalid_data(&recv_handle(&b).unwrap_err()));
+        // a count that does not match the offsets
+        let mut count = payload.clone();
+        count[1..5].copy_from_slice(&3u32.to_le_bytes());
+        send_raw(&a, &count, &[fd.as_raw_fd()]);
+        assert!(is_invalid_data(&recv_handle(&b).unwrap_err()));
+        // still in sync
+        send_raw(&a, &payload, &[fd.as_raw_fd()]);
+        assert_eq!(recv_handle(&b).unwrap().config(), descriptor().config);
+
+        // more than fits a descriptor
+        send_raw(&a, &vec![0; MAX_PAYLOAD_LEN + 1], &[fd.as_raw_fd()]);
+        assert!(is_invalid_data(&recv_handle(&b).unwrap_err()));
+
+        let (a, b) = UnixStream::pair().unwrap();
+        drop(a);
+        let e = recv_handle(&b).unwrap_err();
+        assert!(matches!(e, Error::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+}
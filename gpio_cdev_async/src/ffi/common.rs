@@ -9,6 +9,27 @@ pub(crate) struct Padding<T, const N: usize>(pub(crate) [T; N]);
 #[repr(transparent)]
 pub(crate) struct CString<const N: usize>(pub(crate) [libc::c_char; N]);
 
+/// Plain `repr(C)` uapi structs that can be decoded from the bytes the kernel hands out.
+///
+/// # Safety
+/// Every bit pattern must be a valid value of the implementor, i.e. it may only
+/// contain integers, arrays of integers and unions of integers.
+pub(crate) unsafe trait Pod: Sized {
+    /// Decodes a value from `bytes`, which must be exactly `size_of::<Self>()` long.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != std::mem::size_of::<Self>() {
+            return None;
+        }
+        // SAFETY: the length is checked above and any bit pattern is valid for `Self`
+        Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const Self) })
+    }
+
+    /// Checks the fields that the accessors interpret, e.g. enum ids and counts.
+    fn validate(&self) -> Result<(), crate::parse::ParseError> {
+        Ok(())
+    }
+}
+
 /// Information about a certain GPIO chip
 #[derive(Debug)]
 #[repr(C)]
@@ -19,6 +40,11 @@ pub(crate) struct GpioChipInfo {
     pub(crate) lines: u32,
 }
 
+unsafe impl Pod for GpioChipInfo {}
+
+// the layout of `struct gpiochip_info` in `linux/gpio.h`, on all ABIs
+crate::macros::assert_layout!(GpioChipInfo, 68, { name: 0, label: 32, lines: 64 });
+
 crate::macros::wrap_ioctl!(
     ioctl_read!(
         gpio_get_chipinfo_ioctl,
@@ -45,8 +71,16 @@ mod helper {
     use super::CString;
 
     impl<const N: usize> CString<N> {
+        /// Copies at most `N - 1` bytes of `value`, so the string stays terminated.
+        pub(crate) fn truncated(value: &str) -> Self {
+            let len = value.len().min(N.saturating_sub(1));
+            Self::from(&value[..value.floor_char_boundary(len)])
+        }
+
         pub(crate) fn to_string_lossy(&self) -> Cow<'_, str> {
-            CStr::from_bytes_until_nul(self.0.as_slice())
+            // SAFETY: `c_char` is either `i8` or `u8`, both have the same layout as `u8`
+            let bytes = unsafe { &*(self.0.as_slice() as *const [libc::c_char] as *const [u8]) };
+            CStr::from_bytes_until_nul(bytes)
                 .unwrap_or_default()
                 .to_string_lossy()
         }
@@ -65,9 +99,10 @@ mod helper {
         fn from(value: T) -> Self {
             let value = value.as_ref().as_bytes();
             let len = value.len().min(N);
-            let mut buf = [b'\0'; N];
-            // SAFETY: `len` is always less than or equal to `N`
-            buf[..len].copy_from_slice(&value[..len]);
+            let mut buf = [0; N];
+            for (dst, &src) in buf.iter_mut().zip(&value[..len]) {
+                *dst = src as libc::c_char;
+            }
             Self(buf)
         }
     }
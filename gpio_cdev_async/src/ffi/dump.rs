@@ -0,0 +1,238 @@
+use std::fmt::{Debug, Formatter, Result};
+
+use bitflags::{parser::WriteHex, Flags as BitFlags};
+
+use crate::ffi::common::{CString, GpioChipInfo};
+#[cfg(feature = "v1")]
+use crate::ffi::v1::*;
+#[cfg(feature = "v2")]
+use crate::ffi::v2::*;
+
+/// Semantic dump of the argument of an ioctl: strings decoded, flags rendered
+/// symbolically, masks in binary and only the used part of arrays.
+pub(crate) struct Dump<'a, T>(pub(crate) &'a T);
+
+struct Flags<F>(F);
+
+impl<F> Debug for Flags<F>
+where
+    F: BitFlags,
+    F::Bits: WriteHex,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if self.0.is_empty() {
+            return f.write_str("(empty)");
+        }
+        bitflags::parser::to_writer(&self.0, f)
+    }
+}
+
+struct Mask(u64);
+
+impl Debug for Mask {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{:#b}", self.0)
+    }
+}
+
+struct Str<'a, const N: usize>(&'a CString<N>);
+
+impl<const N: usize> Debug for Str<'_, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Debug::fmt(&self.0.to_string_lossy(), f)
+    }
+}
+
+/// `values`, without the unused trailing zeros.
+fn trimmed(values: &[u8]) -> &[u8] {
+    let len = values.iter().rposition(|&v| v != 0).map_or(0, |i| i + 1);
+    &values[..len]
+}
+
+impl Debug for Dump<'_, u32> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("u32").field("offset", self.0).finish()
+    }
+}
+
+impl Debug for Dump<'_, GpioChipInfo> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("GpioChipInfo")
+            .field("name", &Str(&self.0.name))
+            .field("label", &Str(&self.0.label))
+            .field("lines", &self.0.lines)
+            .finish()
+    }
+}
+
+#[cfg(feature = "v1")]
+impl Debug for Dump<'_, GpioLineInfo> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("GpioLineInfo")
+            .field("line_offset", &self.0.line_offset)
+            .field(
+                "flags",
+                &Flags(GpioLineFlag::from_bits_retain(self.0.flags)),
+            )
+            .field("name", &Str(&self.0.name))
+            .field("consumer", &Str(&self.0.consumer))
+            .finish()
+    }
+}
+
+#[cfg(feature = "v1")]
+impl Debug for Dump<'_, GpioHandleRequest> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let lines = (self.0.lines as usize).min(GPIOHANDLES_MAX);
+        f.debug_struct("GpioHandleRequest")
+            .field("lineoffsets", &&self.0.lineoffsets[..lines])
+            .field(
+                "flags",
+                &Flags(GpioHandleFlags::from_bits_retain(self.0.flags)),
+            )
+            .field("default_values", &&self.0.default_values[..lines])
+            .field("consumer_label", &Str(&self.0.consumer_label))
+            .field("lines", &self.0.lines)
+            .field("fd", &self.0.fd)
+            .finish()
+    }
+}
+
+#[cfg(feature = "v1")]
+impl Debug for Dump<'_, GpioHandleConfig> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("GpioHandleConfig")
+            .field(
+                "flags",
+                &Flags(GpioHandleFlags::from_bits_retain(self.0.flags)),
+            )
+            .field("default_values", &trimmed(&self.0.default_values))
+            .finish()
+    }
+}
+
+#[cfg(feature = "v1")]
+impl Debug for Dump<'_, GpioHandleData> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("GpioHandleData")
+            .field("values", &trimmed(&self.0.values))
+            .finish()
+    }
+}
+
+#[cfg(feature = "v1")]
+impl Debug for Dump<'_, GpioEventRequest> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("GpioEventRequest")
+            .field("lineoffset", &self.0.lineoffset)
+            .field(
+                "handleflags",
+                &Flags(GpioHandleFlags::from_bits_retain(self.0.handleflags)),
+            )
+            .field(
+                "eventflags",
+                &Flags(GpioEventRequestFlags::from_bits_retain(self.0.eventflags)),
+            )
+            .field("consumer_label", &Str(&self.0.consumer_label))
+            .field("fd", &self.0.fd)
+            .finish()
+    }
+}
+
+#[cfg(feature = "v2")]
+impl Debug for Dump<'_, GpioV2LineAttribute> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut res = f.debug_struct("GpioV2LineAttribute");
+        // SAFETY: the union member is selected by `id`, as the kernel does
+        match self.0.id {
+            id if id == GpioV2LineAttrId::Flags as u32 => res.field(
+                "flags",
+                &Flags(GpioV2LineFlag::from_bits_retain(unsafe { self.0.u.flags })),
+            ),
+            id if id == GpioV2LineAttrId::OutputValues as u32 => {
+                res.field("values", &Mask(unsafe { self.0.u.values } as _))
+            }
+            id if id == GpioV2LineAttrId::Debounce as u32 => res
+                .field("debounce_period_us", &unsafe {
+                    self.0.u.debounce_period_us
+                }),
+            id => res
+                .field("id", &id)
+                .field("raw", &Mask(unsafe { self.0.u.values } as _)),
+        };
+        res.finish()
+    }
+}
+
+#[cfg(feature = "v2")]
+impl Debug for Dump<'_, GpioV2LineConfig> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let num_attrs = (self.0.num_attrs as usize).min(GPIO_V2_LINE_NUM_ATTRS_MAX);
+        let attrs: Vec<_> = self.0.attrs[..num_attrs].iter().map(ConfigAttr).collect();
+        f.debug_struct("GpioV2LineConfig")
+            .field(
+                "flags",
+                &Flags(GpioV2LineFlag::from_bits_retain(self.0.flags)),
+            )
+            .field("num_attrs", &self.0.num_attrs)
+            .field("attrs", &attrs)
+            .finish()
+    }
+}
+
+#[cfg(feature = "v2")]
+struct ConfigAttr<'a>(&'a GpioV2LineConfigAttribute);
+
+#[cfg(feature = "v2")]
+impl Debug for ConfigAttr<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("GpioV2LineConfigAttribute")
+            .field("attr", &Dump(&self.0.attr))
+            .field("mask", &Mask(self.0.mask as _))
+            .finish()
+    }
+}
+
+#[cfg(feature = "v2")]
+impl Debug for Dump<'_, GpioV2LineRequest> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let num_lines = (self.0.num_lines as usize).min(GPIO_V2_LINES_MAX);
+        f.debug_struct("GpioV2LineRequest")
+            .field("offsets", &&self.0.offsets[..num_lines])
+            .field("consumer", &Str(&self.0.consumer))
+            .field("config", &Dump(&self.0.config))
+            .field("num_lines", &self.0.num_lines)
+            .field("event_buffer_size", &self.0.event_buffer_size)
+            .field("fd", &self.0.fd)
+            .finish()
+    }
+}
+
+#[cfg(feature = "v2")]
+impl Debug for Dump<'_, GpioV2LineInfo> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let num_attrs = (self.0.num_attrs as usize).min(GPIO_V2_LINE_NUM_ATTRS_MAX);
+        let attrs: Vec<_> = self.0.attrs[..num_attrs].iter().map(Dump).collect();
+        f.debug_struct("GpioV2LineInfo")
+            .field("name", &Str(&self.0.name))
+            .field("consumer", &Str(&self.0.consumer))
+            .field("offset", &self.0.offset)
+            .field("num_attrs", &self.0.num_attrs)
+            .field(
+                "flags",
+                &Flags(GpioV2LineFlag::from_bits_retain(self.0.flags)),
+            )
+            .field("attrs", &attrs)
+            .finish()
+    }
+}
+
+#[cfg(feature = "v2")]
+impl Debug for Dump<'_, GpioV2LineValues> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("GpioV2LineValues")
+            .field("bits", &Mask(self.0.bits as _))
+            .field("mask", &Mask(self.0.mask as _))
+            .finish()
+    }
+}
@@ -2,6 +2,12 @@
 
 /// Common bindings that are version-agnostic.
 pub(crate) mod common;
+/// Dumps of the ioctl arguments, logged with the `debug-ioctl` feature.
+#[cfg(feature = "debug-ioctl")]
+pub(crate) mod dump;
+/// Layout checks against the system's `linux/gpio.h`, see the `uapi-check` feature.
+#[cfg(feature = "uapi-check")]
+mod uapi_check;
 /// GPIO v1 bindings.
 ///
 /// GPIO v1 is deprecated and should not be used.
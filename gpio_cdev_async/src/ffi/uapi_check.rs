@@ -0,0 +1,123 @@
+//! Compares the layouts of the hand-written uapi structs with bindings
+//! generated from the system's `linux/gpio.h` by the build script.
+
+use std::mem::{offset_of, size_of};
+
+#[allow(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals,
+    unreachable_pub,
+    clippy::all
+)]
+mod sys {
+    include!(concat!(env!("OUT_DIR"), "/uapi.rs"));
+}
+
+/// Asserts that `$ours` has the size of `$theirs` and each field its offset.
+macro_rules! assert_same_layout {
+    ($ours:ty, $theirs:ty, { $($field:ident),* $(,)? }) => {
+        crate::macros::const_assert!(size_of::<$ours>() == size_of::<$theirs>());
+        $(crate::macros::const_assert!(offset_of!($ours, $field) == offset_of!($theirs, $field));)*
+    };
+}
+
+assert_same_layout!(super::common::GpioChipInfo, sys::gpiochip_info, { name, label, lines });
+
+#[cfg(feature = "v1")]
+mod v1 {
+    use super::*;
+    use crate::ffi::v1::*;
+
+    assert_same_layout!(GpioLineInfo, sys::gpioline_info, {
+        line_offset,
+        flags,
+        name,
+        consumer,
+    });
+    assert_same_layout!(GpioLineInfoChanged, sys::gpioline_info_changed, {
+        info,
+        timestamp,
+        event_type,
+        padding,
+    });
+    assert_same_layout!(GpioHandleRequest, sys::gpiohandle_request, {
+        lineoffsets,
+        flags,
+        default_values,
+        consumer_label,
+        lines,
+        fd,
+    });
+    assert_same_layout!(GpioHandleConfig, sys::gpiohandle_config, {
+        flags,
+        default_values,
+        padding,
+    });
+    assert_same_layout!(GpioHandleData, sys::gpiohandle_data, { values });
+    assert_same_layout!(GpioEventRequest, sys::gpioevent_request, {
+        lineoffset,
+        handleflags,
+        eventflags,
+        consumer_label,
+        fd,
+    });
+    assert_same_layout!(GpioEventData, sys::gpioevent_data, { timestamp, id });
+}
+
+#[cfg(feature = "v2")]
+mod v2 {
+    use super::*;
+    use crate::ffi::v2::*;
+
+    assert_same_layout!(GpioV2LineValues, sys::gpio_v2_line_values, { bits, mask });
+    assert_same_layout!(GpioV2LineAttribute, sys::gpio_v2_line_attribute, { id, padding });
+    // the union is anonymous in the header
+    crate::macros::const_assert!(
+        offset_of!(GpioV2LineAttribute, u)
+            == offset_of!(sys::gpio_v2_line_attribute, __bindgen_anon_1)
+    );
+    assert_same_layout!(GpioV2LineConfigAttribute, sys::gpio_v2_line_config_attribute, {
+        attr,
+        mask,
+    });
+    assert_same_layout!(GpioV2LineConfig, sys::gpio_v2_line_config, {
+        flags,
+        num_attrs,
+        padding,
+        attrs,
+    });
+    assert_same_layout!(GpioV2LineRequest, sys::gpio_v2_line_request, {
+        offsets,
+        consumer,
+        config,
+        num_lines,
+        event_buffer_size,
+        padding,
+        fd,
+    });
+    assert_same_layout!(GpioV2LineInfo, sys::gpio_v2_line_info, {
+        name,
+        consumer,
+        offset,
+        num_attrs,
+        flags,
+        attrs,
+        padding,
+    });
+    assert_same_layout!(GpioV2LineInfoChanged, sys::gpio_v2_line_info_changed, {
+        info,
+        timestamp_ns,
+        event_type,
+        padding,
+    });
+    assert_same_layout!(GpioV2LineEvent, sys::gpio_v2_line_event, {
+        timestamp_ns,
+        id,
+        offset,
+        seqno,
+        line_seqno,
+        padding,
+    });
+}
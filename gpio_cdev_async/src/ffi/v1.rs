@@ -1,6 +1,9 @@
 use bitflags::bitflags;
 
-use crate::ffi::common::{CString, Padding, GPIO_MAX_NAME_SIZE};
+use crate::{
+    ffi::common::{CString, Padding, Pod, GPIO_MAX_NAME_SIZE},
+    parse::ParseError,
+};
 
 pub(crate) const GPIOHANDLES_MAX: usize = 64;
 
@@ -146,19 +149,18 @@ pub(crate) struct GpioEventRequest {
     pub(crate) fd: libc::c_int,
 }
 
-bitflags! {
-    /// GPIO Event Types
-    #[derive(Debug, Copy, Clone)]
-    pub(crate) struct GpioEventType: u32 {
-        const RISING_EDGE  = 0x01;
-        const FALLING_EDGE = 0x02;
-    }
+/// GPIO Event Types
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpioEventType {
+    RisingEdge = 0x01,
+    FallingEdge = 0x02,
 }
 
 bitflags! {
     /// Event Request flags
     #[derive(Debug, Clone, Copy)]
-    pub(crate) struct GpioEventRequestFlags: u32 {
+    pub struct GpioEventRequestFlags: u32 {
         const REQUEST_RISING_EDGE  = 1 << 0;
         const REQUEST_FALLING_EDGE = 1 << 1;
         const REQUEST_BOTH_EDGES   = Self::REQUEST_RISING_EDGE.bits() | Self::REQUEST_FALLING_EDGE.bits();
@@ -167,13 +169,73 @@ bitflags! {
 
 /// The actual event being pushed to userspace
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct GpioEventData {
     pub(crate) timestamp: u64,
     /// event identifier, one of [`GpioEventType`]
     pub(crate) id: u32,
 }
 
+unsafe impl Pod for GpioLineInfo {}
+
+unsafe impl Pod for GpioLineInfoChanged {
+    fn validate(&self) -> Result<(), ParseError> {
+        match self.event_type {
+            1..=3 => self.info.validate(),
+            id => Err(ParseError::ChangedType(id)),
+        }
+    }
+}
+
+unsafe impl Pod for GpioEventData {
+    fn validate(&self) -> Result<(), ParseError> {
+        match self.id {
+            1..=2 => Ok(()),
+            id => Err(ParseError::EventType(id)),
+        }
+    }
+}
+
+// the layouts of the structs in `linux/gpio.h`, the same on all ABIs but for
+// `gpioevent_data`: its `__u64` is only 4-byte aligned on i386
+crate::macros::assert_layout!(GpioLineInfo, 72, {
+    line_offset: 0,
+    flags: 4,
+    name: 8,
+    consumer: 40,
+});
+crate::macros::assert_layout!(GpioLineInfoChanged, 104, {
+    info: 0,
+    timestamp: 72,
+    event_type: 80,
+    padding: 84,
+});
+crate::macros::assert_layout!(GpioHandleRequest, 364, {
+    lineoffsets: 0,
+    flags: 256,
+    default_values: 260,
+    consumer_label: 324,
+    lines: 356,
+    fd: 360,
+});
+crate::macros::assert_layout!(GpioHandleConfig, 84, {
+    flags: 0,
+    default_values: 4,
+    padding: 68,
+});
+crate::macros::assert_layout!(GpioHandleData, 64, { values: 0 });
+crate::macros::assert_layout!(GpioEventRequest, 48, {
+    lineoffset: 0,
+    handleflags: 4,
+    eventflags: 8,
+    consumer_label: 12,
+    fd: 44,
+});
+#[cfg(not(target_arch = "x86"))]
+crate::macros::assert_layout!(GpioEventData, 16, { timestamp: 0, id: 8 });
+#[cfg(target_arch = "x86")]
+crate::macros::assert_layout!(GpioEventData, 12, { timestamp: 0, id: 8 });
+
 crate::macros::wrap_ioctl!(
     ioctl_readwrite!(
         gpio_get_lineinfo_ioctl,
@@ -247,6 +309,18 @@ crate::macros::wrap_ioctl!(
 mod helper {
     use super::*;
 
+    impl TryFrom<u32> for GpioEventType {
+        type Error = ParseError;
+
+        fn try_from(value: u32) -> Result<Self, Self::Error> {
+            match value {
+                1 => Ok(Self::RisingEdge),
+                2 => Ok(Self::FallingEdge),
+                id => Err(ParseError::EventType(id)),
+            }
+        }
+    }
+
     impl From<u32> for GpioLineChangedType {
         fn from(value: u32) -> Self {
             match value {
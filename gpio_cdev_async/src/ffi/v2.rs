@@ -2,7 +2,10 @@ use std::fmt::Debug;
 
 use bitflags::bitflags;
 
-use crate::ffi::common::{CString, Padding, GPIO_MAX_NAME_SIZE};
+use crate::{
+    ffi::common::{CString, Padding, Pod, GPIO_MAX_NAME_SIZE},
+    parse::ParseError,
+};
 
 pub(crate) const GPIO_V2_LINES_MAX: usize = 64;
 pub(crate) const GPIO_V2_LINE_NUM_ATTRS_MAX: usize = 10;
@@ -10,7 +13,7 @@ pub(crate) const GPIO_V2_LINE_NUM_ATTRS_MAX: usize = 10;
 bitflags! {
     /// [`GpioV2LineAttribute`] flags
     #[derive(Debug, Clone, Copy)]
-    pub struct GpioV2LineFlag: libc::c_ulong {
+    pub struct GpioV2LineFlag: u64 {
         const GPIO_V2_LINE_FLAG_USED                 = 1 << 0;
         const GPIO_V2_LINE_FLAG_ACTIVE_LOW           = 1 << 1;
         const GPIO_V2_LINE_FLAG_INPUT                = 1 << 2;
@@ -32,10 +35,10 @@ bitflags! {
 #[repr(C)]
 pub(crate) struct GpioV2LineValues {
     /// a bitmap containing the value of the lines, set to 1 for active and 0 for inactive.
-    pub(crate) bits: libc::c_ulong,
+    pub(crate) bits: u64,
     /// a bitmap identifying the lines to get or set, with each bit number
     /// corresponding to the index into the `GpioV2LineRequest.offsets` array.
-    pub(crate) mask: libc::c_ulong,
+    pub(crate) mask: u64,
 }
 
 /// [`GpioV2LineAttribute`] id
@@ -49,8 +52,8 @@ pub(crate) enum GpioV2LineAttrId {
 
 #[repr(C)]
 pub(crate) union Union {
-    pub(crate) flags: libc::c_ulong,
-    pub(crate) values: libc::c_ulong,
+    pub(crate) flags: u64,
+    pub(crate) values: u64,
     pub(crate) debounce_period_us: u32,
 }
 
@@ -75,7 +78,7 @@ pub(crate) struct GpioV2LineConfigAttribute {
     /// a bitmap identifying the lines to which the attribute applies,
     /// with each bit number corresponding to the index into the
     /// [`GpioV2LineRequest`].offsets array.
-    pub(crate) mask: libc::c_ulong,
+    pub(crate) mask: u64,
 }
 
 /// Configuration for GPIO lines.
@@ -84,7 +87,7 @@ pub(crate) struct GpioV2LineConfigAttribute {
 pub(crate) struct GpioV2LineConfig {
     /// a bitmap containing the flags for the lines,
     /// with values from [`GpioV2LineFlag`].
-    pub(crate) flags: libc::c_ulong,
+    pub(crate) flags: u64,
     /// the number of attributes in the `attrs` array.
     pub(crate) num_attrs: u32,
     pub(crate) padding: Padding<u32, 5>,
@@ -141,7 +144,7 @@ pub(crate) struct GpioV2LineInfo {
     /// the number of attributes in the `attrs` array
     pub(crate) num_attrs: u32,
     /// the flags for this GPIO line, with values from [`GpioV2LineFlag`]
-    pub(crate) flags: libc::c_ulong,
+    pub(crate) flags: u64,
     /// the configuration attributes associated with the line
     pub(crate) attrs: [GpioV2LineAttribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
     pub(crate) padding: Padding<u32, 4>,
@@ -163,16 +166,16 @@ pub(crate) struct GpioV2LineInfoChanged {
     /// updated line information.
     pub(crate) info: GpioV2LineInfo,
     /// estimate of time of status change occurrence, in nanoseconds
-    pub(crate) timestamp_ns: libc::c_ulong,
+    pub(crate) timestamp_ns: u64,
     /// the type of change with a value from [`GpioV2LineChangedType`]
     pub(crate) event_type: u32,
     pub(crate) padding: Padding<u32, 5>,
 }
 
 /// [`GpioV2LineEvent`] id
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
-pub(crate) enum GpioV2LineEventId {
+pub enum GpioV2LineEventId {
     RisingEdge = 1,
     FallingEdge = 2,
 }
@@ -188,11 +191,11 @@ pub(crate) enum GpioV2LineEventId {
 ///
 /// If the `GPIO_V2_LINE_FLAG_EVENT_CLOCK_HTE` flag is set then the
 /// `timestamp_ns` is provided by the hardware timestamping engine (HTE) subsystem.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub(crate) struct GpioV2LineEvent {
     /// best estimate of time of event occurrence, in nanoseconds
-    pub(crate) timestamp_ns: libc::c_ulong,
+    pub(crate) timestamp_ns: u64,
     /// the type of event with a value from [`GpioV2LineEventId`]
     pub(crate) id: u32,
     /// the offset of the line that triggered the event
@@ -206,6 +209,82 @@ pub(crate) struct GpioV2LineEvent {
     pub(crate) padding: Padding<u32, 6>,
 }
 
+unsafe impl Pod for GpioV2LineInfo {
+    fn validate(&self) -> Result<(), ParseError> {
+        let attrs = self
+            .attrs
+            .get(..self.num_attrs as usize)
+            .ok_or(ParseError::NumAttrs(self.num_attrs))?;
+        match attrs.iter().find(|attr| !matches!(attr.id, 1..=3)) {
+            Some(attr) => Err(ParseError::AttrId(attr.id)),
+            None => Ok(()),
+        }
+    }
+}
+
+unsafe impl Pod for GpioV2LineInfoChanged {
+    fn validate(&self) -> Result<(), ParseError> {
+        match self.event_type {
+            1..=3 => self.info.validate(),
+            id => Err(ParseError::ChangedType(id)),
+        }
+    }
+}
+
+unsafe impl Pod for GpioV2LineEvent {
+    fn validate(&self) -> Result<(), ParseError> {
+        match self.id {
+            1..=2 => Ok(()),
+            id => Err(ParseError::EventType(id)),
+        }
+    }
+}
+
+// the layouts of the structs in `linux/gpio.h`, the same on all ABIs thanks to
+// `__aligned_u64` and explicit padding
+crate::macros::assert_layout!(GpioV2LineValues, 16, { bits: 0, mask: 8 });
+crate::macros::assert_layout!(Union, 8, {});
+crate::macros::assert_layout!(GpioV2LineAttribute, 16, { id: 0, padding: 4, u: 8 });
+crate::macros::assert_layout!(GpioV2LineConfigAttribute, 24, { attr: 0, mask: 16 });
+crate::macros::assert_layout!(GpioV2LineConfig, 272, {
+    flags: 0,
+    num_attrs: 8,
+    padding: 12,
+    attrs: 32,
+});
+crate::macros::assert_layout!(GpioV2LineRequest, 592, {
+    offsets: 0,
+    consumer: 256,
+    config: 288,
+    num_lines: 560,
+    event_buffer_size: 564,
+    padding: 568,
+    fd: 588,
+});
+crate::macros::assert_layout!(GpioV2LineInfo, 256, {
+    name: 0,
+    consumer: 32,
+    offset: 64,
+    num_attrs: 68,
+    flags: 72,
+    attrs: 80,
+    padding: 240,
+});
+crate::macros::assert_layout!(GpioV2LineInfoChanged, 288, {
+    info: 0,
+    timestamp_ns: 256,
+    event_type: 264,
+    padding: 268,
+});
+crate::macros::assert_layout!(GpioV2LineEvent, 48, {
+    timestamp_ns: 0,
+    id: 8,
+    offset: 12,
+    seqno: 16,
+    line_seqno: 20,
+    padding: 24,
+});
+
 crate::macros::wrap_ioctl!(
     ioctl_readwrite!(
         gpio_v2_get_lineinfo_ioctl,
@@ -282,6 +361,13 @@ mod helper {
 
     impl Debug for GpioV2LineAttribute {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            // unused slots of `GpioV2LineInfo::attrs` are zeroed or garbage
+            if !matches!(self.id, 1..=3) {
+                return f
+                    .debug_struct("GpioV2LineAttribute")
+                    .field("id", &self.id)
+                    .finish_non_exhaustive();
+            }
             let id = GpioV2LineAttrId::from(self.id);
             f.debug_struct("GpioV2LineAttribute")
                 .field("id", &id)
@@ -297,6 +383,18 @@ mod helper {
         }
     }
 
+    impl TryFrom<u32> for GpioV2LineEventId {
+        type Error = ParseError;
+
+        fn try_from(value: u32) -> Result<Self, Self::Error> {
+            match value {
+                1 => Ok(Self::RisingEdge),
+                2 => Ok(Self::FallingEdge),
+                id => Err(ParseError::EventType(id)),
+            }
+        }
+    }
+
     impl From<u32> for GpioV2LineChangedType {
         fn from(value: u32) -> Self {
             match value {
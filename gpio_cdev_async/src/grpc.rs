@@ -0,0 +1,364 @@
+//! gRPC service and client, generated from `proto/gpio.proto` with tonic.
+//!
+//! Lines requested through [`GpioService`] stay requested until released,
+//! edge events are streamed until the client drops the stream.
+//!
+//! # Examples
+//! Serve the chips of the host:
+//! ```rust,no_run
+//! # use gpio_cdev_async::grpc::GpioService;
+//! # async fn serve() -> Result<(), Box<dyn std::error::Error>> {
+//! tonic::transport::Server::builder()
+//!     .add_service(GpioService::new().into_server())
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Drive a line remotely:
+//! ```rust,no_run
+//! # use gpio_cdev_async::grpc::{proto, GpioClient};
+//! # async fn drive() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = GpioClient::connect("http://gateway:50051").await?;
+//! let handle = client
+//!     .request_lines(proto::RequestLinesRequest {
+//!         chip: "/dev/gpiochip0".into(),
+//!         offsets: vec![6],
+//!         flags: 1 << 1, // v1 `REQUEST_OUTPUT`
+//!         values: vec![0],
+//!         consumer: "remote".into(),
+//!     })
+//!     .await?
+//!     .into_inner();
+//! client
+//!     .set_values(proto::SetValuesRequest {
+//!         handle: handle.handle,
+//!         values: vec![1],
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - Only chips at `/dev/gpiochip*` are opened.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::HashMap,
+    io,
+    os::fd::{AsRawFd, RawFd},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tokio::{io::unix::AsyncFd, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status};
+
+use crate::{
+    chip::Chip,
+    config::Edge,
+    event::LineEvent,
+    line::{HandleFlags, LineHandle, LineRequest},
+    permissions, Error,
+};
+
+/// The messages and stubs generated from `proto/gpio.proto`.
+#[allow(unreachable_pub, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("gpio_cdev");
+}
+
+pub use proto::{gpio_client::GpioClient, gpio_server::GpioServer};
+
+/// The gRPC service, owning the lines requested by its clients.
+///
+/// The ioctls run on the blocking threads of tokio, every handle has its
+/// own lock so a slow handle does not hold up the others.
+#[derive(Debug, Default)]
+pub struct GpioService {
+    handles: Mutex<HashMap<u64, Arc<Mutex<LineHandle>>>>,
+    next_handle: AtomicU64,
+}
+
+impl GpioService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps the service for [`tonic::transport::Server::add_service`].
+    pub fn into_server(self) -> GpioServer<Self> {
+        GpioServer::new(self)
+    }
+
+    async fn with_line<T: Send + 'static>(
+        &self,
+        handle: u64,
+        f: impl FnOnce(&LineHandle) -> crate::Result<T> + Send + 'static,
+    ) -> Result<T, Status> {
+        let line = self
+            .handles
+            .lock()
+            .unwrap()
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("no handle {handle}")))?;
+        blocking(move || f(&line.lock().unwrap_or_else(|e| e.into_inner()))).await
+    }
+}
+
+#[tonic::async_trait]
+impl proto::gpio_server::Gpio for GpioService {
+    async fn get_chip_info(
+        &self,
+        request: Request<proto::ChipRequest>,
+    ) -> Result<Response<proto::ChipInfo>, Status> {
+        let path = chip_path(request.into_inner().chip)?;
+        let info = blocking(move || {
+            let info = Chip::new(path)?.get_chipinfo()?;
+            Ok(proto::ChipInfo {
+                name: info.name().into_owned(),
+                label: info.label().into_owned(),
+                lines: info.lines(),
+            })
+        })
+        .await?;
+        Ok(Response::new(info))
+    }
+
+    async fn get_line_info(
+        &self,
+        request: Request<proto::LineInfoRequest>,
+    ) -> Result<Response<proto::LineInfo>, Status> {
+        let request = request.into_inner();
+        let path = chip_path(request.chip)?;
+        let info = blocking(move || {
+            let info = Chip::new(path)?.get_lineinfo(request.offset)?;
+            Ok(proto::LineInfo {
+                offset: info.offset(),
+                name: info.name().into_owned(),
+                consumer: info.consumer().into_owned(),
+                flags: info.flags().bits() as _,
+            })
+        })
+        .await?;
+        Ok(Response::new(info))
+    }
+
+    async fn request_lines(
+        &self,
+        request: Request<proto::RequestLinesRequest>,
+    ) -> Result<Response<proto::Handle>, Status> {
+        let request = request.into_inner();
+        let path = chip_path(request.chip)?;
+        let line = blocking(move || {
+            let lines = request
+                .offsets
+                .iter()
+                .enumerate()
+                .map(|(index, &offset)| (offset, request.values.get(index).copied()));
+            let line = LineRequest::builder()
+                .set_flags(HandleFlags::from_bits_retain(request.flags as _))
+                .set_consumer(&request.consumer)
+                .set_offsets_with_values(lines)
+                .build()?;
+            Chip::new(path)?.get_line(line)
+        })
+        .await?;
+
+        let handle = self.next_handle.fetch_add(1, Ordering::Relaxed);
+        self.handles
+            .lock()
+            .unwrap()
+            .insert(handle, Arc::new(Mutex::new(line)));
+        Ok(Response::new(proto::Handle { handle }))
+    }
+
+    async fn get_values(
+        &self,
+        request: Request<proto::Handle>,
+    ) -> Result<Response<proto::Values>, Status> {
+        let values = self
+            .with_line(request.get_ref().handle, |line| line.get_values())
+            .await?;
+        Ok(Response::new(proto::Values {
+            values: values.values_iter().map(|item| item.value).collect(),
+        }))
+    }
+
+    async fn set_values(
+        &self,
+        request: Request<proto::SetValuesRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let request = request.into_inner();
+        self.with_line(request.handle, move |line| {
+            line.set_values_in_order(&request.values)
+        })
+        .await?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn release(
+        &self,
+        request: Request<proto::Handle>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let handle = request.get_ref().handle;
+        self.handles
+            .lock()
+            .unwrap()
+            .remove(&handle)
+            .ok_or_else(|| Status::not_found(format!("no handle {handle}")))?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    type WatchEdgesStream = ReceiverStream<Result<proto::EdgeEvent, Status>>;
+
+    async fn watch_edges(
+        &self,
+        request: Request<proto::WatchEdgesRequest>,
+    ) -> Result<Response<Self::WatchEdgesStream>, Status> {
+        let request = request.into_inner();
+        let path = chip_path(request.chip.clone())?;
+        let edges = match request.edges() {
+            proto::Edge::Both => Edge::Both,
+            proto::Edge::Rising => Edge::Rising,
+            proto::Edge::Falling => Edge::Falling,
+        };
+        let line = blocking(move || {
+            let line = Chip::new(path)?.request_edge_events(
+                request.offset,
+                edges,
+                HandleFlags::from_bits_retain(request.flags as _),
+                &request.consumer,
+            )?;
+            EventLine::new(line)
+        })
+        .await?;
+
+        let line = AsyncFd::new(line).map_err(|e| status(e.into()))?;
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(forward_events(line, tx));
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Forwards the edge events of `line` until the receiver is dropped.
+async fn forward_events(
+    line: AsyncFd<EventLine>,
+    tx: mpsc::Sender<Result<proto::EdgeEvent, Status>>,
+) {
+    let mut events: [LineEvent; 16] = Default::default();
+
+    loop {
+        let mut guard = tokio::select! {
+            guard = line.readable() => match guard {
+                Ok(guard) => guard,
+                Err(e) => {
+                    let _ = tx.send(Err(status(e.into()))).await;
+                    return;
+                }
+            },
+            _ = tx.closed() => return,
+        };
+
+        let n = match guard.try_io(|line| {
+            LineEvent::read(&line.get_ref().line, &mut events).map_err(|e| match e {
+                Error::Io(e) => e,
+                e => io::Error::other(e),
+            })
+        }) {
+            Ok(Ok(n)) => n,
+            Ok(Err(e)) => {
+                let _ = tx.send(Err(status(e.into()))).await;
+                return;
+            }
+            Err(_would_block) => continue,
+        };
+
+        for event in &events[..n] {
+            #[cfg(feature = "v1")]
+            let offset = line.get_ref().line.offsets()[0];
+            #[cfg(feature = "v2")]
+            let offset = event.offset();
+            let event_type = match event.event_type() {
+                Ok(event_type) => event_type,
+                Err(e) => {
+                    let _ = tx.send(Err(status(e))).await;
+                    return;
+                }
+            };
+            let event = proto::EdgeEvent {
+                offset,
+                event_type: event_type as u32,
+                timestamp_ns: event.timestamp_ns() as _,
+            };
+            if tx.send(Ok(event)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// A kernel line handle set non-blocking, for [`AsyncFd`].
+struct EventLine {
+    line: LineHandle,
+    fd: RawFd,
+}
+
+impl EventLine {
+    fn new(line: LineHandle) -> crate::Result<Self> {
+        let fd = line.fd().map(|fd| fd.as_raw_fd()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, "the line handle has no fd")
+        })?;
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(Self { line, fd })
+    }
+}
+
+impl AsRawFd for EventLine {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+/// Runs the blocking `f` on the blocking threads of tokio.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> crate::Result<T> + Send + 'static,
+) -> Result<T, Status> {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res.map_err(status),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(Status::internal(e.to_string())),
+    }
+}
+
+/// Checks that `path` is a chip, only chips are opened.
+fn chip_path(path: String) -> Result<PathBuf, Status> {
+    if !permissions::is_chip_path(&path) {
+        return Err(Status::permission_denied(format!(
+            "{path} is not a GPIO chip"
+        )));
+    }
+    Ok(path.into())
+}
+
+fn status(e: Error) -> Status {
+    let code = match e.raw_os_error() {
+        Some(libc::ENOENT | libc::ENODEV) => Code::NotFound,
+        Some(libc::EACCES | libc::EPERM) => Code::PermissionDenied,
+        Some(libc::EBUSY) => Code::FailedPrecondition,
+        Some(libc::EINVAL) => Code::InvalidArgument,
+        _ => Code::Internal,
+    };
+    Status::new(code, e.to_string())
+}
@@ -12,11 +12,61 @@ compile_error!("Features `v1` and `v2` cannot be enabled at the same time.");
 #[cfg(not(any(feature = "v1", feature = "v2")))]
 compile_error!("One of the features `v1` or `v2` must be enabled.");
 
+pub mod backend;
+#[cfg(any(feature = "mqtt", feature = "rest", feature = "websocket"))]
+mod bridge;
+#[cfg(feature = "broker")]
+pub mod broker;
+pub mod buzzer;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod chip;
+pub mod cleanup;
+pub mod coalesce;
+#[cfg(feature = "compat")]
+pub mod compat;
+pub mod config;
+pub mod consolidate;
 mod error;
 pub mod event;
+#[cfg(feature = "expander")]
+pub mod expander;
+#[cfg(feature = "fdpass")]
+pub mod fdpass;
 mod ffi;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod line;
+pub mod lookup;
 mod macros;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod parse;
+pub mod permissions;
+#[cfg(feature = "profile")]
+pub mod profile;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+#[cfg(feature = "replay")]
+pub mod replay;
+#[cfg(feature = "rest")]
+pub mod rest;
+pub mod ring;
+pub mod sampler;
+pub mod security;
+pub mod sevenseg;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod snapshot;
+#[cfg(feature = "spsc")]
+pub mod spsc;
+pub mod stall;
+pub mod transaction;
+pub mod watchdog;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+mod worker;
 
 pub use error::{Error, IoctlKind, Result};
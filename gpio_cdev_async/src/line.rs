@@ -1,10 +1,29 @@
 use std::{
+    any::Any,
     borrow::Cow,
     fmt::Debug,
-    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    io,
+    os::fd::{AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
+    sync::Mutex,
 };
 
-use crate::{chip::Chip, ffi, Result};
+use crate::{
+    backend::{cdev::CdevLine, LineBackend},
+    chip::Chip,
+    config::{Direction, LineConfig},
+    event::{EventReader, LineEvent, LineEventFilter, LineEventIter, Subscriptions},
+    ffi::{
+        self,
+        common::{CString, Pod},
+    },
+    parse::ParseError,
+    snapshot::OutputSnapshot,
+    Result,
+};
+
+#[cfg(feature = "v1")]
+use crate::config::V1LineParams;
 
 #[cfg(feature = "v1")]
 pub use ffi::v1::GpioHandleFlags as HandleFlags;
@@ -16,6 +35,9 @@ pub use ffi::v1::GpioLineFlag as LineFlags;
 #[cfg(feature = "v2")]
 pub use ffi::v2::GpioV2LineFlag as LineFlags;
 
+#[cfg(feature = "v1")]
+pub use ffi::v1::GpioEventRequestFlags as EventFlags;
+
 #[repr(transparent)]
 pub struct LineInfo {
     #[cfg(feature = "v1")]
@@ -49,6 +71,40 @@ impl LineInfo {
         self.inner.name.to_string_lossy()
     }
 
+    /// Information for a line of a [`GpioBackend`](crate::backend::GpioBackend),
+    /// `name` and `consumer` are truncated to 31 bytes like the kernel's.
+    pub fn new(
+        offset: u32,
+        flags: LineFlags,
+        name: impl AsRef<str>,
+        consumer: impl AsRef<str>,
+    ) -> Self {
+        #[cfg(feature = "v1")]
+        let mut inner: ffi::v1::GpioLineInfo = unsafe { std::mem::zeroed() };
+        #[cfg(feature = "v2")]
+        let mut inner: ffi::v2::GpioV2LineInfo = unsafe { std::mem::zeroed() };
+        #[cfg(feature = "v1")]
+        {
+            inner.line_offset = offset;
+        }
+        #[cfg(feature = "v2")]
+        {
+            inner.offset = offset;
+        }
+        inner.flags = flags.bits();
+        inner.name = CString::truncated(name.as_ref());
+        inner.consumer = CString::truncated(consumer.as_ref());
+        Self { inner }
+    }
+
+    /// Decodes line information from its uapi representation.
+    ///
+    /// Returns `None` if `bytes` is not exactly one valid line information
+    /// long, see [`crate::parse`] for why.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        <Self as Pod>::from_bytes(bytes).filter(|info| info.validate().is_ok())
+    }
+
     #[cfg(feature = "v2")]
     pub fn num_attrs(&self) -> u32 {
         self.inner.num_attrs
@@ -56,13 +112,45 @@ impl LineInfo {
 
     #[cfg(feature = "v2")]
     pub fn attrs(&self) -> Vec<LineAttribute> {
+        self.attr_iter().collect()
+    }
+
+    /// The attributes of the line, converted one by one without allocating.
+    #[cfg(feature = "v2")]
+    pub fn attr_iter(&self) -> impl ExactSizeIterator<Item = LineAttribute> + '_ {
         debug_assert!(self.num_attrs() as usize <= ffi::v2::GPIO_V2_LINE_NUM_ATTRS_MAX);
         self.inner
             .attrs
             .iter()
             .take(self.num_attrs() as usize)
             .map(LineAttribute::from)
-            .collect()
+    }
+
+    /// The debounce period of the line, if it is debounced.
+    #[cfg(feature = "v2")]
+    pub fn debounce(&self) -> Option<std::time::Duration> {
+        self.attr_iter().find_map(|attr| match attr {
+            LineAttribute::DebouncePeriodUs(us) => {
+                Some(std::time::Duration::from_micros(us.into()))
+            }
+            _ => None,
+        })
+    }
+
+    /// The output values attribute of the line, if it has one.
+    #[cfg(feature = "v2")]
+    pub fn output_values(&self) -> Option<u64> {
+        self.attr_iter().find_map(|attr| match attr {
+            LineAttribute::Values(values) => Some(values),
+            _ => None,
+        })
+    }
+}
+
+// SAFETY: `repr(transparent)` over a `Pod` uapi struct
+unsafe impl Pod for LineInfo {
+    fn validate(&self) -> std::result::Result<(), ParseError> {
+        self.inner.validate()
     }
 }
 
@@ -83,7 +171,7 @@ impl Debug for LineInfo {
 #[cfg(feature = "v2")]
 pub enum LineAttribute {
     Flags(LineFlags),
-    Values(libc::c_ulong),
+    Values(u64),
     DebouncePeriodUs(u32),
 }
 
@@ -104,73 +192,237 @@ impl From<&ffi::v2::GpioV2LineAttribute> for LineAttribute {
     }
 }
 
+/// The most lines a single request can have, in both uAPI versions.
+const MAX_LINES: usize = 64;
+
+/// The offsets of a handle or value, stored inline so that handles and
+/// values do not allocate.
+#[derive(Clone, Copy)]
+pub(crate) struct Offsets {
+    len: u8,
+    offsets: [u32; MAX_LINES],
+}
+
+impl Offsets {
+    /// # Panics
+    /// If there are more than 64 offsets.
+    pub(crate) fn new(offsets: &[u32]) -> Self {
+        assert!(offsets.len() <= MAX_LINES, "more than {MAX_LINES} offsets");
+        let mut inline = [0; MAX_LINES];
+        inline[..offsets.len()].copy_from_slice(offsets);
+        Self {
+            len: offsets.len() as u8,
+            offsets: inline,
+        }
+    }
+}
+
+impl std::ops::Deref for Offsets {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        &self.offsets[..self.len as usize]
+    }
+}
+
+impl Debug for Offsets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// What the lines of a handle were requested with, see
+/// [`LineHandle::into_parts`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandleConfig {
+    /// The path of the chip, `None` if unknown.
+    pub chip: Option<PathBuf>,
+    /// The configuration of every line, in the order of the offsets.
+    pub lines: Vec<LineConfig>,
+}
+
 pub struct LineHandle {
-    offsets: Vec<u32>,
-    req_fd: OwnedFd,
+    offsets: Offsets,
+    chip: Option<PathBuf>,
+    /// The configuration of every line, updated by
+    /// [`LineHandle::update_config`].
+    lines: Mutex<Vec<LineConfig>>,
+    pub(crate) backend: Box<dyn LineBackend>,
+    /// The iterators of [`LineHandle::events_for`].
+    pub(crate) subscriptions: Subscriptions,
 }
 
 impl Debug for LineHandle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LineHandle")
-            .field("offsets", &self.offsets.as_slice())
-            .field("req_fd", &self.req_fd)
+            .field("offsets", &self.offsets)
+            .field("chip", &self.chip)
+            .field("lines", &self.line_configs())
+            .field("backend", &self.backend)
             .finish()
     }
 }
 
 impl LineHandle {
+    pub(crate) fn new(
+        offsets: &[u32],
+        config: HandleConfig,
+        backend: Box<dyn LineBackend>,
+    ) -> Self {
+        debug_assert_eq!(offsets.len(), config.lines.len());
+        Self {
+            offsets: Offsets::new(offsets),
+            chip: config.chip,
+            lines: Mutex::new(config.lines),
+            backend,
+            subscriptions: Subscriptions::default(),
+        }
+    }
+
     pub fn offsets(&self) -> &[u32] {
         &self.offsets
     }
 
-    pub fn get_values(&self) -> Result<LineValue> {
-        #[cfg(feature = "v1")]
-        {
-            let mut data: ffi::v1::GpioHandleData = unsafe { std::mem::zeroed() };
-            ffi::v1::gpiohandle_get_line_values_ioctl(self.req_fd.as_raw_fd(), &mut data)?;
-            Ok(LineValue {
-                inner: data,
-                offsets: self.offsets.clone(),
-            })
+    /// What the lines were requested with, or last updated to with
+    /// [`LineHandle::update_config`].
+    pub fn config(&self) -> HandleConfig {
+        HandleConfig {
+            chip: self.chip.clone(),
+            lines: self.line_configs(),
         }
-        #[cfg(feature = "v2")]
-        {
-            let mut mask = 0;
-            for index in 0..self.offsets.len() {
-                mask |= 1 << index;
-            }
-            self.get_values_by_mask(mask)
+    }
+
+    /// The path of the chip the lines were requested from, `None` if
+    /// unknown.
+    pub fn chip_path(&self) -> Option<&Path> {
+        self.chip.as_deref()
+    }
+
+    fn line_configs(&self) -> Vec<LineConfig> {
+        self.lines.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Splits the handle into its request fd, its offsets and its
+    /// configuration, e.g. to pass the fd to another process, see
+    /// [`LineHandle::from_parts`] and [`fdpass`](crate::fdpass).
+    ///
+    /// Returns the handle back if it is not backed by a kernel chip.
+    #[allow(clippy::result_large_err)] // the offsets are inline, the handle is returned as is
+    pub fn into_parts(self) -> std::result::Result<(OwnedFd, Vec<u32>, HandleConfig), Self> {
+        if !(&*self.backend as &dyn Any).is::<CdevLine>() {
+            return Err(self);
         }
+        let config = self.config();
+        let backend: Box<dyn Any> = self.backend;
+        let line = backend.downcast::<CdevLine>().unwrap();
+        Ok((line.fd, self.offsets.to_vec(), config))
+    }
+
+    /// Rebuilds a handle from the parts returned by [`LineHandle::into_parts`].
+    ///
+    /// `fd` must be a line request fd of the enabled uAPI version and
+    /// `offsets` those it was requested with, in order, otherwise the values
+    /// are attributed to the wrong lines. `config` is taken as is, e.g. by
+    /// [`LineHandle::snapshot_outputs`].
+    ///
+    /// # Panics
+    /// If there are more than 64 offsets, or `config` does not have one line
+    /// configuration per offset.
+    pub fn from_parts(fd: OwnedFd, offsets: impl AsRef<[u32]>, config: HandleConfig) -> Self {
+        let offsets = offsets.as_ref();
+        assert_eq!(
+            offsets.len(),
+            config.lines.len(),
+            "one line configuration per offset"
+        );
+        Self::new(offsets, config, Box::new(CdevLine { fd }))
+    }
+
+    /// Returns a blocking iterator over the edge events of this handle.
+    ///
+    /// See [`LineEvent`] for how to request a handle with edge detection enabled.
+    pub fn events(&self) -> LineEventIter<'_> {
+        LineEventIter::new(self)
+    }
+
+    /// Returns a blocking iterator over the edge events of the lines of
+    /// `offsets` only.
+    ///
+    /// The iterators of a handle share its reads: an event is queued for
+    /// every iterator selecting its line, up to
+    /// [`LineEventFilter::CAPACITY`] events per iterator, and discarded if
+    /// none does. Other reads of the handle, e.g. [`LineHandle::events`],
+    /// take the events from under the iterators.
+    ///
+    /// Offsets the handle does not hold are ignored, the iterator yields
+    /// nothing if it holds none of them.
+    pub fn events_for(&self, offsets: impl AsRef<[u32]>) -> LineEventFilter<'_> {
+        LineEventFilter::new(self, offsets_to_mask(self.offsets(), offsets))
+    }
+
+    /// Returns a reader draining up to `batch` edge events per read, for
+    /// handles that see bursts of events.
+    ///
+    /// # Panics
+    /// If `batch` is `0`.
+    pub fn event_reader(&self, batch: usize) -> EventReader<'_> {
+        EventReader::new(self, batch)
+    }
+
+    /// A file descriptor readable when edge events are available, for
+    /// polling, `None` if the backend of the handle has none, e.g. a mock
+    /// handle. Handles of kernel chips always have one.
+    pub fn fd(&self) -> Option<BorrowedFd<'_>> {
+        self.backend.as_fd()
+    }
+
+    /// The fd of a kernel handle, read directly e.g. by [`EventReader`].
+    pub(crate) fn kernel_fd(&self) -> Option<RawFd> {
+        (&*self.backend as &dyn Any)
+            .downcast_ref::<CdevLine>()
+            .map(|line| line.fd.as_raw_fd())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(offsets = ?self.offsets))
+    )]
+    pub fn get_values(&self) -> Result<LineValue> {
+        let mask = self.all_mask();
+        let bits = self.backend.get_values(mask)?;
+        Ok(LineValue::from_bits(self.offsets, mask, bits))
     }
 
+    /// The mask selecting all the lines of the handle.
+    pub(crate) fn all_mask(&self) -> u64 {
+        let len = self.offsets.len().min(64) as u32;
+        u64::MAX.checked_shr(u64::BITS - len).unwrap_or(0)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(offsets = ?self.offsets, ?config))
+    )]
     pub fn update_config(&self, config: LineRequest) -> Result<()> {
         debug_assert_eq!(config.offsets(), self.offsets());
-        #[cfg(feature = "v2")]
-        {
-            let mut data = config.inner.config;
-            ffi::v2::gpio_v2_line_set_config_ioctl(self.req_fd.as_raw_fd(), &mut data)?;
-        }
-        #[cfg(feature = "v1")]
-        {
-            let mut data = ffi::v1::GpioHandleConfig {
-                flags: config.flags().bits(),
-                default_values: config.inner.default_values,
-                padding: ffi::common::Padding([0; 4]),
-            };
-            ffi::v1::gpiohandle_set_config_ioctl(self.req_fd.as_raw_fd(), &mut data)?;
-        }
+        let lines = config.line_configs();
+        self.backend.update_config(config)?;
+        *self.lines.lock().unwrap_or_else(|e| e.into_inner()) = lines;
         Ok(())
     }
 
+    /// Get the values of the lines selected by `mask`.
+    ///
+    /// Each bit of `mask` corresponds to an index into [`LineHandle::offsets`],
+    /// not to a line offset.
     #[cfg(feature = "v2")]
-    pub fn get_values_by_mask(&self, mask: libc::c_ulong) -> Result<LineValue> {
-        let mut data: ffi::v2::GpioV2LineValues = unsafe { std::mem::zeroed() };
-        data.mask = mask;
-        ffi::v2::gpio_v2_line_get_values_ioctl(self.req_fd.as_raw_fd(), &mut data)?;
-        Ok(LineValue {
-            inner: data,
-            offsets: self.offsets.clone(),
-        })
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(offsets = ?self.offsets))
+    )]
+    pub fn get_values_by_mask(&self, mask: u64) -> Result<LineValue> {
+        let bits = self.backend.get_values(mask)?;
+        Ok(LineValue::from_bits(self.offsets, mask, bits))
     }
 
     #[cfg(feature = "v2")]
@@ -179,14 +431,17 @@ impl LineHandle {
         self.get_values_by_mask(mask)
     }
 
+    /// Set the values of the lines selected by `mask` to the corresponding bits of `bits`.
+    ///
+    /// Each bit of `mask` and `bits` corresponds to an index into [`LineHandle::offsets`],
+    /// not to a line offset. Lines not selected by `mask` keep their values.
     #[cfg(feature = "v2")]
-    fn set_values_by_mask(&self, mask: libc::c_ulong, bits: libc::c_ulong) -> Result<()> {
-        let mut data: ffi::v2::GpioV2LineValues = unsafe { std::mem::zeroed() };
-
-        data.mask = mask;
-        data.bits = bits;
-        ffi::v2::gpio_v2_line_set_values_ioctl(self.req_fd.as_raw_fd(), &mut data)?;
-        Ok(())
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(offsets = ?self.offsets))
+    )]
+    pub fn set_values_by_mask(&self, mask: u64, bits: u64) -> Result<()> {
+        self.backend.set_values(mask, bits)
     }
 
     #[cfg(feature = "v2")]
@@ -210,27 +465,124 @@ impl LineHandle {
     }
 
     #[cfg(feature = "v1")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(offsets = ?self.offsets))
+    )]
     pub fn set_values<I>(&self, offsets: I) -> Result<()>
     where
         I: IntoIterator<Item = u32>,
     {
-        let mut data: ffi::v1::GpioHandleData = unsafe { std::mem::zeroed() };
+        let mut bits = 0;
         for offset in offsets.into_iter() {
             if let Some(index) = index_of_offset(&self.offsets, offset) {
-                data.values[index] = 1;
+                bits |= 1 << index;
             }
         }
-        ffi::v1::gpiohandle_set_line_values_ioctl(self.req_fd.as_raw_fd(), &mut data)?;
-        Ok(())
+        self.backend.set_values(self.all_mask(), bits)
+    }
+
+    /// Sets the values of the first `values.len()` lines of the handle, in
+    /// request order, extra values are ignored.
+    ///
+    /// # Notes
+    /// - v1: the lines past `values` are set to `0`, v2: they keep their values.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(offsets = ?self.offsets))
+    )]
+    pub fn set_values_in_order(&self, values: &[u8]) -> Result<()> {
+        let mut mask = 0;
+        let mut bits = 0;
+        for (index, &value) in values.iter().enumerate().take(self.offsets.len()) {
+            mask |= 1 << index;
+            if value != 0 {
+                bits |= 1 << index;
+            }
+        }
+        #[cfg(feature = "v1")]
+        let mask = self.all_mask();
+        self.backend.set_values(mask, bits)
+    }
+
+    /// Sets the value of the line at `offset`, the other lines of the handle
+    /// keep theirs.
+    ///
+    /// # Notes
+    /// - v1 writes all the lines, their values are read first: calls on the
+    ///   same handle from several threads must be serialized by the caller.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(offsets = ?self.offsets))
+    )]
+    pub fn set_value(&self, offset: u32, value: u8) -> Result<()> {
+        let Some(index) = index_of_offset(&self.offsets, offset) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the handle does not hold line {offset}"),
+            )
+            .into());
+        };
+        let mask = 1 << index;
+        self.set_masked(mask, if value != 0 { mask } else { 0 })
+    }
+
+    /// Sets the lines selected by `mask` to `bits`, the others keep their values.
+    fn set_masked(&self, mask: u64, bits: u64) -> Result<()> {
+        #[cfg(feature = "v1")]
+        let (mask, bits) = {
+            // v1 writes all the lines, the others keep their values
+            let current = self.backend.get_values(self.all_mask())?;
+            (self.all_mask(), current & !mask | bits)
+        };
+        self.backend.set_values(mask, bits)
+    }
+
+    /// Captures the values driven on the output lines of the handle, see
+    /// [`snapshot`](crate::snapshot).
+    ///
+    /// Whether a line is an output is taken from the configuration it was
+    /// requested with, see [`LineHandle::config`].
+    pub fn snapshot_outputs(&self) -> Result<OutputSnapshot> {
+        let values = self.get_values()?;
+        let lines = self.line_configs();
+        let mut snapshot = OutputSnapshot::default();
+        for (&offset, config) in self.offsets().iter().zip(&lines) {
+            if config.direction == Some(Direction::Output)
+                && let Some(value) = values.value_of_offset(offset)
+            {
+                snapshot.values.push((offset, value));
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Drives the values of `snapshot` on the lines of the handle, lines the
+    /// handle does not hold are ignored.
+    pub fn restore(&self, snapshot: &OutputSnapshot) -> Result<()> {
+        let mut mask = 0;
+        let mut bits = 0;
+        for &(offset, value) in &snapshot.values {
+            if let Some(index) = index_of_offset(&self.offsets, offset) {
+                mask |= 1 << index;
+                if value != 0 {
+                    bits |= 1 << index;
+                }
+            }
+        }
+        if mask == 0 {
+            return Ok(());
+        }
+        self.set_masked(mask, bits)
     }
 }
 
 #[repr(transparent)]
 pub struct LineRequest {
     #[cfg(feature = "v1")]
-    inner: ffi::v1::GpioHandleRequest,
+    pub(crate) inner: ffi::v1::GpioHandleRequest,
     #[cfg(feature = "v2")]
-    inner: ffi::v2::GpioV2LineRequest,
+    pub(crate) inner: ffi::v2::GpioV2LineRequest,
 }
 
 impl LineRequest {
@@ -356,25 +708,68 @@ impl LineRequest {
 }
 
 impl LineRequest {
-    pub fn request(self, chip: &Chip) -> Result<LineHandle> {
-        #[cfg(feature = "v2")]
-        {
-            let mut data = self;
-            ffi::v2::gpio_v2_get_line_ioctl(chip.file.as_raw_fd(), &mut data.inner)?;
-            Ok(LineHandle {
-                offsets: data.offsets().into(),
-                req_fd: unsafe { OwnedFd::from_raw_fd(data.inner.fd) },
-            })
-        }
+    /// The configuration of the line at `offset`, `None` if the request does
+    /// not hold it.
+    ///
+    /// Flags that do not decode to a [`LineConfig`], e.g. with bits the
+    /// kernel rejects, give an empty configuration.
+    pub fn config_of_offset(&self, offset: u32) -> Option<LineConfig> {
+        let index = self.index_of_offset(offset)?;
         #[cfg(feature = "v1")]
-        {
-            let mut data = self;
-            ffi::v1::gpio_get_linehandle_ioctl(chip.file.as_raw_fd(), &mut data.inner)?;
-            Ok(LineHandle {
-                offsets: data.offsets().into(),
-                req_fd: unsafe { OwnedFd::from_raw_fd(data.inner.fd) },
-            })
-        }
+        let config = LineConfig::try_from(V1LineParams {
+            handle_flags: self.inner.flags,
+            event_flags: 0,
+            default_value: self.default_values().get(index).copied().unwrap_or(0),
+        })
+        .unwrap_or_default();
+        #[cfg(feature = "v2")]
+        let config = {
+            let flags = self.flags_of_offset(offset)?;
+            let mut config = LineConfig::from_v2_flags(flags.bits()).unwrap_or_default();
+            if config.direction == Some(Direction::Output) {
+                config.output_value = self.default_value_of_offset(offset);
+            }
+            config.debounce = self
+                .attrs()
+                .iter()
+                .filter(|c_attr| c_attr.mask & (1 << index) != 0)
+                .find_map(|c_attr| match LineAttribute::from(&c_attr.attr) {
+                    LineAttribute::DebouncePeriodUs(us) => {
+                        Some(std::time::Duration::from_micros(us.into()))
+                    }
+                    _ => None,
+                });
+            config
+        };
+        Some(config)
+    }
+
+    /// The configuration of every line, in the order of the offsets.
+    fn line_configs(&self) -> Vec<LineConfig> {
+        self.offsets()
+            .iter()
+            .map(|&offset| self.config_of_offset(offset).unwrap_or_default())
+            .collect()
+    }
+}
+
+impl LineRequest {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(chip = %chip.path().display(), request = ?self)
+        )
+    )]
+    pub fn request(self, chip: &Chip) -> Result<LineHandle> {
+        let offsets = Offsets::new(self.offsets());
+        let config = HandleConfig {
+            chip: Some(chip.path().to_path_buf()),
+            lines: self.line_configs(),
+        };
+        let backend = chip.backend.request_lines(self)?;
+        Ok(LineHandle::new(&offsets, config, backend))
     }
 }
 
@@ -390,8 +785,7 @@ impl Debug for LineRequest {
     }
 }
 
-#[cfg(feature = "v2")]
-fn offsets_to_mask(offsets: &[u32], target_offsets: impl AsRef<[u32]>) -> libc::c_ulong {
+fn offsets_to_mask(offsets: &[u32], target_offsets: impl AsRef<[u32]>) -> u64 {
     let target_offsets = target_offsets.as_ref();
     let mut mask = 0;
     for (index, &offset) in offsets.iter().enumerate() {
@@ -403,7 +797,7 @@ fn offsets_to_mask(offsets: &[u32], target_offsets: impl AsRef<[u32]>) -> libc::
     mask
 }
 
-fn index_of_offset(offsets: &[u32], target: u32) -> Option<usize> {
+pub(crate) fn index_of_offset(offsets: &[u32], target: u32) -> Option<usize> {
     offsets.iter().position(|&o| o == target)
 }
 
@@ -412,10 +806,33 @@ pub struct LineValue {
     inner: ffi::v2::GpioV2LineValues,
     #[cfg(feature = "v1")]
     inner: ffi::v1::GpioHandleData,
-    offsets: Vec<u32>,
+    offsets: Offsets,
 }
 
 impl LineValue {
+    /// The values of the lines of `offsets` selected by `mask`, in `bits`.
+    ///
+    /// v1 values have no mask, all the lines are set.
+    pub(crate) fn from_bits(offsets: Offsets, mask: u64, bits: u64) -> Self {
+        #[cfg(feature = "v1")]
+        let inner = {
+            let _ = mask;
+            let mut inner: ffi::v1::GpioHandleData = unsafe { std::mem::zeroed() };
+            for (index, value) in inner.values.iter_mut().enumerate() {
+                *value = (bits >> index & 1) as u8;
+            }
+            inner
+        };
+        #[cfg(feature = "v2")]
+        let inner = {
+            let mut inner: ffi::v2::GpioV2LineValues = unsafe { std::mem::zeroed() };
+            inner.mask = mask as _;
+            inner.bits = (bits & mask) as _;
+            inner
+        };
+        Self { inner, offsets }
+    }
+
     pub fn value_of_offset(&self, offset: u32) -> Option<u8> {
         let index = index_of_offset(&self.offsets, offset)?;
         self.value_of_index(index)
@@ -559,6 +976,11 @@ impl LineRequestBuilder {
         self
     }
 
+    /// Sets the lines to request, along with their per-line configuration.
+    ///
+    /// # Notes
+    /// - v2: equal attributes of different lines share one of the 10 attribute
+    ///   slots of the request, lines whose attributes no longer fit are not requested.
     pub fn set_offsets<I, T>(mut self, configs: I) -> Self
     where
         I: IntoIterator<Item = T>,
@@ -566,37 +988,55 @@ impl LineRequestBuilder {
     {
         #[cfg(feature = "v2")]
         {
+            let request = &mut self.inner.inner;
             // also as line index
             let mut lines_num = 0;
             // also as attr index
             let mut attrs_num = 0;
 
-            'outer: for config in configs
+            for config in configs
                 .into_iter()
                 .map(Into::<PinConfig>::into)
-                .take(self.inner.inner.offsets.len())
+                .take(request.offsets.len())
             {
+                // attributes equal to an already configured one share its slot,
+                // only add the line if all of its new attributes fit
+                let new_attrs = config
+                    .line_attr
+                    .iter()
+                    .filter(|attr| {
+                        !request.config.attrs[..attrs_num]
+                            .iter()
+                            .any(|c_attr| attr.can_share(c_attr))
+                    })
+                    .count();
+                if attrs_num + new_attrs > request.config.attrs.len() {
+                    break;
+                }
+
                 // set offset
-                self.inner.inner.offsets[lines_num as usize] = config.offset;
+                request.offsets[lines_num as usize] = config.offset;
                 // set attr
                 for attr in config.line_attr {
-                    let attr_config = &mut self.inner.inner.config.attrs[attrs_num as usize];
-                    attr_config.mask = 1 << lines_num;
-
-                    attr_config.attr = attr.into_line_attribute(lines_num);
-
-                    attrs_num += 1;
-                    if attrs_num as usize >= self.inner.inner.config.attrs.len() {
-                        lines_num += 1;
-                        break 'outer;
+                    match request.config.attrs[..attrs_num]
+                        .iter_mut()
+                        .find(|c_attr| attr.can_share(c_attr))
+                    {
+                        Some(c_attr) => attr.share(lines_num, c_attr),
+                        None => {
+                            let attr_config = &mut request.config.attrs[attrs_num];
+                            attr_config.mask = 1 << lines_num;
+                            attr_config.attr = attr.into_line_attribute(lines_num);
+                            attrs_num += 1;
+                        }
                     }
                 }
 
                 lines_num += 1;
             }
 
-            self.inner.inner.num_lines = lines_num;
-            self.inner.inner.config.num_attrs = attrs_num;
+            request.num_lines = lines_num;
+            request.config.num_attrs = attrs_num as u32;
         }
 
         #[cfg(feature = "v1")]
@@ -620,6 +1060,33 @@ impl LineRequestBuilder {
         self
     }
 
+    /// Sets the lines to request, each with its initial output value, `None`
+    /// for the lines requested without one.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use gpio_cdev_async::line::LineRequest;
+    /// let offsets = [4, 5, 6];
+    /// let values = [1, 0];
+    /// let request = LineRequest::builder()
+    ///     .set_offsets_with_values(
+    ///         offsets.iter().enumerate().map(|(i, &offset)| (offset, values.get(i).copied())),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(request.offsets(), offsets);
+    /// ```
+    pub fn set_offsets_with_values<I>(self, lines: I) -> Self
+    where
+        I: IntoIterator<Item = (u32, Option<u8>)>,
+    {
+        self.set_offsets(
+            lines
+                .into_iter()
+                .map(|(offset, value)| PinConfig::with_value(offset, value)),
+        )
+    }
+
     #[cfg(feature = "v2")]
     pub fn set_event_buffer_size(mut self, size: u32) -> Self {
         self.inner.inner.event_buffer_size = size;
@@ -647,6 +1114,19 @@ pub struct PinConfig {
     line_attr: Vec<PinAttribute>,
 }
 
+impl PinConfig {
+    /// The line at `offset` with the initial output `value`, if any.
+    pub(crate) fn with_value(offset: u32, value: Option<u8>) -> Self {
+        match value {
+            #[cfg(feature = "v1")]
+            Some(value) => Self::from((offset, value)),
+            #[cfg(feature = "v2")]
+            Some(value) => Self::from((offset, [PinAttribute::Value(value)])),
+            None => Self::from(offset),
+        }
+    }
+}
+
 #[cfg(feature = "v2")]
 impl<T> From<(u32, T)> for PinConfig
 where
@@ -699,6 +1179,35 @@ pub enum PinAttribute {
 
 #[cfg(feature = "v2")]
 impl PinAttribute {
+    /// Whether this attribute can be applied by adding a line to `c_attr`.
+    ///
+    /// Output values of all lines share a single attribute, flags and debounce
+    /// periods are shared when they are equal.
+    fn can_share(&self, c_attr: &ffi::v2::GpioV2LineConfigAttribute) -> bool {
+        use ffi::v2::GpioV2LineAttrId;
+        match (self, GpioV2LineAttrId::from(c_attr.attr.id)) {
+            (Self::Value(_), GpioV2LineAttrId::OutputValues) => true,
+            (Self::Flags(flags), GpioV2LineAttrId::Flags) => {
+                flags.bits() == unsafe { c_attr.attr.u.flags }
+            }
+            (Self::DebouncePeriodUs(us), GpioV2LineAttrId::Debounce) => {
+                *us == unsafe { c_attr.attr.u.debounce_period_us }
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds the line at `index` to `c_attr`, see [`PinAttribute::can_share`].
+    fn share(self, index: u32, c_attr: &mut ffi::v2::GpioV2LineConfigAttribute) {
+        debug_assert!(self.can_share(c_attr));
+        c_attr.mask |= 1 << index;
+        if let Self::Value(v) = self
+            && v != 0
+        {
+            unsafe { c_attr.attr.u.values |= 1 << index };
+        }
+    }
+
     fn into_line_attribute(self, index: u32) -> ffi::v2::GpioV2LineAttribute {
         match self {
             Self::Value(v) => ffi::v2::GpioV2LineAttribute {
@@ -747,6 +1256,90 @@ impl From<u32> for PinAttribute {
     }
 }
 
+/// A request for edge events on a single GPIO line.
+///
+/// The resulting [`LineHandle`] can both read the value of the line
+/// and read [`LineEvent`]s.
+#[cfg(feature = "v1")]
+#[repr(transparent)]
+pub struct EventRequest {
+    pub(crate) inner: ffi::v1::GpioEventRequest,
+}
+
+#[cfg(feature = "v1")]
+impl EventRequest {
+    pub fn new(
+        offset: u32,
+        handle_flags: HandleFlags,
+        event_flags: EventFlags,
+        consumer: impl AsRef<str>,
+    ) -> Self {
+        let mut inner: ffi::v1::GpioEventRequest = unsafe { std::mem::zeroed() };
+        inner.lineoffset = offset;
+        inner.handleflags = handle_flags.bits();
+        inner.eventflags = event_flags.bits();
+        inner.consumer_label = consumer.into();
+        Self { inner }
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.inner.lineoffset
+    }
+
+    pub fn consumer(&self) -> Cow<'_, str> {
+        self.inner.consumer_label.to_string_lossy()
+    }
+
+    pub fn handle_flags(&self) -> HandleFlags {
+        HandleFlags::from_bits_retain(self.inner.handleflags)
+    }
+
+    pub fn event_flags(&self) -> EventFlags {
+        EventFlags::from_bits_retain(self.inner.eventflags)
+    }
+
+    /// The configuration of the line, empty if the flags do not decode to
+    /// one, see [`LineConfig::try_from`].
+    pub fn config(&self) -> LineConfig {
+        LineConfig::try_from(V1LineParams {
+            handle_flags: self.inner.handleflags,
+            event_flags: self.inner.eventflags,
+            default_value: 0,
+        })
+        .unwrap_or_default()
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(chip = %chip.path().display(), request = ?self)
+        )
+    )]
+    pub fn request(self, chip: &Chip) -> Result<LineHandle> {
+        let offset = self.offset();
+        let config = HandleConfig {
+            chip: Some(chip.path().to_path_buf()),
+            lines: vec![self.config()],
+        };
+        let backend = chip.backend.request_event_line(self)?;
+        Ok(LineHandle::new(&[offset], config, backend))
+    }
+}
+
+#[cfg(feature = "v1")]
+impl Debug for EventRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventRequest")
+            .field("offset", &self.offset())
+            .field("consumer", &self.consumer())
+            .field("handle_flags", &self.handle_flags())
+            .field("event_flags", &self.event_flags())
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub struct PinHandle {
     line_handle: LineHandle,
@@ -790,20 +1383,13 @@ impl PinRequest {
         default_value: u8,
         consumer: impl AsRef<str>,
     ) -> Self {
-        let line_request_builder = LineRequestBuilder::new()
+        let line_request = LineRequestBuilder::new()
             .set_flags(flags)
-            .set_consumer(consumer);
-
-        #[cfg(feature = "v2")]
-        let line_request_builder =
-            line_request_builder.set_offsets([(offset, [PinAttribute::Value(default_value)])]);
-
-        #[cfg(feature = "v1")]
-        let line_request_builder = line_request_builder.set_offsets([(offset, default_value)]);
-
-        Self {
-            line_request: line_request_builder.build().unwrap(),
-        }
+            .set_consumer(consumer)
+            .set_offsets_with_values([(offset, Some(default_value))])
+            .build()
+            .unwrap();
+        Self { line_request }
     }
 
     pub fn offset(&self) -> u32 {
@@ -0,0 +1,175 @@
+//! Finding lines by name across all the chips.
+//!
+//! SoCs name hundreds of lines, and the exact names vary between board
+//! files, e.g. `SPI0_CS` and `SPI0_CS0`. [`find_lines_matching`] returns the
+//! lines whose names match a glob pattern, [`find_lines_where`] the lines
+//! whose names satisfy any predicate, e.g. a regex from the `regex` crate.
+//!
+//! # Examples
+//! ```rust,no_run
+//! use gpio_cdev_async::lookup;
+//!
+//! for line in lookup::find_lines_matching("SPI*_CS")? {
+//!     println!("{} {} {}", line.chip.display(), line.offset, line.name);
+//! }
+//! // with the `regex` crate
+//! // let re = regex::Regex::new(r"^SPI\d_CS\d?$").unwrap();
+//! // let lines = lookup::find_lines_where(|name| re.is_match(name))?;
+//! # Ok::<(), gpio_cdev_async::Error>(())
+//! ```
+//!
+//! # Notes
+//! - Chips that cannot be opened, e.g. for lack of permission, are skipped.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::path::PathBuf;
+
+use crate::{chip::Chip, permissions, Result};
+
+/// A named line, see [`find_lines_matching`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// The path of the chip of the line.
+    pub chip: PathBuf,
+    pub offset: u32,
+    pub name: String,
+}
+
+/// The lines of all the chips whose names match `pattern`, in the order of
+/// the chips and offsets.
+///
+/// In the pattern, `*` matches any characters, `?` one character and
+/// `[...]` one of the characters or ranges in brackets, `[!...]` one of the
+/// others. Other characters match themselves, case-sensitively.
+pub fn find_lines_matching(pattern: &str) -> Result<Vec<LineMatch>> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    find_lines_where(|name| glob_match(&pattern, &name.chars().collect::<Vec<_>>()))
+}
+
+/// Whether `name` matches the glob `pattern`, see [`find_lines_matching`].
+///
+/// # Examples
+/// ```rust
+/// # use gpio_cdev_async::lookup::matches_glob;
+/// assert!(matches_glob("SPI*_CS", "SPI0_CS"));
+/// assert!(matches_glob("SPI?_CS[0-9]", "SPI1_CS2"));
+/// assert!(!matches_glob("SPI?_CS[!0]", "SPI1_CS0"));
+/// assert!(!matches_glob("SPI*_CS", "SPI0_CS0"));
+/// ```
+pub fn matches_glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    glob_match(&pattern, &name.chars().collect::<Vec<_>>())
+}
+
+/// The lines of all the chips whose names satisfy `matches`, in the order
+/// of the chips and offsets. Unnamed lines are left out.
+pub fn find_lines_where(mut matches: impl FnMut(&str) -> bool) -> Result<Vec<LineMatch>> {
+    let mut lines = Vec::new();
+    for path in permissions::chip_paths()? {
+        let Ok(chip) = Chip::new(&path) else {
+            continue;
+        };
+        lines.extend(lines_where(&chip, &mut matches)?);
+    }
+    Ok(lines)
+}
+
+/// The lines of `chip` whose names satisfy `matches`, in the order of the
+/// offsets.
+pub fn lines_where(chip: &Chip, mut matches: impl FnMut(&str) -> bool) -> Result<Vec<LineMatch>> {
+    let mut lines = Vec::new();
+    for offset in 0..chip.get_chipinfo()?.lines() {
+        let info = chip.get_lineinfo(offset)?;
+        let name = info.name();
+        if !name.is_empty() && matches(&name) {
+            lines.push(LineMatch {
+                chip: chip.path().to_path_buf(),
+                offset,
+                name: name.into_owned(),
+            });
+        }
+    }
+    Ok(lines)
+}
+
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // the position after the last `*` and the name position it matched up to
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            Some('[') => {
+                if let Some((matched, len)) = class_match(&pattern[p..], name[n]) {
+                    if matched {
+                        p += len;
+                        n += 1;
+                        continue;
+                    }
+                } else if name[n] == '[' {
+                    // an unclosed bracket matches itself
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+            }
+            Some(&c) if c == name[n] => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        // backtrack: let the last `*` match one more character
+        match star {
+            Some((after, matched)) => {
+                p = after;
+                n = matched + 1;
+                star = Some((after, matched + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the bracket class at the start of `pattern`.
+///
+/// Returns whether it matched and the length of the class, `None` if the
+/// bracket is not closed.
+fn class_match(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match *pattern.get(i)? {
+            // a `]` right after the opening bracket is a character
+            ']' if !first => return Some((matched != negated, i + 1)),
+            lo => {
+                let hi = pattern.get(i + 2).filter(|&&hi| hi != ']');
+                if let (Some('-'), Some(&hi)) = (pattern.get(i + 1), hi) {
+                    matched |= (lo..=hi).contains(&c);
+                    i += 3;
+                } else {
+                    matched |= lo == c;
+                    i += 1;
+                }
+            }
+        }
+        first = false;
+    }
+}
@@ -5,9 +5,40 @@ macro_rules! wrap_ioctl {
         }
 
         pub(crate) fn $name(fd: libc::c_int, data: &mut $ty) -> $crate::error::Result<libc::c_int> {
-            unsafe {
+            #[cfg(feature = "debug-ioctl")]
+            eprintln!(
+                "{}(fd={fd}) -> {:#?}",
+                stringify!($name),
+                $crate::ffi::dump::Dump(&*data)
+            );
+            #[cfg(any(feature = "tracing", feature = "profile"))]
+            let start = std::time::Instant::now();
+            let res = unsafe {
                 $name::$name(fd, data).map_err(|e| $crate::error::ioctl_error($ioctl_error_ty, e))
+            };
+            #[cfg(feature = "debug-ioctl")]
+            match &res {
+                Ok(ret) => eprintln!(
+                    "{}(fd={fd}) = {ret} <- {:#?}",
+                    stringify!($name),
+                    $crate::ffi::dump::Dump(&*data)
+                ),
+                Err(e) => eprintln!("{}(fd={fd}) = {e}", stringify!($name)),
             }
+            #[cfg(feature = "tracing")]
+            {
+                let elapsed_us = start.elapsed().as_micros() as u64;
+                let ioctl = stringify!($name);
+                match &res {
+                    Ok(_) => tracing::trace!(ioctl, fd, elapsed_us, "ioctl"),
+                    Err(e) => tracing::debug!(ioctl, fd, elapsed_us, error = %e, "ioctl failed"),
+                }
+            }
+            #[cfg(feature = "profile")]
+            if $crate::profile::enabled() {
+                $crate::profile::record($ioctl_error_ty, start.elapsed(), &res);
+            }
+            res
         }
     };
 }
@@ -18,4 +49,14 @@ macro_rules! const_assert {
     };
 }
 
+/// Asserts the size of a uapi struct and the offsets of its fields at compile time.
+macro_rules! assert_layout {
+    ($ty:ty, $size:expr, { $($field:ident: $offset:expr),* $(,)? }) => {
+        $crate::macros::const_assert!(std::mem::size_of::<$ty>() == $size);
+        $($crate::macros::const_assert!(std::mem::offset_of!($ty, $field) == $offset);)*
+    };
+}
+
+pub(crate) use assert_layout;
+pub(crate) use const_assert;
 pub(crate) use wrap_ioctl;
@@ -0,0 +1,442 @@
+//! In-memory chips for unit tests without hardware.
+//!
+//! A [`MockChip`] grants line requests like the kernel would, failing with
+//! `EBUSY` on lines already requested, and records them. The values read by a
+//! [`MockLineHandle`] are scripted from the test and its edge events injected
+//! with chosen timestamps.
+//!
+//! A [`MockChip`] is also a [`GpioBackend`], so code written against
+//! [`Chip`] runs on it unchanged through [`Chip::from_backend`].
+//!
+//! # Examples
+//! ```rust
+//! # use gpio_cdev_async::{event::LineEventType, line::{HandleFlags, LineRequest}, mock::MockChip};
+//! let chip = MockChip::new(8);
+//! # #[cfg(feature = "v1")]
+//! # let flags = HandleFlags::REQUEST_INPUT;
+//! # #[cfg(feature = "v2")]
+//! # let flags = HandleFlags::GPIO_V2_LINE_FLAG_INPUT;
+//! let request = LineRequest::builder()
+//!     .set_flags(flags)
+//!     .set_consumer("test")
+//!     .set_offsets([3u32])
+//!     .build()
+//!     .unwrap();
+//! let handle = chip.get_line(request).unwrap();
+//! assert_eq!(chip.requests()[0].consumer, "test");
+//!
+//! chip.script_values(3, [1, 0]);
+//! assert_eq!(handle.get_values().unwrap().value_of_offset(3), Some(1));
+//! assert_eq!(handle.get_values().unwrap().value_of_offset(3), Some(0));
+//!
+//! chip.inject_event(3, LineEventType::RisingEdge, 1_000);
+//! let event = handle.events().next().unwrap().unwrap();
+//! assert_eq!(event.timestamp_ns(), 1_000);
+//! assert!(handle.events().next().is_none());
+//! ```
+//!
+//! Through [`Chip`]:
+//! ```rust
+//! # use gpio_cdev_async::{chip::Chip, line::LineRequest, mock::MockChip};
+//! let mock = MockChip::new(8);
+//! let chip = Chip::from_backend("mock", mock.clone());
+//!
+//! mock.set_value(5, 1);
+//! let request = LineRequest::builder().set_offsets([5u32]).build().unwrap();
+//! let handle = chip.get_line(request).unwrap();
+//! assert_eq!(handle.get_values().unwrap().value_of_offset(5), Some(1));
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "v1")]
+use crate::line::EventRequest;
+#[cfg(feature = "v2")]
+use crate::line::LineValueItem;
+use crate::{
+    backend::{GpioBackend, LineBackend},
+    chip::{Chip, ChipInfo},
+    error::ioctl_error,
+    event::{LineEvent, LineEventType},
+    line::{HandleFlags, LineFlags, LineInfo, LineRequest, LineValue, Offsets},
+    IoctlKind, Result,
+};
+
+/// A line request granted by a [`MockChip`].
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub offsets: Vec<u32>,
+    pub flags: HandleFlags,
+    pub consumer: String,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The values served to reads, the last one stays.
+    values: BTreeMap<u32, VecDeque<u8>>,
+    /// The consumers of the requested lines.
+    requested: BTreeMap<u32, String>,
+    requests: Vec<MockRequest>,
+    /// The injected events not read yet, in order.
+    events: VecDeque<(u32, LineEventType, u64)>,
+}
+
+impl State {
+    fn read(&mut self, offset: u32) -> u8 {
+        let values = self.values.entry(offset).or_default();
+        if values.len() > 1 {
+            values.pop_front().unwrap()
+        } else {
+            values.front().copied().unwrap_or(0)
+        }
+    }
+
+    fn write(&mut self, offset: u32, value: u8) {
+        self.values.insert(offset, [value].into());
+    }
+}
+
+/// An in-memory chip, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct MockChip {
+    name: String,
+    label: String,
+    lines: u32,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockChip {
+    /// A chip of `lines` lines, all low.
+    pub fn new(lines: u32) -> Self {
+        Self {
+            name: "gpiochip0".into(),
+            label: "mock".into(),
+            lines,
+            state: Default::default(),
+        }
+    }
+
+    /// The name of the chip, `gpiochip0` by default.
+    pub fn set_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// The label of the chip, `mock` by default.
+    pub fn set_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+
+    /// Requests lines, outputs start at their default values.
+    pub fn get_line(&self, request: LineRequest) -> Result<MockLineHandle> {
+        #[cfg(feature = "v1")]
+        let output = request.flags().contains(HandleFlags::REQUEST_OUTPUT);
+        #[cfg(feature = "v2")]
+        let output = request
+            .flags()
+            .contains(HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT);
+
+        let handle = self.request(
+            IoctlKind::GetLine,
+            MockRequest {
+                offsets: request.offsets().into(),
+                flags: request.flags(),
+                consumer: request.consumer().into_owned(),
+            },
+        )?;
+        if output {
+            let mut state = self.state.lock().unwrap();
+            for &offset in request.offsets() {
+                state.write(offset, request.default_value_of_offset(offset).unwrap_or(0));
+            }
+        }
+        Ok(handle)
+    }
+
+    /// Requests a line for edge events.
+    #[cfg(feature = "v1")]
+    pub fn get_event_line(&self, request: EventRequest) -> Result<MockLineHandle> {
+        self.request(
+            IoctlKind::GetLineEvent,
+            MockRequest {
+                offsets: vec![request.offset()],
+                flags: request.handle_flags(),
+                consumer: request.consumer().into_owned(),
+            },
+        )
+    }
+
+    fn request(&self, kind: IoctlKind, request: MockRequest) -> Result<MockLineHandle> {
+        let mut state = self.state.lock().unwrap();
+        let offsets: BTreeSet<_> = request.offsets.iter().copied().collect();
+        if offsets.is_empty()
+            || offsets.len() != request.offsets.len()
+            || offsets.iter().any(|&offset| offset >= self.lines)
+        {
+            return Err(ioctl_error(kind, nix::Error::EINVAL));
+        }
+        if offsets
+            .iter()
+            .any(|offset| state.requested.contains_key(offset))
+        {
+            return Err(ioctl_error(kind, nix::Error::EBUSY));
+        }
+
+        for &offset in &offsets {
+            state.requested.insert(offset, request.consumer.clone());
+        }
+        let handle = MockLineHandle {
+            offsets: request.offsets.clone(),
+            state: self.state.clone(),
+            seqnos: Default::default(),
+        };
+        state.requests.push(request);
+        Ok(handle)
+    }
+
+    /// The requests granted so far, released or not.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Sets the value of the line at `offset` until it is changed again.
+    pub fn set_value(&self, offset: u32, value: u8) {
+        self.state.lock().unwrap().write(offset, value);
+    }
+
+    /// Serves `values` to the reads of the line at `offset` in turn, the
+    /// last one stays.
+    pub fn script_values(&self, offset: u32, values: impl IntoIterator<Item = u8>) {
+        let values: VecDeque<_> = values.into_iter().collect();
+        if !values.is_empty() {
+            self.state.lock().unwrap().values.insert(offset, values);
+        }
+    }
+
+    /// The value the line at `offset` would read, e.g. as set by its consumer.
+    pub fn value(&self, offset: u32) -> u8 {
+        let state = self.state.lock().unwrap();
+        let values = state.values.get(&offset);
+        values
+            .and_then(|values| values.front().copied())
+            .unwrap_or(0)
+    }
+
+    /// Queues an edge event on the line at `offset`, the line then reads
+    /// high after a rising edge and low after a falling edge.
+    pub fn inject_event(&self, offset: u32, event_type: LineEventType, timestamp_ns: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.events.push_back((offset, event_type, timestamp_ns));
+        let value = match event_type {
+            LineEventType::RisingEdge => 1,
+            LineEventType::FallingEdge => 0,
+        };
+        state.write(offset, value);
+    }
+}
+
+/// Lines requested from a [`MockChip`], released when dropped.
+#[derive(Debug)]
+pub struct MockLineHandle {
+    offsets: Vec<u32>,
+    state: Arc<Mutex<State>>,
+    /// The sequence numbers of the last event, of the request and per line.
+    seqnos: Mutex<(u32, BTreeMap<u32, u32>)>,
+}
+
+impl MockLineHandle {
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Returns an iterator over the injected edge events of this handle.
+    ///
+    /// Unlike [`LineHandle::events`](crate::line::LineHandle::events), it
+    /// ends once the injected events are read instead of blocking.
+    pub fn events(&self) -> MockEventIter<'_> {
+        MockEventIter { handle: self }
+    }
+
+    pub fn get_values(&self) -> Result<LineValue> {
+        let mask = u64::MAX.checked_shr(u64::BITS - self.offsets.len() as u32);
+        let mask = mask.unwrap_or(0);
+        let bits = LineBackend::get_values(self, mask)?;
+        Ok(LineValue::from_bits(Offsets::new(&self.offsets), mask, bits))
+    }
+
+    #[cfg(feature = "v2")]
+    pub fn set_values<I, T>(&self, offsets: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<LineValueItem>,
+    {
+        let mut state = self.state.lock().unwrap();
+        for LineValueItem { offset, value } in offsets.into_iter().map(Into::into) {
+            if self.offsets.contains(&offset) {
+                state.write(offset, u8::from(value != 0));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the lines at `offsets` high and the others low.
+    #[cfg(feature = "v1")]
+    pub fn set_values<I>(&self, offsets: I) -> Result<()>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let high: BTreeSet<_> = offsets.into_iter().collect();
+        let mut state = self.state.lock().unwrap();
+        for &offset in &self.offsets {
+            state.write(offset, u8::from(high.contains(&offset)));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MockLineHandle {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        for offset in &self.offsets {
+            state.requested.remove(offset);
+        }
+    }
+}
+
+/// An iterator over the injected edge events of a [`MockLineHandle`].
+///
+/// See [`MockLineHandle::events`].
+#[derive(Debug)]
+pub struct MockEventIter<'a> {
+    handle: &'a MockLineHandle,
+}
+
+impl Iterator for MockEventIter<'_> {
+    type Item = Result<LineEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.handle.next_event().map(Ok)
+    }
+}
+
+impl MockLineHandle {
+    fn next_event(&self) -> Option<LineEvent> {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .events
+            .iter()
+            .position(|(offset, ..)| self.offsets.contains(offset))?;
+        let (offset, event_type, timestamp_ns) = state.events.remove(index).unwrap();
+
+        let mut seqnos = self.seqnos.lock().unwrap();
+        let (seqno, line_seqnos) = &mut *seqnos;
+        *seqno += 1;
+        let line_seqno = line_seqnos.entry(offset).or_default();
+        *line_seqno += 1;
+        Some(LineEvent::new(
+            offset,
+            event_type,
+            timestamp_ns,
+            *seqno,
+            *line_seqno,
+        ))
+    }
+}
+
+impl GpioBackend for MockChip {
+    fn chip_info(&self) -> Result<ChipInfo> {
+        Ok(ChipInfo::new(&self.name, &self.label, self.lines))
+    }
+
+    fn line_info(&self, offset: u32) -> Result<LineInfo> {
+        if offset >= self.lines {
+            return Err(ioctl_error(IoctlKind::GetLineInfo, nix::Error::EINVAL));
+        }
+        let state = self.state.lock().unwrap();
+        let info = match state.requested.get(&offset) {
+            #[cfg(feature = "v1")]
+            Some(consumer) => LineInfo::new(offset, LineFlags::KERNEL, "", consumer),
+            #[cfg(feature = "v2")]
+            Some(consumer) => {
+                LineInfo::new(offset, LineFlags::GPIO_V2_LINE_FLAG_USED, "", consumer)
+            }
+            None => LineInfo::new(offset, LineFlags::empty(), "", ""),
+        };
+        Ok(info)
+    }
+
+    fn request_lines(&self, request: LineRequest) -> Result<Box<dyn LineBackend>> {
+        Ok(Box::new(self.get_line(request)?))
+    }
+
+    #[cfg(feature = "v1")]
+    fn request_event_line(&self, request: EventRequest) -> Result<Box<dyn LineBackend>> {
+        Ok(Box::new(self.get_event_line(request)?))
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn GpioBackend>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+/// Injected events are read without blocking, [`LineHandle::events`] ends
+/// once they are read.
+///
+/// [`LineHandle::events`]: crate::line::LineHandle::events
+impl LineBackend for MockLineHandle {
+    fn get_values(&self, mask: u64) -> Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let mut bits = 0;
+        for (index, &offset) in self.offsets.iter().enumerate() {
+            if mask >> index & 1 != 0 && state.read(offset) != 0 {
+                bits |= 1 << index;
+            }
+        }
+        Ok(bits)
+    }
+
+    fn set_values(&self, mask: u64, bits: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for (index, &offset) in self.offsets.iter().enumerate() {
+            if mask >> index & 1 != 0 {
+                state.write(offset, (bits >> index & 1) as u8);
+            }
+        }
+        Ok(())
+    }
+
+    fn update_config(&self, config: LineRequest) -> Result<()> {
+        let _ = config;
+        Ok(())
+    }
+
+    fn read_events(&self, buf: &mut [LineEvent]) -> Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            let Some(event) = self.next_event() else {
+                break;
+            };
+            buf[len] = event;
+            len += 1;
+        }
+        Ok(len)
+    }
+}
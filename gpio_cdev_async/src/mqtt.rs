@@ -0,0 +1,394 @@
+//! MQTT bridge publishing edge events and accepting set commands.
+//!
+//! With the default topic prefix `gpio`:
+//! - `gpio/status`: `online` once connected, `offline` as last will, retained.
+//! - `gpio/<offset>/event`: every edge of an input,
+//!   e.g. `{"offset":6,"edge":"rising","timestamp_ns":1234}`.
+//! - `gpio/<offset>/state`: the last known value of a line, `0` or `1`, retained.
+//! - `gpio/<offset>/set`: commands setting an output, `0`/`1`, `off`/`on` or
+//!   `false`/`true`.
+//!
+//! The bridge reconnects to the broker on its own, resubscribing and
+//! republishing the line states once connected again.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::mqtt::MqttBridge;
+//! MqttBridge::builder("/dev/gpiochip0", "broker.local", 1883)
+//!     .set_inputs([6, 13])
+//!     .set_outputs([17])
+//!     .build()
+//!     .unwrap()
+//!     .run()
+//!     .unwrap();
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::HashMap,
+    io,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use rumqttc::{Client, ClientError, Event, LastWill, MqttOptions, Packet, QoS};
+
+use crate::{
+    bridge::BridgeLines,
+    event::LineEventType,
+    line::LineHandle,
+    worker::{self, StopFd, Wakeup, Worker},
+    Result,
+};
+
+/// Builds a [`MqttBridge`], see [`MqttBridge::builder`].
+#[derive(Debug, Clone)]
+pub struct MqttBridgeBuilder {
+    chip: PathBuf,
+    host: String,
+    port: u16,
+    client_id: String,
+    topic_prefix: String,
+    consumer: String,
+    keep_alive: Duration,
+    inputs: Vec<u32>,
+    outputs: Vec<u32>,
+}
+
+impl MqttBridgeBuilder {
+    /// The MQTT client id, `gpio-cdev-<pid>` by default.
+    pub fn set_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = client_id.into();
+        self
+    }
+
+    /// The prefix of all topics, `gpio` by default.
+    pub fn set_topic_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.topic_prefix = prefix.into();
+        self
+    }
+
+    /// The consumer label of the requested lines, `mqtt` by default.
+    pub fn set_consumer(mut self, consumer: impl Into<String>) -> Self {
+        self.consumer = consumer.into();
+        self
+    }
+
+    /// The MQTT keep alive interval, 30 seconds by default.
+    pub fn set_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// The offsets of the lines whose edges are published.
+    pub fn set_inputs(mut self, offsets: impl IntoIterator<Item = u32>) -> Self {
+        self.inputs = offsets.into_iter().collect();
+        self
+    }
+
+    /// The offsets of the lines set by commands, they start low.
+    pub fn set_outputs(mut self, offsets: impl IntoIterator<Item = u32>) -> Self {
+        self.outputs = offsets.into_iter().collect();
+        self
+    }
+
+    /// Requests the lines, the broker is only connected by [`MqttBridge::run`].
+    pub fn build(self) -> Result<MqttBridge> {
+        let BridgeLines { inputs, outputs } =
+            BridgeLines::request(&self.chip, &self.consumer, &self.inputs, &self.outputs)?;
+
+        Ok(MqttBridge {
+            config: self,
+            inputs,
+            outputs,
+        })
+    }
+}
+
+/// An MQTT bridge owning its lines, see the [module documentation](self).
+#[derive(Debug)]
+pub struct MqttBridge {
+    config: MqttBridgeBuilder,
+    inputs: Vec<LineHandle>,
+    outputs: Option<LineHandle>,
+}
+
+impl MqttBridge {
+    /// Bridges the lines of the chip at `chip` to the MQTT broker at `host:port`.
+    pub fn builder(
+        chip: impl AsRef<Path>,
+        host: impl Into<String>,
+        port: u16,
+    ) -> MqttBridgeBuilder {
+        MqttBridgeBuilder {
+            chip: chip.as_ref().to_path_buf(),
+            host: host.into(),
+            port,
+            client_id: format!("gpio-cdev-{}", std::process::id()),
+            topic_prefix: "gpio".into(),
+            consumer: "mqtt".into(),
+            keep_alive: Duration::from_secs(30),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Runs the bridge, only returns if reading edges or setting outputs fails.
+    ///
+    /// Connection errors are retried every second. The threads publishing
+    /// the edges are stopped before it returns.
+    pub fn run(self) -> Result<()> {
+        let config = self.config;
+        let prefix = config.topic_prefix.clone();
+        let status_topic = format!("{prefix}/status");
+
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(config.keep_alive);
+        options.set_last_will(LastWill::new(
+            &status_topic,
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        let (client, mut connection) = Client::new(options, 64);
+
+        // the last known values, republished on every connection
+        let states: Arc<Mutex<HashMap<u32, u8>>> = Default::default();
+        let outputs = self.outputs;
+        if let Some(outputs) = &outputs {
+            let values = outputs.get_values()?;
+            let mut states = states.lock().unwrap();
+            states.extend(values.values_iter().map(|item| (item.offset, item.value)));
+        }
+
+        let (errors_tx, errors) = mpsc::channel();
+        let stop = Arc::new(StopFd::new()?);
+        // stopped and joined when `run` returns
+        let _publishers: Vec<Worker> = self
+            .inputs
+            .into_iter()
+            .map(|input| {
+                let (client, states, errors_tx) =
+                    (client.clone(), states.clone(), errors_tx.clone());
+                let (prefix, stop) = (prefix.clone(), stop.clone());
+                Worker::spawn(
+                    {
+                        let stop = stop.clone();
+                        move || {
+                            if let Err(e) = publish_edges(&input, &client, &prefix, &states, &stop)
+                            {
+                                let _ = errors_tx.send(e);
+                            }
+                            Ok(())
+                        }
+                    },
+                    move || stop.signal(),
+                )
+            })
+            .collect();
+
+        loop {
+            if let Ok(e) = errors.try_recv() {
+                return Err(e);
+            }
+            let event = match connection.recv_timeout(config.keep_alive) {
+                Ok(Ok(event)) => event,
+                // retried by the next receive
+                Ok(Err(_)) => {
+                    std::thread::sleep(Duration::from_secs(1));
+                    continue;
+                }
+                Err(_timeout) => continue,
+            };
+
+            match event {
+                Event::Incoming(Packet::ConnAck(_)) => {
+                    let _ = client.subscribe(format!("{prefix}/+/set"), QoS::AtLeastOnce);
+                    let _ = client.publish(&status_topic, QoS::AtLeastOnce, true, "online");
+                    for (offset, value) in states.lock().unwrap().iter() {
+                        let _ = publish_state(&client, &prefix, *offset, *value);
+                    }
+                }
+                Event::Incoming(Packet::Publish(publish)) => {
+                    let Some(outputs) = &outputs else { continue };
+                    let Some((offset, value)) =
+                        parse_command(&prefix, &publish.topic, &publish.payload)
+                    else {
+                        continue;
+                    };
+                    if !outputs.offsets().contains(&offset) {
+                        continue;
+                    }
+                    {
+                        // also serializes the read-modify-write of v1
+                        let mut states = states.lock().unwrap();
+                        outputs.set_value(offset, value)?;
+                        states.insert(offset, value);
+                    }
+                    let _ = publish_state(&client, &prefix, offset, value);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn publish_edges(
+    input: &LineHandle,
+    client: &Client,
+    prefix: &str,
+    states: &Mutex<HashMap<u32, u8>>,
+    stop: &StopFd,
+) -> Result<()> {
+    let Some(fd) = input.fd() else {
+        return Err(
+            io::Error::new(io::ErrorKind::Unsupported, "the input has no fd to poll").into(),
+        );
+    };
+    let fd = fd.as_raw_fd();
+    let mut events = input.events();
+    loop {
+        if worker::poll(fd, stop, None)? == Wakeup::Stop {
+            return Ok(());
+        }
+        let Some(event) = events.next() else {
+            return Ok(());
+        };
+        let event = event?;
+
+        #[cfg(feature = "v1")]
+        let offset = input.offsets()[0];
+        #[cfg(feature = "v2")]
+        let offset = event.offset();
+        let (edge, value) = match event.event_type()? {
+            LineEventType::RisingEdge => ("rising", 1),
+            LineEventType::FallingEdge => ("falling", 0),
+        };
+        states.lock().unwrap().insert(offset, value);
+
+        let payload = format!(
+            r#"{{"offset":{offset},"edge":"{edge}","timestamp_ns":{}}}"#,
+            event.timestamp_ns()
+        );
+        // dropped while the broker is unreachable, the state is republished once connected
+        let _ = client.try_publish(
+            format!("{prefix}/{offset}/event"),
+            QoS::AtMostOnce,
+            false,
+            payload,
+        );
+        let _ = publish_state(client, prefix, offset, value);
+    }
+}
+
+fn publish_state(
+    client: &Client,
+    prefix: &str,
+    offset: u32,
+    value: u8,
+) -> std::result::Result<(), ClientError> {
+    client.try_publish(
+        format!("{prefix}/{offset}/state"),
+        QoS::AtLeastOnce,
+        true,
+        value.to_string(),
+    )
+}
+
+/// Parses a set command, e.g. `gpio/17/set` with `on`.
+fn parse_command(prefix: &str, topic: &str, payload: &[u8]) -> Option<(u32, u8)> {
+    let offset = topic
+        .strip_prefix(prefix)?
+        .strip_prefix('/')?
+        .strip_suffix("/set")?
+        .parse()
+        .ok()?;
+    let value = match std::str::from_utf8(payload).ok()?.trim() {
+        "0" | "off" | "false" => 0,
+        "1" | "on" | "true" => 1,
+        _ => return None,
+    };
+    Some((offset, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse_command("gpio", "gpio/17/set", b"1"), Some((17, 1)));
+        assert_eq!(parse_command("gpio", "gpio/0/set", b"off"), Some((0, 0)));
+        assert_eq!(parse_command("gpio", "gpio/5/set", b"true"), Some((5, 1)));
+        assert_eq!(
+            parse_command("home/gpio", "home/gpio/5/set", b"on"),
+            Some((5, 1))
+        );
+    }
+
+    #[test]
+    fn rejects_other_prefixes() {
+        for topic in [
+            "home/17/set",
+            "gpiox/17/set",
+            "gpio17/set",
+            "/gpio/17/set",
+            "GPIO/17/set",
+        ] {
+            assert_eq!(parse_command("gpio", topic, b"1"), None, "{topic:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_other_topics() {
+        for topic in [
+            "gpio/17",
+            "gpio/17/get",
+            "gpio/17/state",
+            "gpio/17/set/more",
+            "gpio/1/7/set",
+        ] {
+            assert_eq!(parse_command("gpio", topic, b"1"), None, "{topic:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_non_numeric_offsets() {
+        for topic in [
+            "gpio//set",
+            "gpio/led/set",
+            "gpio/-1/set",
+            "gpio/0x11/set",
+            "gpio/4294967296/set",
+            "gpio/+/set",
+        ] {
+            assert_eq!(parse_command("gpio", topic, b"1"), None, "{topic:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_payloads() {
+        for payload in [&b""[..], b"2", b"toggle", b"ON", b"1 0", b"\xff", b"o\0n"] {
+            assert_eq!(
+                parse_command("gpio", "gpio/17/set", payload),
+                None,
+                "{payload:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn trims_the_payload_only() {
+        assert_eq!(
+            parse_command("gpio", "gpio/17/set", b" on\r\n"),
+            Some((17, 1))
+        );
+        assert_eq!(parse_command("gpio", "gpio/17/set", b"\t0 "), Some((17, 0)));
+        assert_eq!(parse_command("gpio", "gpio/ 17/set", b"1"), None);
+        assert_eq!(parse_command("gpio", "gpio/17 /set", b"1"), None);
+        assert_eq!(parse_command("gpio", " gpio/17/set", b"1"), None);
+    }
+}
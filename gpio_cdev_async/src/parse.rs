@@ -0,0 +1,96 @@
+//! Pure decoders of the records the kernel hands out on reads and ioctls.
+//!
+//! They never panic, whatever the bytes: a buffer that is not a whole number
+//! of records, an unknown event id or an out-of-range attribute count is a
+//! [`ParseError`]. The read paths of [`Chip`](crate::chip::Chip) and
+//! [`LineHandle`](crate::line::LineHandle) run the same checks, so a
+//! misbehaving driver surfaces as [`std::io::ErrorKind::InvalidData`].
+//!
+//! The cargo-fuzz targets in `fuzz/` drive these functions.
+//!
+//! ```
+//! use gpio_cdev_async::parse::{parse_line_events, ParseError};
+//!
+//! assert!(parse_line_events(&[]).unwrap().is_empty());
+//! assert!(matches!(parse_line_events(&[0; 3]), Err(ParseError::Length { len: 3, .. })));
+//! ```
+
+use std::mem::size_of;
+
+use crate::{
+    event::{LineEvent, LineInfoChangedEvent},
+    ffi::common::Pod,
+    line::LineInfo,
+};
+
+/// Why bytes could not be decoded into records.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("short read: {len} bytes is not a whole number of {record}-byte records")]
+    Length { len: usize, record: usize },
+    #[error("unknown edge event id {0}")]
+    EventType(u32),
+    #[error("unknown line info change type {0}")]
+    ChangedType(u32),
+    #[error("{0} line attributes exceed the maximum of 10")]
+    NumAttrs(u32),
+    #[error("unknown line attribute id {0}")]
+    AttrId(u32),
+}
+
+impl From<ParseError> for crate::Error {
+    fn from(e: ParseError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e).into()
+    }
+}
+
+/// Decodes exactly one edge event, as read from a [`LineHandle`](crate::line::LineHandle).
+pub fn parse_line_event(bytes: &[u8]) -> Result<LineEvent, ParseError> {
+    parse_one(bytes)
+}
+
+/// Decodes all the edge events of one read.
+pub fn parse_line_events(bytes: &[u8]) -> Result<Vec<LineEvent>, ParseError> {
+    parse_many(bytes)
+}
+
+/// Decodes exactly one line info change, as read from a [`Chip`](crate::chip::Chip).
+pub fn parse_line_info_changed(bytes: &[u8]) -> Result<LineInfoChangedEvent, ParseError> {
+    parse_one(bytes)
+}
+
+/// Decodes all the line info changes of one read.
+pub fn parse_line_info_changes(bytes: &[u8]) -> Result<Vec<LineInfoChangedEvent>, ParseError> {
+    parse_many(bytes)
+}
+
+/// Decodes exactly one line information, as filled in by the line info ioctls.
+pub fn parse_line_info(bytes: &[u8]) -> Result<LineInfo, ParseError> {
+    parse_one(bytes)
+}
+
+fn parse_one<T: Pod>(bytes: &[u8]) -> Result<T, ParseError> {
+    let record = T::from_bytes(bytes).ok_or(ParseError::Length {
+        len: bytes.len(),
+        record: size_of::<T>(),
+    })?;
+    record.validate()?;
+    Ok(record)
+}
+
+fn parse_many<T: Pod>(bytes: &[u8]) -> Result<Vec<T>, ParseError> {
+    record_count::<T>(bytes.len())?;
+    bytes.chunks_exact(size_of::<T>()).map(parse_one).collect()
+}
+
+/// Returns the number of whole `T` records in `len` bytes.
+///
+/// The kernel only ever hands out whole records, so a trailing partial record
+/// is an error rather than being dropped.
+pub(crate) fn record_count<T: Pod>(len: usize) -> Result<usize, ParseError> {
+    let record = size_of::<T>();
+    if !len.is_multiple_of(record) {
+        return Err(ParseError::Length { len, record });
+    }
+    Ok(len / record)
+}
@@ -0,0 +1,195 @@
+//! Checking and granting access to the GPIO chips.
+//!
+//! The chips are usually owned by `root`, and on many distributions by a
+//! `gpio` group with mode `0660`. [`check`] tells who owns a chip and
+//! whether the calling process can open it, and [`udev_rule`] writes a rule
+//! giving a group access to all the chips.
+//!
+//! # Examples
+//! ```rust,no_run
+//! use gpio_cdev_async::permissions;
+//!
+//! for path in permissions::chip_paths()? {
+//!     let access = permissions::check(&path)?;
+//!     if !access.accessible {
+//!         println!("{} is not accessible: {access:?}", path.display());
+//!     }
+//! }
+//! print!("{}", permissions::udev_rule("gpio")?);
+//! # Ok::<(), gpio_cdev_async::Error>(())
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    ffi::{CStr, CString},
+    fs, io,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use crate::Result;
+
+/// Where [`udev_rule`] is usually installed.
+pub const UDEV_RULE_PATH: &str = "/etc/udev/rules.d/60-gpio-cdev.rules";
+
+/// The ownership of a chip and whether the calling process can open it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChipAccess {
+    pub path: PathBuf,
+    pub uid: u32,
+    pub gid: u32,
+    /// The permission bits, e.g. `0o660`.
+    pub mode: u32,
+    /// Whether the calling process can open the chip for reading and writing.
+    pub accessible: bool,
+}
+
+/// The paths of all the chips, `/dev/gpiochip*`, in the order of their
+/// numbers.
+pub fn chip_paths() -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir("/dev")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_chip_path(path))
+        .collect();
+    // `gpiochip2` before `gpiochip10`
+    paths.sort_by_cached_key(|path| {
+        let number = path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_prefix("gpiochip")?.parse::<u32>().ok());
+        (number.is_none(), number, path.clone())
+    });
+    Ok(paths)
+}
+
+/// Whether `path` is the path of a chip, `/dev/gpiochip*`.
+///
+/// # Examples
+/// ```rust
+/// # use gpio_cdev_async::permissions::is_chip_path;
+/// assert!(is_chip_path("/dev/gpiochip0"));
+/// assert!(!is_chip_path("/dev/../dev/gpiochip0"));
+/// assert!(!is_chip_path("/tmp/gpiochip0"));
+/// ```
+pub fn is_chip_path(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    path.parent() == Some(Path::new("/dev"))
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("gpiochip"))
+}
+
+/// Inspects the ownership of the chip at `path`.
+pub fn check(path: impl AsRef<Path>) -> Result<ChipAccess> {
+    let path = path.as_ref();
+    let metadata = fs::metadata(path)?;
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(io::Error::from)?;
+    // SAFETY: `c_path` is a valid C string
+    let accessible = unsafe { libc::access(c_path.as_ptr(), libc::R_OK | libc::W_OK) } == 0;
+    Ok(ChipAccess {
+        path: path.to_path_buf(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        mode: metadata.mode() & 0o7777,
+        accessible,
+    })
+}
+
+/// The effective and supplementary groups of the calling process.
+pub fn groups() -> Result<Vec<u32>> {
+    // SAFETY: a null list only queries the number of groups
+    let len = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if len == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let mut groups = vec![0; len as usize];
+    // SAFETY: `groups` has room for `len` groups
+    let len = unsafe { libc::getgroups(len, groups.as_mut_ptr()) };
+    if len == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    groups.truncate(len as usize);
+    // SAFETY: `getegid` has no requirements
+    let egid = unsafe { libc::getegid() };
+    if !groups.contains(&egid) {
+        groups.insert(0, egid);
+    }
+    Ok(groups)
+}
+
+/// The id of the group `name`, `None` if there is no such group.
+pub fn group_id(name: &str) -> Result<Option<u32>> {
+    let name = CString::new(name).map_err(io::Error::from)?;
+    with_group(
+        // SAFETY: `name` is a valid C string and `buf` is as long as given
+        |group, buf, result| unsafe {
+            libc::getgrnam_r(name.as_ptr(), group, buf.as_mut_ptr(), buf.len(), result)
+        },
+        |group| group.gr_gid,
+    )
+}
+
+/// The name of the group `gid`, `None` if there is no such group.
+pub fn group_name(gid: u32) -> Result<Option<String>> {
+    with_group(
+        // SAFETY: `buf` is as long as given
+        |group, buf, result| unsafe {
+            libc::getgrgid_r(gid, group, buf.as_mut_ptr(), buf.len(), result)
+        },
+        // SAFETY: the name is a C string in the buffer, which is still alive
+        |group| unsafe { CStr::from_ptr(group.gr_name) }.to_string_lossy().into_owned(),
+    )
+}
+
+/// Calls a `getgr*_r` function, growing its buffer until the entry fits,
+/// and maps the entry found before the buffer is dropped.
+fn with_group<T>(
+    mut get: impl FnMut(&mut libc::group, &mut [libc::c_char], &mut *mut libc::group) -> libc::c_int,
+    map: impl FnOnce(&libc::group) -> T,
+) -> Result<Option<T>> {
+    let mut buf = vec![0; 1024];
+    loop {
+        // SAFETY: `group` only contains integers and pointers
+        let mut group: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        match get(&mut group, &mut buf, &mut result) {
+            0 if result.is_null() => return Ok(None),
+            0 => return Ok(Some(map(&group))),
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            errno => return Err(io::Error::from_raw_os_error(errno).into()),
+        }
+    }
+}
+
+/// A udev rule giving `group` read and write access to all the chips, to
+/// install at [`UDEV_RULE_PATH`].
+///
+/// The rule takes effect for new devices, or after
+/// `udevadm trigger --subsystem-match=gpio`.
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] if `group` is not a portable
+/// group name: empty, starting with `-`, or with other characters than
+/// ASCII letters, digits, `_`, `.` and `-`, which could alter the rule.
+///
+/// # Examples
+/// ```rust
+/// # use gpio_cdev_async::permissions::udev_rule;
+/// assert!(udev_rule("gpio").unwrap().contains(r#"GROUP="gpio""#));
+/// assert!(udev_rule(r#"gpio", MODE="0666"#).is_err());
+/// assert!(udev_rule("-gpio").is_err());
+/// ```
+pub fn udev_rule(group: &str) -> Result<String> {
+    let portable = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-');
+    if group.is_empty() || group.starts_with('-') || !group.chars().all(portable) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{group:?} is not a portable group name"),
+        )
+        .into());
+    }
+    Ok(format!(
+        "# give the `{group}` group access to the GPIO chips\n\
+         SUBSYSTEM==\"gpio\", KERNEL==\"gpiochip*\", GROUP=\"{group}\", MODE=\"0660\"\n"
+    ))
+}
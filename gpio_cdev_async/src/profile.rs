@@ -0,0 +1,73 @@
+//! Timing of the ioctls sent to the kernel.
+//!
+//! Some pin controllers sleep in their get and set paths, e.g. the ones
+//! behind I²C or SPI, so a value ioctl can take milliseconds. A hook
+//! installed with [`set_ioctl_hook`] is called after every ioctl of this
+//! crate with its [`IoctlKind`], how long it took and its result, so an
+//! application can export the durations as a histogram or log slow calls.
+//!
+//! The hook runs on the thread that made the ioctl, inside the call, and
+//! should be quick.
+//!
+//! # Examples
+//! ```rust,no_run
+//! use std::time::Duration;
+//!
+//! use gpio_cdev_async::profile;
+//!
+//! profile::set_ioctl_hook(|kind, elapsed, result| {
+//!     if elapsed > Duration::from_millis(1) {
+//!         eprintln!("slow {kind:?}: {elapsed:?} ({})", result.is_ok());
+//!     }
+//! });
+//! // the chips and lines of the application
+//! profile::clear_ioctl_hook();
+//! ```
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use crate::{Error, IoctlKind};
+
+/// The signature of an ioctl hook, see [`set_ioctl_hook`].
+///
+/// The result is the return value of the ioctl or the error it failed with.
+pub type IoctlHook = dyn Fn(IoctlKind, Duration, std::result::Result<i32, &Error>) + Send + Sync;
+
+static HOOK: RwLock<Option<Arc<IoctlHook>>> = RwLock::new(None);
+/// Whether [`HOOK`] is set, so ioctls skip the clock and the lock without one.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Installs `hook` for all ioctls of the process, replacing the previous one.
+pub fn set_ioctl_hook(
+    hook: impl Fn(IoctlKind, Duration, std::result::Result<i32, &Error>) + Send + Sync + 'static,
+) {
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(hook));
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Removes the installed hook, if any.
+pub fn clear_ioctl_hook() {
+    ENABLED.store(false, Ordering::Release);
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Returns whether ioctls should be timed.
+#[inline]
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Calls the installed hook, if any.
+pub(crate) fn record(kind: IoctlKind, elapsed: Duration, result: &crate::Result<libc::c_int>) {
+    // cloned out of the lock, so a hook may replace itself
+    let hook = HOOK.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(hook) = hook {
+        hook(kind, elapsed, result.as_ref().map(|ret| *ret));
+    }
+}
@@ -0,0 +1,113 @@
+//! Process setup for low-latency event loops.
+//!
+//! A page fault in the middle of a loop costs far more than the ioctls in
+//! it. [`lock_memory`] locks the current and future pages of the process
+//! and faults in stack and heap in advance, so the loop does not take page
+//! faults later; [`set_fifo_priority`] then moves the calling thread to
+//! `SCHED_FIFO`. Both need `CAP_IPC_LOCK` and `CAP_SYS_NICE` (or matching
+//! `RLIMIT_MEMLOCK`/`RLIMIT_RTPRIO` limits).
+//!
+//! # Examples
+//! ```rust,no_run
+//! use gpio_cdev_async::realtime;
+//!
+//! realtime::lock_memory()?;
+//! realtime::set_fifo_priority(80)?;
+//! // the event loop
+//! # Ok::<(), gpio_cdev_async::Error>(())
+//! ```
+
+use std::hint::black_box;
+
+use crate::Result;
+
+/// The stack [`lock_memory`] faults in, enough for the loops of this crate.
+pub const DEFAULT_STACK_PREFAULT: usize = 256 * 1024;
+/// The heap [`lock_memory`] faults in.
+pub const DEFAULT_HEAP_PREFAULT: usize = 8 * 1024 * 1024;
+
+const PAGE: usize = 4096;
+
+/// Locks all current and future memory of the process and faults in
+/// [`DEFAULT_STACK_PREFAULT`] of the calling thread's stack and
+/// [`DEFAULT_HEAP_PREFAULT`] of heap.
+pub fn lock_memory() -> Result<()> {
+    lock_memory_with(DEFAULT_STACK_PREFAULT, DEFAULT_HEAP_PREFAULT)
+}
+
+/// Like [`lock_memory`], with the given prefault sizes in bytes.
+pub fn lock_memory_with(stack: usize, heap: usize) -> Result<()> {
+    // SAFETY: `mlockall` has no memory safety requirements
+    if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    prefault_stack(stack);
+    prefault_heap(heap);
+    Ok(())
+}
+
+/// Touches `bytes` of the calling thread's stack, so they are mapped (and
+/// locked after [`lock_memory`]) before the loop needs them.
+///
+/// The stack of the thread must be larger than `bytes`.
+pub fn prefault_stack(bytes: usize) {
+    #[inline(never)]
+    fn touch(pages: usize) {
+        let page = black_box([0u8; PAGE]);
+        if pages > 1 {
+            touch(pages - 1);
+        }
+        black_box(&page);
+    }
+    touch(bytes.div_ceil(PAGE));
+}
+
+/// Touches `bytes` of heap and keeps it with the allocator once freed, so
+/// later allocations up to that size do not fault.
+///
+/// On glibc this also stops `malloc` from trimming the heap or serving large
+/// allocations with `mmap`, which would hand the pages back to the kernel.
+pub fn prefault_heap(bytes: usize) {
+    #[cfg(target_env = "gnu")]
+    // SAFETY: `mallopt` has no memory safety requirements
+    unsafe {
+        libc::mallopt(libc::M_TRIM_THRESHOLD, -1);
+        libc::mallopt(libc::M_MMAP_MAX, 0);
+    }
+    let mut heap = vec![0u8; bytes];
+    for byte in heap.iter_mut().step_by(PAGE) {
+        // a zeroed allocation may come straight from untouched pages
+        *black_box(byte) = 1;
+    }
+    drop(black_box(heap));
+}
+
+/// Moves the calling thread to `SCHED_FIFO` at `priority`, from 1 to 99.
+pub fn set_fifo_priority(priority: i32) -> Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    // SAFETY: `param` is a valid `sched_param`
+    if unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Pins the calling thread to the CPUs in `cpus`.
+pub fn set_cpu_affinity(cpus: impl IntoIterator<Item = usize>) -> Result<()> {
+    // SAFETY: an all-zero `cpu_set_t` is the empty set
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(std::io::Error::from_raw_os_error(libc::EINVAL).into());
+        }
+        // SAFETY: `cpu` is within the set, checked above
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    // SAFETY: `set` is a valid `cpu_set_t` of the given size
+    if unsafe { libc::sched_setaffinity(0, std::mem::size_of_val(&set), &set) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
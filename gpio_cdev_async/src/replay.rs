@@ -0,0 +1,807 @@
+//! Recording, replaying and fault injection of backends.
+//!
+//! A [`Recorder`] wraps a [`GpioBackend`] and records every operation on the
+//! chip and its lines, with its arguments and outcome, into a [`Trace`]. It can
+//! also fail chosen operations with a chosen errno, to exercise the error
+//! handling of an application deterministically.
+//!
+//! A [`Replayer`] is a backend serving a trace back: each operation must match
+//! the next recorded one and gets its recorded outcome, so an application
+//! recorded against hardware runs again without it. Operations of other
+//! threads may interleave as long as each thread keeps its recorded order.
+//!
+//! A trace is saved and loaded as JSON lines, one operation per line, e.g.
+//! `{"handle":0,"mask":1,"ok":1,"op":"get_values"}`. The v2 attributes of line
+//! information are not recorded.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{chip::Chip, replay::{Recorder, Replayer, Trace}};
+//! # fn run(chip: &Chip) {}
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let recorder = Recorder::open("/dev/gpiochip0")?;
+//! // the application sees its third operation fail with EBUSY
+//! recorder.fail_nth(3, libc::EBUSY);
+//! run(&Chip::from_backend("/dev/gpiochip0", recorder.clone()));
+//! std::fs::write("trace.jsonl", recorder.trace().to_string())?;
+//!
+//! // later, without the chip
+//! let trace: Trace = std::fs::read_to_string("trace.jsonl")?.parse()?;
+//! let replayer = Replayer::new(trace);
+//! run(&Chip::from_backend("/dev/gpiochip0", replayer.clone()));
+//! assert_eq!(replayer.remaining(), 0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    io,
+    os::fd::BorrowedFd,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Map, Value};
+
+#[cfg(feature = "v1")]
+use crate::line::{EventFlags, EventRequest};
+use crate::{
+    backend::{cdev::Cdev, GpioBackend, LineBackend},
+    chip::ChipInfo,
+    error::ioctl_error,
+    event::{LineChangedType, LineEvent, LineEventType, LineInfoChangedEvent},
+    line::{LineFlags, LineInfo, LineRequest},
+    Error, IoctlKind, Result,
+};
+
+/// How long a replayed operation waits for the operations recorded before it
+/// to be replayed by other threads.
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The operations of a backend, see [`Recorder::fail_nth_of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OpKind {
+    ChipInfo,
+    LineInfo,
+    RequestLines,
+    #[cfg(feature = "v1")]
+    RequestEventLine,
+    WatchLineInfo,
+    UnwatchLineInfo,
+    ReadLineInfoChanges,
+    GetValues,
+    SetValues,
+    UpdateConfig,
+    ReadEvents,
+}
+
+impl OpKind {
+    const ALL: &[Self] = &[
+        Self::ChipInfo,
+        Self::LineInfo,
+        Self::RequestLines,
+        #[cfg(feature = "v1")]
+        Self::RequestEventLine,
+        Self::WatchLineInfo,
+        Self::UnwatchLineInfo,
+        Self::ReadLineInfoChanges,
+        Self::GetValues,
+        Self::SetValues,
+        Self::UpdateConfig,
+        Self::ReadEvents,
+    ];
+
+    /// The name of the operation in a trace.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ChipInfo => "chip_info",
+            Self::LineInfo => "line_info",
+            Self::RequestLines => "request_lines",
+            #[cfg(feature = "v1")]
+            Self::RequestEventLine => "request_event_line",
+            Self::WatchLineInfo => "watch_line_info",
+            Self::UnwatchLineInfo => "unwatch_line_info",
+            Self::ReadLineInfoChanges => "read_line_info_changes",
+            Self::GetValues => "get_values",
+            Self::SetValues => "set_values",
+            Self::UpdateConfig => "update_config",
+            Self::ReadEvents => "read_events",
+        }
+    }
+
+    /// The ioctl of the operation on a kernel chip, reads have none.
+    fn ioctl(self) -> Option<IoctlKind> {
+        match self {
+            Self::ChipInfo => Some(IoctlKind::GetChipInfo),
+            Self::LineInfo | Self::WatchLineInfo => Some(IoctlKind::GetLineInfo),
+            Self::RequestLines => Some(IoctlKind::GetLine),
+            #[cfg(feature = "v1")]
+            Self::RequestEventLine => Some(IoctlKind::GetLineEvent),
+            Self::GetValues => Some(IoctlKind::GetValues),
+            Self::SetValues => Some(IoctlKind::SetValues),
+            Self::UpdateConfig => Some(IoctlKind::SetLineConfig),
+            Self::UnwatchLineInfo | Self::ReadLineInfoChanges | Self::ReadEvents => None,
+        }
+    }
+
+    /// The error of the operation failing with `errno`, like the kernel's.
+    fn error(self, errno: i32) -> Error {
+        match self.ioctl() {
+            Some(kind) => ioctl_error(kind, nix::Error::from_raw(errno)),
+            None => io::Error::from_raw_os_error(errno).into(),
+        }
+    }
+}
+
+/// A recorded operation, its arguments and outcome.
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    kind: OpKind,
+    args: Map<String, Value>,
+    /// The returned value or `{"errno":...,"message":...}`.
+    outcome: std::result::Result<Value, Value>,
+}
+
+impl Record {
+    fn to_json(&self) -> Value {
+        let mut object = self.args.clone();
+        object.insert("op".into(), self.kind.name().into());
+        match &self.outcome {
+            Ok(value) => object.insert("ok".into(), value.clone()),
+            Err(error) => object.insert("err".into(), error.clone()),
+        };
+        Value::Object(object)
+    }
+
+    fn from_json(line: &str) -> Option<Self> {
+        let mut args: Map<String, Value> = serde_json::from_str(line).ok()?;
+        let name = args.remove("op")?;
+        let kind = *OpKind::ALL.iter().find(|kind| name == kind.name())?;
+        let outcome = match (args.remove("ok"), args.remove("err")) {
+            (Some(value), None) => Ok(value),
+            (None, Some(error)) => Err(error),
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            args,
+            outcome,
+        })
+    }
+
+    /// The error recorded as `error`, as returned by `kind`.
+    fn error(kind: OpKind, error: &Value) -> Error {
+        match error["errno"].as_i64() {
+            Some(errno) => kind.error(errno as i32),
+            None => io::Error::other(error["message"].as_str().unwrap_or_default()).into(),
+        }
+    }
+}
+
+/// The operations recorded by a [`Recorder`], in order.
+///
+/// Displayed and parsed as JSON lines, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    records: Vec<Record>,
+}
+
+impl Trace {
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for record in &self.records {
+            writeln!(f, "{}", record.to_json())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Trace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let records = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                Record::from_json(line)
+                    .ok_or_else(|| invalid(format!("invalid record on line {}", i + 1)))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { records })
+    }
+}
+
+fn invalid(message: String) -> Error {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+fn object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(object) => object,
+        _ => Map::new(),
+    }
+}
+
+fn request_args(request: &LineRequest) -> Value {
+    let offsets = request.offsets();
+    #[cfg(feature = "v1")]
+    let flags: Vec<_> = offsets.iter().map(|_| request.flags().bits()).collect();
+    #[cfg(feature = "v2")]
+    let flags: Vec<_> = offsets
+        .iter()
+        .map(|&offset| {
+            request
+                .flags_of_offset(offset)
+                .unwrap_or(request.flags())
+                .bits()
+        })
+        .collect();
+    let values: Vec<_> = offsets
+        .iter()
+        .map(|&offset| request.default_value_of_offset(offset))
+        .collect();
+    json!({
+        "offsets": offsets,
+        "flags": flags,
+        "values": values,
+        "consumer": request.consumer(),
+    })
+}
+
+fn chip_info_json(info: &ChipInfo) -> Value {
+    json!({ "name": info.name(), "label": info.label(), "lines": info.lines() })
+}
+
+fn chip_info_from(value: &Value) -> Option<ChipInfo> {
+    Some(ChipInfo::new(
+        value["name"].as_str()?,
+        value["label"].as_str()?,
+        value["lines"].as_u64()? as u32,
+    ))
+}
+
+fn line_info_json(info: &LineInfo) -> Value {
+    json!({
+        "offset": info.offset(),
+        "flags": info.flags().bits(),
+        "name": info.name(),
+        "consumer": info.consumer(),
+    })
+}
+
+fn line_info_from(value: &Value) -> Option<LineInfo> {
+    Some(LineInfo::new(
+        value["offset"].as_u64()? as u32,
+        LineFlags::from_bits_retain(value["flags"].as_u64()? as _),
+        value["name"].as_str()?,
+        value["consumer"].as_str()?,
+    ))
+}
+
+fn event_json(event: &LineEvent) -> Value {
+    #[cfg(feature = "v1")]
+    let (offset, seqno, line_seqno) = (0, 0, 0);
+    #[cfg(feature = "v2")]
+    let (offset, seqno, line_seqno) = (event.offset(), event.seqno(), event.line_seqno());
+    json!({
+        "type": event.event_type().ok().map(|edge| edge as u32),
+        "timestamp_ns": event.timestamp_ns(),
+        "offset": offset,
+        "seqno": seqno,
+        "line_seqno": line_seqno,
+    })
+}
+
+fn event_from(value: &Value) -> Option<LineEvent> {
+    Some(LineEvent::new(
+        value["offset"].as_u64()? as u32,
+        LineEventType::try_from(value["type"].as_u64()? as u32).ok()?,
+        value["timestamp_ns"].as_u64()?,
+        value["seqno"].as_u64()? as u32,
+        value["line_seqno"].as_u64()? as u32,
+    ))
+}
+
+fn info_change_json(event: &LineInfoChangedEvent) -> Value {
+    json!({
+        "info": line_info_json(event.lineinfo()),
+        "type": event.event_type() as u32,
+        "timestamp_ns": event.timestamp_ns(),
+    })
+}
+
+fn info_change_from(value: &Value) -> Option<LineInfoChangedEvent> {
+    Some(LineInfoChangedEvent::new(
+        line_info_from(&value["info"])?,
+        LineChangedType::from(value["type"].as_u64()? as u32),
+        value["timestamp_ns"].as_u64()?,
+    ))
+}
+
+/// Fails the `nth` operation of `kind`, or of any kind if `None`.
+#[derive(Debug)]
+struct Fault {
+    kind: Option<OpKind>,
+    nth: usize,
+    errno: i32,
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    records: Vec<Record>,
+    faults: Vec<Fault>,
+    /// The operations started so far, of any kind and per kind.
+    ops: usize,
+    ops_of: BTreeMap<OpKind, usize>,
+}
+
+#[derive(Debug)]
+struct RecorderInner {
+    backend: Box<dyn GpioBackend>,
+    state: Mutex<RecorderState>,
+    next_handle: AtomicU32,
+}
+
+impl RecorderInner {
+    /// Runs `op` unless a fault is injected, and records its outcome.
+    fn call<T>(
+        &self,
+        kind: OpKind,
+        args: Value,
+        op: impl FnOnce() -> Result<T>,
+        encode: impl FnOnce(&T) -> Value,
+    ) -> Result<T> {
+        let fault = {
+            let mut state = self.state.lock().unwrap();
+            state.ops += 1;
+            let (ops, ops_of) = (state.ops, state.ops_of.entry(kind).or_default());
+            *ops_of += 1;
+            let ops_of = *ops_of;
+            state
+                .faults
+                .iter()
+                .find(|fault| match fault.kind {
+                    None => fault.nth == ops,
+                    Some(fault_kind) => fault_kind == kind && fault.nth == ops_of,
+                })
+                .map(|fault| fault.errno)
+        };
+
+        let result = match fault {
+            Some(errno) => Err(kind.error(errno)),
+            None => op(),
+        };
+        let outcome = match &result {
+            Ok(value) => Ok(encode(value)),
+            Err(e) => Err(json!({ "errno": e.raw_os_error(), "message": e.to_string() })),
+        };
+        let record = Record {
+            kind,
+            args: object(args),
+            outcome,
+        };
+        self.state.lock().unwrap().records.push(record);
+        result
+    }
+
+    fn line(self: &Arc<Self>, line: Box<dyn LineBackend>) -> RecordedLine {
+        RecordedLine {
+            id: self.next_handle.fetch_add(1, Ordering::Relaxed),
+            line,
+            recorder: self.clone(),
+        }
+    }
+}
+
+/// Records the operations on a backend, see the [module documentation](self).
+///
+/// Clones share the backend and the trace.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    inner: Arc<RecorderInner>,
+}
+
+impl Recorder {
+    pub fn new(backend: impl GpioBackend + 'static) -> Self {
+        Self {
+            inner: Arc::new(RecorderInner {
+                backend: Box::new(backend),
+                state: Default::default(),
+                next_handle: AtomicU32::new(0),
+            }),
+        }
+    }
+
+    /// Records the operations on the kernel chip at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(Cdev::open(path.as_ref())?))
+    }
+
+    /// Fails the `nth` operation with `errno` instead of running it, counting
+    /// from 1 since the recorder was created, e.g. `recorder.fail_nth(1,
+    /// libc::EIO)`.
+    ///
+    /// Failed ioctls return [`Error::Ioctl`] like the kernel's.
+    pub fn fail_nth(&self, nth: usize, errno: i32) {
+        let fault = Fault {
+            kind: None,
+            nth,
+            errno,
+        };
+        self.inner.state.lock().unwrap().faults.push(fault);
+    }
+
+    /// Fails the `nth` operation of `kind` with `errno`, see
+    /// [`Recorder::fail_nth`].
+    pub fn fail_nth_of(&self, kind: OpKind, nth: usize, errno: i32) {
+        let fault = Fault {
+            kind: Some(kind),
+            nth,
+            errno,
+        };
+        self.inner.state.lock().unwrap().faults.push(fault);
+    }
+
+    /// The operations recorded so far, injected faults included.
+    pub fn trace(&self) -> Trace {
+        let records = self.inner.state.lock().unwrap().records.clone();
+        Trace { records }
+    }
+}
+
+impl GpioBackend for Recorder {
+    fn chip_info(&self) -> Result<ChipInfo> {
+        let backend = &self.inner.backend;
+        self.inner.call(
+            OpKind::ChipInfo,
+            json!({}),
+            || backend.chip_info(),
+            chip_info_json,
+        )
+    }
+
+    fn line_info(&self, offset: u32) -> Result<LineInfo> {
+        let backend = &self.inner.backend;
+        self.inner.call(
+            OpKind::LineInfo,
+            json!({ "offset": offset }),
+            || backend.line_info(offset),
+            line_info_json,
+        )
+    }
+
+    fn request_lines(&self, request: LineRequest) -> Result<Box<dyn LineBackend>> {
+        let args = request_args(&request);
+        let line = self.inner.call(
+            OpKind::RequestLines,
+            args,
+            || Ok(self.inner.line(self.inner.backend.request_lines(request)?)),
+            |line| json!(line.id),
+        )?;
+        Ok(Box::new(line))
+    }
+
+    #[cfg(feature = "v1")]
+    fn request_event_line(&self, request: EventRequest) -> Result<Box<dyn LineBackend>> {
+        let args = json!({
+            "offset": request.offset(),
+            "handle_flags": request.handle_flags().bits(),
+            "event_flags": request.event_flags().bits(),
+            "consumer": request.consumer(),
+        });
+        let line = self.inner.call(
+            OpKind::RequestEventLine,
+            args,
+            || {
+                Ok(self
+                    .inner
+                    .line(self.inner.backend.request_event_line(request)?))
+            },
+            |line| json!(line.id),
+        )?;
+        Ok(Box::new(line))
+    }
+
+    fn watch_line_info(&self, offset: u32) -> Result<LineInfo> {
+        let backend = &self.inner.backend;
+        self.inner.call(
+            OpKind::WatchLineInfo,
+            json!({ "offset": offset }),
+            || backend.watch_line_info(offset),
+            line_info_json,
+        )
+    }
+
+    fn unwatch_line_info(&self, offset: u32) -> Result<()> {
+        let backend = &self.inner.backend;
+        self.inner.call(
+            OpKind::UnwatchLineInfo,
+            json!({ "offset": offset }),
+            || backend.unwatch_line_info(offset),
+            |_| Value::Null,
+        )
+    }
+
+    fn read_line_info_changes(&self, buf: &mut [LineInfoChangedEvent]) -> Result<usize> {
+        let backend = &self.inner.backend;
+        let (read, _) = self.inner.call(
+            OpKind::ReadLineInfoChanges,
+            json!({ "len": buf.len() }),
+            || {
+                let read = backend.read_line_info_changes(buf)?;
+                Ok((read, buf[..read].iter().map(info_change_json).collect()))
+            },
+            |(_, changes): &(usize, Vec<_>)| changes.clone().into(),
+        )?;
+        Ok(read)
+    }
+}
+
+/// Lines requested through a [`Recorder`].
+#[derive(Debug)]
+struct RecordedLine {
+    id: u32,
+    line: Box<dyn LineBackend>,
+    recorder: Arc<RecorderInner>,
+}
+
+impl LineBackend for RecordedLine {
+    fn get_values(&self, mask: u64) -> Result<u64> {
+        self.recorder.call(
+            OpKind::GetValues,
+            json!({ "handle": self.id, "mask": mask }),
+            || self.line.get_values(mask),
+            |&bits| json!(bits),
+        )
+    }
+
+    fn set_values(&self, mask: u64, bits: u64) -> Result<()> {
+        self.recorder.call(
+            OpKind::SetValues,
+            json!({ "handle": self.id, "mask": mask, "bits": bits }),
+            || self.line.set_values(mask, bits),
+            |_| Value::Null,
+        )
+    }
+
+    fn update_config(&self, config: LineRequest) -> Result<()> {
+        let mut args = request_args(&config);
+        args["handle"] = json!(self.id);
+        self.recorder.call(
+            OpKind::UpdateConfig,
+            args,
+            || self.line.update_config(config),
+            |_| Value::Null,
+        )
+    }
+
+    fn read_events(&self, buf: &mut [LineEvent]) -> Result<usize> {
+        let (read, _) = self.recorder.call(
+            OpKind::ReadEvents,
+            json!({ "handle": self.id, "len": buf.len() }),
+            || {
+                let read = self.line.read_events(buf)?;
+                Ok((read, buf[..read].iter().map(event_json).collect()))
+            },
+            |(_, events): &(usize, Vec<_>)| events.clone().into(),
+        )?;
+        Ok(read)
+    }
+
+    fn as_fd(&self) -> Option<BorrowedFd<'_>> {
+        self.line.as_fd()
+    }
+}
+
+#[derive(Debug)]
+struct ReplayerInner {
+    records: Vec<Record>,
+    /// The index of the next record to replay.
+    next: Mutex<usize>,
+    replayed: Condvar,
+}
+
+impl ReplayerInner {
+    /// Replays the next record, which must be `kind` with `args`.
+    fn replay(&self, kind: OpKind, args: Value) -> Result<Value> {
+        let args = object(args);
+        let op = || {
+            let mut op = args.clone();
+            op.insert("op".into(), kind.name().into());
+            Value::Object(op)
+        };
+        let deadline = Instant::now() + REPLAY_TIMEOUT;
+        let mut next = self.next.lock().unwrap();
+        loop {
+            let Some(record) = self.records.get(*next) else {
+                return Err(diverged(format!("{} after the end of the trace", op())));
+            };
+            if record.kind == kind && record.args == args {
+                *next += 1;
+                self.replayed.notify_all();
+                return record
+                    .outcome
+                    .clone()
+                    .map_err(|error| Record::error(kind, &error));
+            }
+
+            // the record may be replayed by another thread
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                return Err(diverged(format!(
+                    "{} instead of record {}: {}",
+                    op(),
+                    *next,
+                    record.to_json()
+                )));
+            }
+            next = self.replayed.wait_timeout(next, timeout).unwrap().0;
+        }
+    }
+}
+
+fn diverged(message: String) -> Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("replay diverged: {message}"),
+    )
+    .into()
+}
+
+fn malformed(kind: OpKind) -> Error {
+    invalid(format!("malformed {} record", kind.name()))
+}
+
+/// Serves a [`Trace`] back, see the [module documentation](self).
+///
+/// Operations not matching the trace fail with
+/// [`io::ErrorKind::InvalidInput`]. Clones share the progress.
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    inner: Arc<ReplayerInner>,
+}
+
+impl Replayer {
+    pub fn new(trace: Trace) -> Self {
+        Self {
+            inner: Arc::new(ReplayerInner {
+                records: trace.records,
+                next: Mutex::new(0),
+                replayed: Condvar::new(),
+            }),
+        }
+    }
+
+    /// How many records are not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.inner.records.len() - *self.inner.next.lock().unwrap()
+    }
+
+    fn line(&self, kind: OpKind, value: &Value) -> Result<Box<dyn LineBackend>> {
+        let id = value.as_u64().ok_or_else(|| malformed(kind))? as u32;
+        Ok(Box::new(ReplayedLine {
+            id,
+            replayer: self.inner.clone(),
+        }))
+    }
+}
+
+impl GpioBackend for Replayer {
+    fn chip_info(&self) -> Result<ChipInfo> {
+        let kind = OpKind::ChipInfo;
+        let value = self.inner.replay(kind, json!({}))?;
+        chip_info_from(&value).ok_or_else(|| malformed(kind))
+    }
+
+    fn line_info(&self, offset: u32) -> Result<LineInfo> {
+        let kind = OpKind::LineInfo;
+        let value = self.inner.replay(kind, json!({ "offset": offset }))?;
+        line_info_from(&value).ok_or_else(|| malformed(kind))
+    }
+
+    fn request_lines(&self, request: LineRequest) -> Result<Box<dyn LineBackend>> {
+        let kind = OpKind::RequestLines;
+        let value = self.inner.replay(kind, request_args(&request))?;
+        self.line(kind, &value)
+    }
+
+    #[cfg(feature = "v1")]
+    fn request_event_line(&self, request: EventRequest) -> Result<Box<dyn LineBackend>> {
+        let kind = OpKind::RequestEventLine;
+        let args = json!({
+            "offset": request.offset(),
+            "handle_flags": request.handle_flags().bits(),
+            "event_flags": request.event_flags().bits(),
+            "consumer": request.consumer(),
+        });
+        let value = self.inner.replay(kind, args)?;
+        self.line(kind, &value)
+    }
+
+    fn watch_line_info(&self, offset: u32) -> Result<LineInfo> {
+        let kind = OpKind::WatchLineInfo;
+        let value = self.inner.replay(kind, json!({ "offset": offset }))?;
+        line_info_from(&value).ok_or_else(|| malformed(kind))
+    }
+
+    fn unwatch_line_info(&self, offset: u32) -> Result<()> {
+        let kind = OpKind::UnwatchLineInfo;
+        self.inner.replay(kind, json!({ "offset": offset }))?;
+        Ok(())
+    }
+
+    fn read_line_info_changes(&self, buf: &mut [LineInfoChangedEvent]) -> Result<usize> {
+        let kind = OpKind::ReadLineInfoChanges;
+        let value = self.inner.replay(kind, json!({ "len": buf.len() }))?;
+        fill(buf, &value, info_change_from).ok_or_else(|| malformed(kind))
+    }
+}
+
+/// Fills `buf` with the array of records `value`.
+fn fill<T>(buf: &mut [T], value: &Value, decode: impl Fn(&Value) -> Option<T>) -> Option<usize> {
+    let records = value.as_array()?;
+    if records.len() > buf.len() {
+        return None;
+    }
+    for (slot, record) in buf.iter_mut().zip(records) {
+        *slot = decode(record)?;
+    }
+    Some(records.len())
+}
+
+/// Lines requested from a [`Replayer`].
+#[derive(Debug)]
+struct ReplayedLine {
+    id: u32,
+    replayer: Arc<ReplayerInner>,
+}
+
+impl LineBackend for ReplayedLine {
+    fn get_values(&self, mask: u64) -> Result<u64> {
+        let kind = OpKind::GetValues;
+        let value = self
+            .replayer
+            .replay(kind, json!({ "handle": self.id, "mask": mask }))?;
+        value.as_u64().ok_or_else(|| malformed(kind))
+    }
+
+    fn set_values(&self, mask: u64, bits: u64) -> Result<()> {
+        let args = json!({ "handle": self.id, "mask": mask, "bits": bits });
+        self.replayer.replay(OpKind::SetValues, args)?;
+        Ok(())
+    }
+
+    fn update_config(&self, config: LineRequest) -> Result<()> {
+        let mut args = request_args(&config);
+        args["handle"] = json!(self.id);
+        self.replayer.replay(OpKind::UpdateConfig, args)?;
+        Ok(())
+    }
+
+    fn read_events(&self, buf: &mut [LineEvent]) -> Result<usize> {
+        let kind = OpKind::ReadEvents;
+        let args = json!({ "handle": self.id, "len": buf.len() });
+        let value = self.replayer.replay(kind, args)?;
+        fill(buf, &value, event_from).ok_or_else(|| malformed(kind))
+    }
+}
@@ -0,0 +1,293 @@
+//! HTTP control API with a server-sent events stream, served with axum.
+//!
+//! - `GET /lines`: the values of all the lines, e.g. `{"6":0,"17":1}`.
+//! - `GET /lines/<offset>`: the value of a line, e.g. `{"offset":6,"value":0}`.
+//! - `PUT /lines/<offset>`: sets an output, the body is `0`, `1` or
+//!   `{"value":1}`, answered like `GET`.
+//! - `GET /events`: an SSE stream of the edges of the inputs, each an `edge`
+//!   event with data like `{"offset":6,"edge":"rising","timestamp_ns":1234}`.
+//!
+//! Errors are answered with a status code and `{"error":"..."}`.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::rest::RestServer;
+//! # async fn serve() -> gpio_cdev_async::Result<()> {
+//! RestServer::builder("/dev/gpiochip0")
+//!     .set_inputs([6])
+//!     .set_outputs([17])
+//!     .build()?
+//!     .serve("0.0.0.0:8080")
+//!     .await
+//! # }
+//! ```
+//!
+//! ```sh
+//! curl -X PUT -d 1 http://gateway:8080/lines/17
+//! curl -N http://gateway:8080/events
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "v1")]
+use std::sync::Mutex;
+
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use tokio::{net::ToSocketAddrs, sync::broadcast};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::{bridge::BridgeLines, event::LineEventType, line::LineHandle, Error, Result};
+
+/// How many edges a slow SSE client may lag behind before missing some.
+const EVENT_BACKLOG: usize = 256;
+
+/// Builds a [`RestServer`], see [`RestServer::builder`].
+#[derive(Debug, Clone)]
+pub struct RestServerBuilder {
+    chip: PathBuf,
+    consumer: String,
+    inputs: Vec<u32>,
+    outputs: Vec<u32>,
+}
+
+impl RestServerBuilder {
+    /// The consumer label of the requested lines, `rest` by default.
+    pub fn set_consumer(mut self, consumer: impl Into<String>) -> Self {
+        self.consumer = consumer.into();
+        self
+    }
+
+    /// The offsets of the lines read and whose edges are streamed.
+    pub fn set_inputs(mut self, offsets: impl IntoIterator<Item = u32>) -> Self {
+        self.inputs = offsets.into_iter().collect();
+        self
+    }
+
+    /// The offsets of the lines set by `PUT`, they start low.
+    pub fn set_outputs(mut self, offsets: impl IntoIterator<Item = u32>) -> Self {
+        self.outputs = offsets.into_iter().collect();
+        self
+    }
+
+    /// Requests the lines, nothing is served before [`RestServer::serve`].
+    pub fn build(self) -> Result<RestServer> {
+        let BridgeLines { inputs, outputs } =
+            BridgeLines::request(&self.chip, &self.consumer, &self.inputs, &self.outputs)?;
+
+        Ok(RestServer {
+            inputs: inputs.into_iter().map(Arc::new).collect(),
+            outputs,
+        })
+    }
+}
+
+/// An HTTP server owning its lines, see the [module documentation](self).
+#[derive(Debug)]
+pub struct RestServer {
+    inputs: Vec<Arc<LineHandle>>,
+    outputs: Option<LineHandle>,
+}
+
+/// The state shared by the handlers.
+struct Lines {
+    inputs: Vec<Arc<LineHandle>>,
+    outputs: Option<LineHandle>,
+    /// Serializes the read-modify-write of v1 outputs.
+    #[cfg(feature = "v1")]
+    lock: Mutex<()>,
+    events: broadcast::Sender<String>,
+}
+
+impl RestServer {
+    /// Serves the lines of the chip at `chip`.
+    pub fn builder(chip: impl AsRef<Path>) -> RestServerBuilder {
+        RestServerBuilder {
+            chip: chip.as_ref().to_path_buf(),
+            consumer: "rest".into(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Listens on `addr` and serves requests.
+    ///
+    /// Only returns if listening fails.
+    pub async fn serve(self, addr: impl ToSocketAddrs) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+
+        let (events, _) = broadcast::channel(EVENT_BACKLOG);
+        // the edges are read by blocking threads, one per input handle
+        for input in &self.inputs {
+            let (input, events) = (input.clone(), events.clone());
+            std::thread::spawn(move || stream_edges(&input, &events));
+        }
+
+        let lines = Arc::new(Lines {
+            inputs: self.inputs,
+            outputs: self.outputs,
+            #[cfg(feature = "v1")]
+            lock: Mutex::new(()),
+            events,
+        });
+        let app = Router::new()
+            .route("/lines", get(get_lines))
+            .route("/lines/{offset}", get(get_line).put(put_line))
+            .route("/events", get(get_events))
+            .with_state(lines);
+        axum::serve(listener, app).await?;
+        Ok(())
+    }
+}
+
+fn stream_edges(input: &LineHandle, events: &broadcast::Sender<String>) {
+    for event in input.events() {
+        let Ok(event) = event else { return };
+
+        #[cfg(feature = "v1")]
+        let offset = input.offsets()[0];
+        #[cfg(feature = "v2")]
+        let offset = event.offset();
+        let edge = match event.event_type() {
+            Ok(LineEventType::RisingEdge) => "rising",
+            Ok(LineEventType::FallingEdge) => "falling",
+            Err(_) => return,
+        };
+        let data = json!({
+            "offset": offset,
+            "edge": edge,
+            "timestamp_ns": event.timestamp_ns(),
+        });
+        // fails only while no client is subscribed
+        let _ = events.send(data.to_string());
+    }
+}
+
+/// A failed request, answered with `{"error":"..."}`.
+struct ApiError(StatusCode, String);
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        let status = match e.raw_os_error() {
+            Some(libc::EACCES | libc::EPERM) => StatusCode::FORBIDDEN,
+            Some(libc::EBUSY) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult = std::result::Result<Json<Value>, ApiError>;
+
+/// Runs the blocking `f`, e.g. reading the lines, on the blocking threads of tokio.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> std::result::Result<T, ApiError> {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => Ok(res?),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+fn values(lines: &Lines) -> Result<BTreeMap<u32, u8>> {
+    let mut values = BTreeMap::new();
+    for line in lines
+        .inputs
+        .iter()
+        .map(|line| &**line)
+        .chain(&lines.outputs)
+    {
+        let line_values = line.get_values()?;
+        values.extend(
+            line_values
+                .values_iter()
+                .map(|item| (item.offset, item.value)),
+        );
+    }
+    Ok(values)
+}
+
+async fn get_lines(State(lines): State<Arc<Lines>>) -> ApiResult {
+    Ok(Json(json!(blocking(move || values(&lines)).await?)))
+}
+
+async fn get_line(State(lines): State<Arc<Lines>>, UrlPath(offset): UrlPath<u32>) -> ApiResult {
+    let values = blocking(move || values(&lines)).await?;
+    let Some(value) = values.get(&offset).copied() else {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("{offset} is not served"),
+        ));
+    };
+    Ok(Json(json!({ "offset": offset, "value": value })))
+}
+
+async fn put_line(
+    State(lines): State<Arc<Lines>>,
+    UrlPath(offset): UrlPath<u32>,
+    body: String,
+) -> ApiResult {
+    let value = match serde_json::from_str::<Value>(&body) {
+        Ok(Value::Object(object)) => object.get("value").and_then(Value::as_u64),
+        Ok(value) => value.as_u64(),
+        Err(_) => None,
+    };
+    let Some(value) = value else {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("invalid value {body:?}"),
+        ));
+    };
+    if !lines
+        .outputs
+        .as_ref()
+        .is_some_and(|outputs| outputs.offsets().contains(&offset))
+    {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("{offset} is not an output"),
+        ));
+    }
+
+    let value = u8::from(value != 0);
+    blocking(move || {
+        #[cfg(feature = "v1")]
+        let _lock = lines.lock.lock().unwrap();
+        lines.outputs.as_ref().unwrap().set_value(offset, value)
+    })
+    .await?;
+    Ok(Json(json!({ "offset": offset, "value": value })))
+}
+
+async fn get_events(
+    State(lines): State<Arc<Lines>>,
+) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>>> {
+    // lagging clients miss the overwritten edges
+    let edges = BroadcastStream::new(lines.events.subscribe())
+        .filter_map(|data| data.ok())
+        .map(|data| Ok(Event::default().event("edge").data(data)));
+    Sse::new(edges).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,258 @@
+//! A bounded ring of events between a reader and slower consumers.
+//!
+//! The kernel buffers only a few events per line and drops the rest when
+//! userspace falls behind. [`EventRing::spawn_reader`] drains a
+//! [`LineHandle`] on its own thread into a ring of a fixed capacity, so
+//! bursts are absorbed while the memory stays bounded; what happens when
+//! the ring is full is up to the [`Backpressure`] policy, and the
+//! [`RingStats`] count what was dropped.
+//!
+//! # Examples
+//! ```rust
+//! use gpio_cdev_async::ring::{Backpressure, EventRing};
+//!
+//! let ring = EventRing::new(2, Backpressure::DropOldest);
+//! for i in 0..5 {
+//!     ring.push(i);
+//! }
+//! assert_eq!(ring.try_pop(), Some(3));
+//! assert_eq!(ring.try_pop(), Some(4));
+//! assert_eq!(ring.stats().dropped_oldest, 3);
+//! ```
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{event::LineEvent, line::LineHandle, Result};
+
+/// The number of events [`EventRing::spawn_reader`] reads at once.
+const READ_BATCH: usize = 16;
+
+/// What [`EventRing::push`] does when the ring is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Drops the oldest queued event to make room, consumers see the latest.
+    DropOldest,
+    /// Drops the pushed event, consumers see the first ones.
+    DropNewest,
+    /// Waits for a consumer to make room, which stalls the reader and leaves
+    /// the dropping to the kernel.
+    Block,
+}
+
+/// Counters of an [`EventRing`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RingStats {
+    /// Events pushed, including the dropped ones.
+    pub pushed: u64,
+    /// Queued events dropped by [`Backpressure::DropOldest`].
+    pub dropped_oldest: u64,
+    /// Pushed events dropped by [`Backpressure::DropNewest`].
+    pub dropped_newest: u64,
+    /// The most events queued at once.
+    pub high_water: usize,
+}
+
+impl RingStats {
+    /// All the events dropped so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped_oldest + self.dropped_newest
+    }
+}
+
+/// A bounded multi-producer multi-consumer queue with a [`Backpressure`] policy.
+#[derive(Debug)]
+pub struct EventRing<T> {
+    capacity: usize,
+    policy: Backpressure,
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    items: VecDeque<T>,
+    stats: RingStats,
+    /// Set by [`EventRing::close`], pushes are refused from then on.
+    closed: bool,
+}
+
+impl<T> EventRing<T> {
+    /// # Panics
+    /// If `capacity` is `0`.
+    pub fn new(capacity: usize, policy: Backpressure) -> Self {
+        assert!(capacity > 0, "the capacity of an event ring must not be 0");
+        Self {
+            capacity,
+            policy,
+            state: Mutex::new(State {
+                items: VecDeque::with_capacity(capacity),
+                stats: RingStats::default(),
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn policy(&self) -> Backpressure {
+        self.policy
+    }
+
+    /// Queues `item` according to the policy of the ring.
+    ///
+    /// Returns `false` if the ring is closed or `item` was dropped by
+    /// [`Backpressure::DropNewest`].
+    pub fn push(&self, item: T) -> bool {
+        let mut state = self.lock();
+        if state.closed {
+            return false;
+        }
+        state.stats.pushed += 1;
+        if state.items.len() >= self.capacity {
+            match self.policy {
+                Backpressure::DropOldest => {
+                    state.items.pop_front();
+                    state.stats.dropped_oldest += 1;
+                }
+                Backpressure::DropNewest => {
+                    state.stats.dropped_newest += 1;
+                    return false;
+                }
+                Backpressure::Block => {
+                    state = self
+                        .not_full
+                        .wait_while(state, |state| {
+                            !state.closed && state.items.len() >= self.capacity
+                        })
+                        .unwrap();
+                    if state.closed {
+                        return false;
+                    }
+                }
+            }
+        }
+        state.items.push_back(item);
+        state.stats.high_water = state.stats.high_water.max(state.items.len());
+        self.not_empty.notify_one();
+        true
+    }
+
+    /// Takes the oldest event, blocking until one is available.
+    ///
+    /// Returns `None` once the ring is closed and empty.
+    pub fn pop(&self) -> Option<T> {
+        let state = self.lock();
+        let state = self
+            .not_empty
+            .wait_while(state, |state| !state.closed && state.items.is_empty())
+            .unwrap();
+        self.take(state)
+    }
+
+    /// Like [`EventRing::pop`], but gives up after `timeout`.
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        while !state.closed && state.items.is_empty() {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return None;
+            }
+            state = self.not_empty.wait_timeout(state, left).unwrap().0;
+        }
+        self.take(state)
+    }
+
+    /// Takes the oldest event if there is one, without blocking.
+    pub fn try_pop(&self) -> Option<T> {
+        let state = self.lock();
+        self.take(state)
+    }
+
+    /// Refuses further pushes and wakes everyone up, the queued events can
+    /// still be popped.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// The number of queued events.
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> RingStats {
+        self.lock().stats
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap()
+    }
+
+    fn take(&self, mut state: MutexGuard<'_, State<T>>) -> Option<T> {
+        let item = state.items.pop_front()?;
+        self.not_full.notify_one();
+        Some(item)
+    }
+}
+
+impl EventRing<LineEvent> {
+    /// Moves `handle` to a thread that pushes its edge events into a new ring.
+    ///
+    /// The thread closes the ring and ends when the events of the handle end,
+    /// a read fails, or the ring is closed by a consumer and the next event
+    /// arrives. Its result is that of the last read.
+    ///
+    /// # Panics
+    /// If `capacity` is `0`.
+    pub fn spawn_reader(
+        handle: LineHandle,
+        capacity: usize,
+        policy: Backpressure,
+    ) -> (Arc<Self>, JoinHandle<Result<()>>) {
+        let ring = Arc::new(Self::new(capacity, policy));
+        let reader = {
+            let ring = Arc::clone(&ring);
+            std::thread::spawn(move || {
+                let res = drain(&handle, &ring);
+                ring.close();
+                res
+            })
+        };
+        (ring, reader)
+    }
+}
+
+fn drain(handle: &LineHandle, ring: &EventRing<LineEvent>) -> Result<()> {
+    let mut reader = handle.event_reader(READ_BATCH);
+    let mut events = Vec::with_capacity(READ_BATCH);
+    loop {
+        if reader.read(&mut events)? == 0 {
+            return Ok(());
+        }
+        for event in events.drain(..) {
+            if !ring.push(event) && ring.is_closed() {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Reading inputs at a fixed rate.
+//!
+//! Edge detection does not suit every input: analog comparators chatter
+//! around their threshold, and bouncy contacts raise bursts of events. A
+//! [`Sampler`] reads the lines of a handle at a fixed rate instead, from its
+//! own thread, and passes timestamped [`SampleFrame`]s to a callback or a
+//! channel.
+//!
+//! Samples are scheduled from the start, sample `n` is due `n` periods
+//! after it, so the rate does not drift with the time spent reading. Samples
+//! that are more than a period late are skipped and counted.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use std::time::Duration;
+//! # use gpio_cdev_async::{chip::Chip, line::LineRequest, sampler::Sampler};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! # let chip = Chip::new("/dev/gpiochip0")?;
+//! # let request = LineRequest::builder().set_offsets([5u32, 6]).build()?;
+//! let comparators = chip.get_line(request)?;
+//! let (sampler, frames) = Sampler::channel(comparators, Duration::from_millis(10));
+//! for frame in frames.iter().take(100) {
+//!     println!("{:?} {:?}", frame.timestamp, frame.values);
+//! }
+//! sampler.stop()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    line::{LineHandle, LineValue},
+    worker::Worker,
+    Result,
+};
+
+/// The values of the lines at one sampling instant, see [`Sampler`].
+#[derive(Debug)]
+pub struct SampleFrame {
+    /// The number of the sample since the start, skipped samples included.
+    pub index: u64,
+    /// When the values were read.
+    pub timestamp: Instant,
+    /// How long after its due time the sample was read.
+    pub late: Duration,
+    pub values: LineValue,
+}
+
+/// Reads the lines of a handle at a fixed rate, see the
+/// [module documentation](self).
+///
+/// The thread is stopped when the sampler is dropped.
+#[derive(Debug)]
+pub struct Sampler {
+    shared: Arc<Shared>,
+    worker: Worker,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    stop: Mutex<bool>,
+    wakeup: Condvar,
+    skipped: AtomicU64,
+}
+
+impl Sampler {
+    /// Moves `handle` to a thread reading it every `period`, starting now,
+    /// and calling `callback` with every frame.
+    pub fn spawn(
+        handle: LineHandle,
+        period: Duration,
+        mut callback: impl FnMut(SampleFrame) + Send + 'static,
+    ) -> Self {
+        Self::spawn_until(handle, period, move |frame| {
+            callback(frame);
+            true
+        })
+    }
+
+    /// Like [`Sampler::spawn`], but sends the frames to the returned
+    /// receiver. The thread stops when the receiver is dropped.
+    pub fn channel(handle: LineHandle, period: Duration) -> (Self, Receiver<SampleFrame>) {
+        let (tx, rx) = mpsc::channel();
+        let sampler = Self::spawn_until(handle, period, move |frame| tx.send(frame).is_ok());
+        (sampler, rx)
+    }
+
+    fn spawn_until(
+        handle: LineHandle,
+        period: Duration,
+        on_frame: impl FnMut(SampleFrame) -> bool + Send + 'static,
+    ) -> Self {
+        let shared = Arc::new(Shared::default());
+        let worker = Worker::spawn(
+            {
+                let shared = shared.clone();
+                move || sample(&handle, period, &shared, on_frame)
+            },
+            {
+                let shared = shared.clone();
+                move || {
+                    *shared.stop.lock().unwrap_or_else(|e| e.into_inner()) = true;
+                    shared.wakeup.notify_one();
+                }
+            },
+        );
+        Self { shared, worker }
+    }
+
+    /// The number of samples skipped so far because the thread was late
+    /// by more than a period.
+    pub fn skipped(&self) -> u64 {
+        self.shared.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Stops the thread and returns its result, e.g. a failed read.
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+fn sample(
+    handle: &LineHandle,
+    period: Duration,
+    shared: &Shared,
+    mut on_frame: impl FnMut(SampleFrame) -> bool,
+) -> Result<()> {
+    let start = Instant::now();
+    let mut index: u64 = 0;
+    loop {
+        let due = start + nanos(period.as_nanos() * u128::from(index));
+        let mut stop = shared.stop.lock().unwrap_or_else(|e| e.into_inner());
+        while !*stop {
+            let now = Instant::now();
+            if now >= due {
+                break;
+            }
+            stop = shared
+                .wakeup
+                .wait_timeout(stop, due - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        if *stop {
+            return Ok(());
+        }
+        drop(stop);
+
+        let values = handle.get_values()?;
+        let timestamp = Instant::now();
+        let late = timestamp.saturating_duration_since(due);
+        if !on_frame(SampleFrame {
+            index,
+            timestamp,
+            late,
+            values,
+        }) {
+            return Ok(());
+        }
+
+        // the next sample due in the future, the ones in between are skipped
+        let elapsed = Instant::now().duration_since(start);
+        let next = (elapsed.as_nanos() / period.as_nanos().max(1)) as u64 + 1;
+        let next = next.max(index + 1);
+        shared.skipped.fetch_add(next - index - 1, Ordering::Relaxed);
+        index = next;
+    }
+}
+
+fn nanos(nanos: u128) -> Duration {
+    Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX))
+}
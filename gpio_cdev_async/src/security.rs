@@ -0,0 +1,131 @@
+//! The syscalls made by this crate, for building seccomp filters.
+//!
+//! [`required_syscalls`] lists the syscalls the enabled features make
+//! directly, with one entry per ioctl request number, so a hardened daemon
+//! can allow `ioctl` only for the GPIO requests it needs.
+//!
+//! The list does not cover the Rust runtime and the allocator (`mmap`,
+//! `brk`, `futex`, `clone3`, ...), nor the network servers of `grpc`,
+//! `mqtt`, `rest` and `websocket`, whose syscalls depend on their
+//! runtime. Applications polling handles add their own `poll`/`ppoll` or
+//! `epoll_*` calls.
+//!
+//! # Examples
+//! ```rust
+//! use gpio_cdev_async::security;
+//!
+//! for syscall in security::required_syscalls() {
+//!     match syscall.ioctl_request {
+//!         Some(request) => println!("{} ({}) request {request:#x}", syscall.name, syscall.number),
+//!         None => println!("{} ({})", syscall.name, syscall.number),
+//!     }
+//! }
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::mem::size_of;
+
+use crate::ffi::{self, common::GPIO_IOC_MAGIC};
+
+/// A syscall made by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Syscall {
+    /// The name of the syscall, e.g. `"ioctl"`.
+    pub name: &'static str,
+    /// The number of the syscall on the target architecture.
+    pub number: libc::c_long,
+    /// The request number, the second argument, of an `ioctl`.
+    pub ioctl_request: Option<u64>,
+}
+
+impl Syscall {
+    const fn new(name: &'static str, number: libc::c_long) -> Self {
+        Self {
+            name,
+            number,
+            ioctl_request: None,
+        }
+    }
+
+    const fn ioctl(request: u64) -> Self {
+        Self {
+            name: "ioctl",
+            number: libc::SYS_ioctl,
+            ioctl_request: Some(request),
+        }
+    }
+}
+
+/// Returns the syscalls made by the enabled features of this crate, see the
+/// [module documentation](self).
+pub fn required_syscalls() -> Vec<Syscall> {
+    let mut syscalls = vec![
+        // opening chips, with `O_CLOEXEC`
+        Syscall::new("openat", libc::SYS_openat),
+        Syscall::new("close", libc::SYS_close),
+        // event reads
+        Syscall::new("read", libc::SYS_read),
+        // `FD_CLOEXEC` of request fds and `Chip::try_clone`
+        Syscall::new("fcntl", libc::SYS_fcntl),
+    ];
+    syscalls.extend(gpio_ioctls().into_iter().map(Syscall::ioctl));
+
+    #[cfg(feature = "expander")]
+    syscalls.extend([
+        Syscall::new("write", libc::SYS_write),
+        // `I2C_SLAVE`
+        Syscall::ioctl(0x0703),
+    ]);
+    #[cfg(feature = "fdpass")]
+    syscalls.extend([
+        Syscall::new("sendmsg", libc::SYS_sendmsg),
+        Syscall::new("recvmsg", libc::SYS_recvmsg),
+    ]);
+    #[cfg(feature = "realtime")]
+    syscalls.extend([
+        Syscall::new("mlockall", libc::SYS_mlockall),
+        Syscall::new("sched_setscheduler", libc::SYS_sched_setscheduler),
+        Syscall::new("sched_setaffinity", libc::SYS_sched_setaffinity),
+    ]);
+    #[cfg(feature = "spsc")]
+    syscalls.push(Syscall::new("futex", libc::SYS_futex));
+
+    syscalls
+}
+
+/// The request numbers of the GPIO ioctls of the enabled uAPI version.
+fn gpio_ioctls() -> Vec<u64> {
+    use nix::{request_code_read as read, request_code_readwrite as readwrite};
+
+    let mut requests = vec![
+        read!(GPIO_IOC_MAGIC, 0x01, size_of::<ffi::common::GpioChipInfo>()) as u64,
+        readwrite!(GPIO_IOC_MAGIC, 0x0C, size_of::<u32>()) as u64,
+    ];
+    #[cfg(feature = "v1")]
+    {
+        use ffi::v1::*;
+        requests.extend([
+            readwrite!(GPIO_IOC_MAGIC, 0x02, size_of::<GpioLineInfo>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x03, size_of::<GpioHandleRequest>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x04, size_of::<GpioEventRequest>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x08, size_of::<GpioHandleData>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x09, size_of::<GpioHandleData>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x0A, size_of::<GpioHandleConfig>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x0B, size_of::<GpioLineInfo>()) as u64,
+        ]);
+    }
+    #[cfg(feature = "v2")]
+    {
+        use ffi::v2::*;
+        requests.extend([
+            readwrite!(GPIO_IOC_MAGIC, 0x05, size_of::<GpioV2LineInfo>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x06, size_of::<GpioV2LineInfo>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x07, size_of::<GpioV2LineRequest>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x0D, size_of::<GpioV2LineConfig>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x0E, size_of::<GpioV2LineValues>()) as u64,
+            readwrite!(GPIO_IOC_MAGIC, 0x0F, size_of::<GpioV2LineValues>()) as u64,
+        ]);
+    }
+    requests
+}
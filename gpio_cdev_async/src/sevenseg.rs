@@ -0,0 +1,309 @@
+//! Driving multiplexed seven-segment displays wired to lines.
+//!
+//! A display of several digits shares its segment lines between the
+//! digits, and has one select line per digit. A [`SevenSegment`] lights the
+//! digits one after the other from its own thread, fast enough for the eye
+//! to see them all, and dims the display by leaving each digit dark for part
+//! of its slot.
+//!
+//! The driver writes logical values: a lit segment and a selected digit are
+//! `1`. Request the lines that are lit by a low level, e.g. the digit
+//! selects of a common cathode display driven through PNP transistors, as
+//! active-low.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{chip::Chip, line::LineRequest, sevenseg::SevenSegment};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! # let chip = Chip::new("/dev/gpiochip0")?;
+//! // segments a to g and the decimal point, then the digit selects
+//! let segments = [2, 3, 4, 5, 6, 7, 8, 9];
+//! let digits = [10, 11, 12, 13];
+//! # let offsets = segments.iter().chain(&digits).copied();
+//! # let request = LineRequest::builder().set_offsets(offsets).build()?;
+//! let display = SevenSegment::new(chip.get_line(request)?, &segments, &digits)?;
+//! display.set_text("12.34").expect("all the characters have a form");
+//! display.set_brightness(0.5);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - v1 handles write all their lines, the handle is best left to the display.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    line::{index_of_offset, LineHandle},
+    worker::{self, Worker},
+    Error, Result,
+};
+
+/// The segment bits of a digit, `a` is bit 0 to `g` bit 6, the decimal
+/// point bit 7.
+pub type Segments = u8;
+
+/// The decimal point bit of [`Segments`].
+pub const DECIMAL_POINT: Segments = 1 << 7;
+
+/// The segments showing `c`, `None` if it has no seven-segment form.
+///
+/// Digits, space, `-`, `_`, `=` and most letters are supported, letters in
+/// the case that can be told apart, e.g. `b` and `d` in lower case.
+///
+/// # Examples
+/// ```rust
+/// # use gpio_cdev_async::sevenseg::encode;
+/// assert_eq!(encode('8'), Some(0x7f));
+/// assert_eq!(encode('b'), encode('B'));
+/// assert_eq!(encode('%'), None);
+/// ```
+pub fn encode(c: char) -> Option<Segments> {
+    let segments = match c {
+        '0' | 'O' | 'D' => 0x3f,
+        '1' | 'I' => 0x06,
+        '2' | 'Z' | 'z' => 0x5b,
+        '3' => 0x4f,
+        '4' => 0x66,
+        '5' | 'S' | 's' => 0x6d,
+        '6' => 0x7d,
+        '7' => 0x07,
+        '8' => 0x7f,
+        'B' | 'b' => 0x7c,
+        '9' | 'g' => 0x6f,
+        'A' | 'a' => 0x77,
+        'C' => 0x39,
+        'c' => 0x58,
+        'd' => 0x5e,
+        'E' | 'e' => 0x79,
+        'F' | 'f' => 0x71,
+        'G' => 0x3d,
+        'H' => 0x76,
+        'h' => 0x74,
+        'i' => 0x04,
+        'J' | 'j' => 0x1e,
+        'L' | 'l' => 0x38,
+        'N' | 'n' => 0x54,
+        'o' => 0x5c,
+        'P' | 'p' => 0x73,
+        'Q' | 'q' => 0x67,
+        'R' | 'r' => 0x50,
+        'T' | 't' => 0x78,
+        'U' => 0x3e,
+        'u' | 'v' | 'V' => 0x1c,
+        'Y' | 'y' => 0x6e,
+        ' ' => 0x00,
+        '-' => 0x40,
+        '_' => 0x08,
+        '=' => 0x48,
+        _ => return None,
+    };
+    Some(segments)
+}
+
+/// Drives a multiplexed seven-segment display, see the
+/// [module documentation](self).
+///
+/// The display is blanked and the thread stopped when it is dropped.
+#[derive(Debug)]
+pub struct SevenSegment {
+    digits: usize,
+    shared: Arc<Shared>,
+    worker: Worker,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    stop: AtomicBool,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    frame: Vec<Segments>,
+    brightness: f32,
+    /// How often every digit is lit.
+    refresh: Duration,
+}
+
+/// The indices of the display lines in the handle.
+struct Lines {
+    segments: Vec<usize>,
+    digits: Vec<usize>,
+}
+
+impl SevenSegment {
+    /// The default refresh rate of the whole display, in Hz.
+    pub const DEFAULT_REFRESH_HZ: u32 = 100;
+
+    /// Moves `handle` to a thread refreshing the display, blank at first.
+    ///
+    /// `segments` are the offsets of the segments `a` to `g`, optionally
+    /// followed by the decimal point, `digits` the offsets of the digit
+    /// selects, leftmost first.
+    pub fn new(handle: LineHandle, segments: &[u32], digits: &[u32]) -> Result<Self> {
+        let invalid =
+            |msg: &str| -> Error { io::Error::new(io::ErrorKind::InvalidInput, msg).into() };
+        if !(7..=8).contains(&segments.len()) {
+            return Err(invalid("a display has 7 segments and a decimal point"));
+        }
+        if digits.is_empty() {
+            return Err(invalid("a display has at least one digit"));
+        }
+        let index = |&offset: &u32| {
+            index_of_offset(handle.offsets(), offset)
+                .ok_or_else(|| invalid("the handle does not hold a display line"))
+        };
+        let lines = Lines {
+            segments: segments.iter().map(index).collect::<Result<_>>()?,
+            digits: digits.iter().map(index).collect::<Result<_>>()?,
+        };
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                frame: vec![0; digits.len()],
+                brightness: 1.0,
+                refresh: Duration::from_secs(1) / Self::DEFAULT_REFRESH_HZ,
+            }),
+            stop: AtomicBool::new(false),
+        });
+        let worker = Worker::spawn(
+            {
+                let shared = shared.clone();
+                move || refresh(&handle, &lines, &shared)
+            },
+            {
+                let shared = shared.clone();
+                move || shared.stop.store(true, Ordering::Relaxed)
+            },
+        );
+        Ok(Self {
+            digits: digits.len(),
+            shared,
+            worker,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The number of digits of the display.
+    pub fn digits(&self) -> usize {
+        self.digits
+    }
+
+    /// Shows the segments of every digit, leftmost first, see [`Segments`].
+    ///
+    /// Missing digits are blank, extra ones are ignored.
+    pub fn set_segments(&self, frame: &[Segments]) {
+        let mut state = self.lock();
+        state.frame.fill(0);
+        for (digit, &segments) in state.frame.iter_mut().zip(frame) {
+            *digit = segments;
+        }
+    }
+
+    /// Shows `text`, right-aligned, see [`encode`]. A `.` lights the decimal
+    /// point of the character before it.
+    ///
+    /// Fails with the first character that has no seven-segment form,
+    /// leaving the display as it was.
+    pub fn set_text(&self, text: &str) -> std::result::Result<(), char> {
+        let mut frame: Vec<Segments> = Vec::new();
+        for c in text.chars() {
+            match (c, frame.last_mut()) {
+                ('.', Some(last)) if *last & DECIMAL_POINT == 0 => *last |= DECIMAL_POINT,
+                ('.', _) => frame.push(DECIMAL_POINT),
+                _ => frame.push(encode(c).ok_or(c)?),
+            }
+        }
+        let blank = self.digits.saturating_sub(frame.len());
+        let skip = frame.len().saturating_sub(self.digits);
+        let frame: Vec<_> = std::iter::repeat_n(0, blank)
+            .chain(frame.into_iter().skip(skip))
+            .collect();
+        self.set_segments(&frame);
+        Ok(())
+    }
+
+    /// Shows nothing.
+    pub fn clear(&self) {
+        self.set_segments(&[]);
+    }
+
+    /// Sets the share of its slot every digit is lit, from `0.0` to `1.0`.
+    ///
+    /// Values out of range are clamped, NaN is dark.
+    pub fn set_brightness(&self, brightness: f32) {
+        let brightness = if brightness.is_nan() { 0.0 } else { brightness };
+        self.lock().brightness = brightness.clamp(0.0, 1.0);
+    }
+
+    /// Sets how many times per second every digit is lit, flickering below
+    /// about 50 Hz.
+    pub fn set_refresh_rate(&self, hz: u32) {
+        self.lock().refresh = Duration::from_secs(1) / hz.max(1);
+    }
+
+    /// Blanks the display, stops the thread and returns its result, e.g. a
+    /// failed write.
+    pub fn close(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+fn refresh(handle: &LineHandle, lines: &Lines, shared: &Shared) -> Result<()> {
+    let used = lines
+        .segments
+        .iter()
+        .chain(&lines.digits)
+        .fold(0u64, |mask, index| mask | 1 << index);
+    let write_mask = worker::write_mask(handle, used);
+    let write = |bits: u64| handle.backend.set_values(write_mask, bits & used);
+
+    let mut deadline = Instant::now();
+    let sleep_until = |deadline: Instant| {
+        if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+    };
+    while !shared.stop.load(Ordering::Relaxed) {
+        let state = shared.state.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        // after a stall, start over instead of catching up
+        let now = Instant::now();
+        if now > deadline + state.refresh {
+            deadline = now;
+        }
+        let slot = state.refresh / state.frame.len() as u32;
+        let lit = slot.mul_f32(state.brightness);
+        for (&segments, &select) in state.frame.iter().zip(&lines.digits) {
+            let mut bits = 1 << select;
+            for (bit, &index) in lines.segments.iter().enumerate() {
+                if segments & 1 << bit != 0 {
+                    bits |= 1 << index;
+                }
+            }
+            if !lit.is_zero() {
+                write(bits)?;
+                sleep_until(deadline + lit);
+            }
+            if lit < slot {
+                write(0)?;
+            }
+            // from the previous deadline, so the refresh rate does not drift
+            deadline += slot;
+            sleep_until(deadline);
+        }
+    }
+    write(0)
+}
@@ -0,0 +1,400 @@
+//! Virtual chips for tests, created through the kernel's gpio-sim configfs
+//! interface.
+//!
+//! A [`TestChip`] is a gpio-sim device with one chip per [`Bank`], the chips
+//! are removed when it is dropped. The simulated lines are driven from the
+//! "outside" with [`TestChip::set_pull`] and their outputs read back with
+//! [`TestChip::get_value`].
+//!
+//! Kernels without gpio-sim fall back to the legacy gpio-mockup module,
+//! loaded with one chip per bank and unloaded on drop. gpio-mockup names the
+//! chips and lines itself and has no hogs, so banks configuring those fail
+//! with [`io::ErrorKind::Unsupported`].
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{chip::Chip, sim::{Bank, Hog, Pull, TestChip}};
+//! let sim = TestChip::builder()
+//!     .add_bank(
+//!         Bank::new(8)
+//!             .set_label("test")
+//!             .set_line_name(0, "button")
+//!             .set_hog(7, "hogged", Hog::OutputHigh),
+//!     )
+//!     .build()
+//!     .unwrap();
+//!
+//! let chip = Chip::new(sim.chip_path(0)).unwrap();
+//! sim.set_pull(0, 0, Pull::Up).unwrap();
+//! ```
+//!
+//! # Notes
+//! - Needs root. gpio-sim needs configfs mounted at `/sys/kernel/config`,
+//!   gpio-mockup needs debugfs mounted at `/sys/kernel/debug`, `modprobe` and
+//!   no other user of the module.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{chip::Chip, Result};
+
+/// The configfs directory of gpio-sim.
+const CONFIGFS: &str = "/sys/kernel/config/gpio-sim";
+/// The debugfs directory of gpio-mockup, one directory per chip.
+const MOCKUP_DEBUGFS: &str = "/sys/kernel/debug/gpio-mockup";
+/// The most chips gpio-mockup creates.
+const MOCKUP_MAX_CHIPS: usize = 10;
+
+/// Makes the device names unique within the process.
+static NEXT_DEVICE: AtomicU32 = AtomicU32::new(0);
+
+/// The direction and value a hogged line is held at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hog {
+    Input,
+    OutputHigh,
+    OutputLow,
+}
+
+impl Hog {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::OutputHigh => "output-high",
+            Self::OutputLow => "output-low",
+        }
+    }
+}
+
+/// The pull simulated on an input line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pull {
+    Up,
+    Down,
+}
+
+/// A bank of a [`TestChip`], exposed as a chip of its own.
+#[derive(Debug, Clone)]
+pub struct Bank {
+    num_lines: u32,
+    label: Option<String>,
+    names: BTreeMap<u32, String>,
+    hogs: BTreeMap<u32, (String, Hog)>,
+}
+
+impl Bank {
+    /// A bank of `num_lines` unnamed lines.
+    pub fn new(num_lines: u32) -> Self {
+        Self {
+            num_lines,
+            label: None,
+            names: BTreeMap::new(),
+            hogs: BTreeMap::new(),
+        }
+    }
+
+    /// The label of the chip, gpio-sim derives one by default.
+    pub fn set_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// The name of the line at `offset`.
+    pub fn set_line_name(mut self, offset: u32, name: impl Into<String>) -> Self {
+        self.names.insert(offset, name.into());
+        self
+    }
+
+    /// Hogs the line at `offset` with the consumer `name`.
+    pub fn set_hog(mut self, offset: u32, name: impl Into<String>, hog: Hog) -> Self {
+        self.hogs.insert(offset, (name.into(), hog));
+        self
+    }
+}
+
+/// Builds a [`TestChip`], see [`TestChip::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct TestChipBuilder {
+    banks: Vec<Bank>,
+}
+
+impl TestChipBuilder {
+    /// Adds a bank, the banks are numbered in the order they are added.
+    pub fn add_bank(mut self, bank: Bank) -> Self {
+        self.banks.push(bank);
+        self
+    }
+
+    /// Creates and enables the device, a single bank of 8 lines if none was added.
+    ///
+    /// Uses gpio-sim, or gpio-mockup if gpio-sim is unavailable.
+    pub fn build(self) -> Result<TestChip> {
+        let mut banks = self.banks;
+        if banks.is_empty() {
+            banks.push(Bank::new(8));
+        }
+
+        if !Path::new(CONFIGFS).is_dir() {
+            // the directory only exists once the module is loaded
+            let _ = modprobe(&["gpio-sim"]);
+        }
+        if Path::new(CONFIGFS).is_dir() {
+            build_sim(&banks)
+        } else {
+            build_mockup(&banks)
+        }
+    }
+}
+
+fn build_sim(banks: &[Bank]) -> Result<TestChip> {
+    let name = format!(
+        "gpio-cdev-{}-{}",
+        std::process::id(),
+        NEXT_DEVICE.fetch_add(1, Ordering::Relaxed)
+    );
+    let mut sim = TestChip {
+        backend: Backend::Sim {
+            dir: Path::new(CONFIGFS).join(name),
+            banks: Vec::new(),
+            sysfs: PathBuf::new(),
+        },
+        chips: Vec::new(),
+    };
+    let Backend::Sim {
+        dir,
+        banks: dirs,
+        sysfs,
+    } = &mut sim.backend
+    else {
+        unreachable!()
+    };
+
+    // from here on, dropping `sim` removes what was created
+    fs::create_dir(&*dir)?;
+    for (index, bank) in banks.iter().enumerate() {
+        let bank_dir = dir.join(format!("gpio-bank{index}"));
+        fs::create_dir(&bank_dir)?;
+        dirs.push(BankDir {
+            dir: bank_dir.clone(),
+            lines: Vec::new(),
+            hogs: Vec::new(),
+        });
+        let created = dirs.last_mut().unwrap();
+
+        fs::write(bank_dir.join("num_lines"), bank.num_lines.to_string())?;
+        if let Some(label) = &bank.label {
+            fs::write(bank_dir.join("label"), label)?;
+        }
+        let offsets = bank.names.keys().chain(bank.hogs.keys());
+        for &offset in offsets.collect::<BTreeSet<_>>() {
+            let line = bank_dir.join(format!("line{offset}"));
+            fs::create_dir(&line)?;
+            created.lines.push(line.clone());
+
+            if let Some(name) = bank.names.get(&offset) {
+                fs::write(line.join("name"), name)?;
+            }
+            if let Some((name, hog)) = bank.hogs.get(&offset) {
+                let hog_dir = line.join("hog");
+                fs::create_dir(&hog_dir)?;
+                created.hogs.push(hog_dir.clone());
+                fs::write(hog_dir.join("name"), name)?;
+                fs::write(hog_dir.join("direction"), hog.as_str())?;
+            }
+        }
+    }
+    fs::write(dir.join("live"), "1")?;
+
+    let dev_name = read_trimmed(&dir.join("dev_name"))?;
+    *sysfs = Path::new("/sys/devices/platform").join(dev_name);
+    let chips = dirs
+        .iter()
+        .map(|bank| read_trimmed(&bank.dir.join("chip_name")))
+        .collect::<io::Result<_>>()?;
+    sim.chips = chips;
+    Ok(sim)
+}
+
+fn build_mockup(banks: &[Bank]) -> Result<TestChip> {
+    let unsupported = |message: &str| io::Error::new(io::ErrorKind::Unsupported, message);
+    if banks
+        .iter()
+        .any(|bank| bank.label.is_some() || !bank.names.is_empty() || !bank.hogs.is_empty())
+    {
+        return Err(unsupported("gpio-mockup has no labels, line names or hogs").into());
+    }
+    if banks.len() > MOCKUP_MAX_CHIPS {
+        return Err(unsupported("gpio-mockup has at most 10 chips").into());
+    }
+    if Path::new("/sys/module/gpio_mockup").exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "gpio-mockup is already loaded",
+        )
+        .into());
+    }
+
+    // `-1` lets the kernel pick the base of every chip
+    let ranges: Vec<_> = banks
+        .iter()
+        .map(|bank| format!("-1,{}", bank.num_lines))
+        .collect();
+    modprobe(&[
+        "gpio-mockup",
+        &format!("gpio_mockup_ranges={}", ranges.join(",")),
+    ])?;
+    let mut mockup = TestChip {
+        backend: Backend::Mockup,
+        chips: Vec::new(),
+    };
+
+    // the chips are labelled `gpio-mockup-A`, `gpio-mockup-B`, ... in bank order
+    let mut chips = vec![None; banks.len()];
+    for entry in fs::read_dir(MOCKUP_DEBUGFS)? {
+        let chip_name = entry?.file_name().to_string_lossy().into_owned();
+        let info = Chip::new(Path::new("/dev").join(&chip_name))?.get_chipinfo()?;
+        let bank = info
+            .label()
+            .strip_prefix("gpio-mockup-")
+            .and_then(|letter| letter.bytes().next())
+            .map(|letter| letter.wrapping_sub(b'A') as usize);
+        if let Some(chip) = bank.and_then(|bank| chips.get_mut(bank)) {
+            *chip = Some(chip_name);
+        }
+    }
+    mockup.chips = chips
+        .into_iter()
+        .collect::<Option<_>>()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "missing gpio-mockup chips"))?;
+    Ok(mockup)
+}
+
+/// The configfs entries of a bank, removed in reverse.
+#[derive(Debug)]
+struct BankDir {
+    dir: PathBuf,
+    lines: Vec<PathBuf>,
+    hogs: Vec<PathBuf>,
+}
+
+/// The kernel module simulating the chips.
+#[derive(Debug)]
+enum Backend {
+    Sim {
+        dir: PathBuf,
+        banks: Vec<BankDir>,
+        sysfs: PathBuf,
+    },
+    Mockup,
+}
+
+/// A live simulated device, see the [module documentation](self).
+#[derive(Debug)]
+pub struct TestChip {
+    backend: Backend,
+    /// The chip names of the banks, e.g. `gpiochip3`.
+    chips: Vec<String>,
+}
+
+impl TestChip {
+    /// A device with the banks added to the builder.
+    pub fn builder() -> TestChipBuilder {
+        TestChipBuilder::default()
+    }
+
+    /// Whether the chips are simulated by gpio-mockup rather than gpio-sim.
+    pub fn is_mockup(&self) -> bool {
+        matches!(self.backend, Backend::Mockup)
+    }
+
+    /// The number of banks, and so of chips.
+    pub fn num_banks(&self) -> usize {
+        self.chips.len()
+    }
+
+    /// The path of the chip of `bank`, e.g. `/dev/gpiochip3`.
+    ///
+    /// # Panics
+    /// If there is no such bank.
+    pub fn chip_path(&self, bank: usize) -> PathBuf {
+        Path::new("/dev").join(&self.chips[bank])
+    }
+
+    /// Simulates a pull on the line at `offset` of `bank`, an input then
+    /// reads it and edges are generated.
+    pub fn set_pull(&self, bank: usize, offset: u32, pull: Pull) -> Result<()> {
+        let pull = match (&self.backend, pull) {
+            (Backend::Sim { .. }, Pull::Up) => "pull-up",
+            (Backend::Sim { .. }, Pull::Down) => "pull-down",
+            (Backend::Mockup, Pull::Up) => "1",
+            (Backend::Mockup, Pull::Down) => "0",
+        };
+        fs::write(self.line_file(bank, offset, "pull"), pull)?;
+        Ok(())
+    }
+
+    /// The value of the line at `offset` of `bank`, as driven by its
+    /// consumer if it is an output.
+    pub fn get_value(&self, bank: usize, offset: u32) -> Result<u8> {
+        let value = read_trimmed(&self.line_file(bank, offset, "value"))?;
+        value
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, value).into())
+    }
+
+    /// The sysfs or debugfs file of a line, gpio-mockup has a single file per line.
+    fn line_file(&self, bank: usize, offset: u32, attr: &str) -> PathBuf {
+        match &self.backend {
+            Backend::Sim { sysfs, .. } => sysfs
+                .join(&self.chips[bank])
+                .join(format!("sim_gpio{offset}"))
+                .join(attr),
+            Backend::Mockup => Path::new(MOCKUP_DEBUGFS)
+                .join(&self.chips[bank])
+                .join(offset.to_string()),
+        }
+    }
+}
+
+impl Drop for TestChip {
+    fn drop(&mut self) {
+        match &self.backend {
+            // best effort, configfs refuses to remove a live device or a non-empty directory
+            Backend::Sim { dir, banks, .. } => {
+                let _ = fs::write(dir.join("live"), "0");
+                for bank in banks.iter().rev() {
+                    for dir in bank.hogs.iter().rev().chain(bank.lines.iter().rev()) {
+                        let _ = fs::remove_dir(dir);
+                    }
+                    let _ = fs::remove_dir(&bank.dir);
+                }
+                let _ = fs::remove_dir(dir);
+            }
+            Backend::Mockup => {
+                let _ = modprobe(&["-r", "gpio-mockup"]);
+            }
+        }
+    }
+}
+
+fn modprobe(args: &[&str]) -> io::Result<()> {
+    let status = Command::new("modprobe").args(args).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "modprobe {} failed: {status}",
+            args.join(" ")
+        )));
+    }
+    Ok(())
+}
+
+fn read_trimmed(path: &Path) -> io::Result<String> {
+    Ok(fs::read_to_string(path)?.trim().to_owned())
+}
@@ -0,0 +1,151 @@
+//! Saving and restoring the values driven on outputs.
+//!
+//! [`LineHandle::snapshot_outputs`] captures the values of the output lines
+//! of a handle, and [`LineHandle::restore`] drives them again, e.g. after a
+//! service reinitialised its handles. Handles registered with [`register`]
+//! can be captured and restored together with [`snapshot_all`] and
+//! [`restore_all`].
+//!
+//! Snapshots identify lines by chip path and offset, not by handle, so they
+//! can be restored to handles requested again. Paths naming the same chip,
+//! e.g. through a symlink, match.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use std::sync::Arc;
+//! # use gpio_cdev_async::{chip::Chip, line::LineRequest, snapshot};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! let chip = Arc::new(Chip::new("/dev/gpiochip0")?);
+//! # let request = || LineRequest::builder().set_offsets([17u32, 18]).build();
+//! let relays = Arc::new(chip.get_line(request()?)?);
+//! let registration = snapshot::register(relays.clone());
+//! let saved = snapshot::snapshot_all()?;
+//!
+//! // the service reinitialises
+//! drop((registration, relays));
+//! let relays = Arc::new(chip.get_line(request()?)?);
+//! let _registration = snapshot::register(relays.clone());
+//! snapshot::restore_all(&saved)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - Whether a line is an output is taken from the configuration it was
+//!   requested with.
+//! - Handles of an unknown chip, see [`LineHandle::chip_path`], are left out
+//!   of [`snapshot_all`] and [`restore_all`].
+//! - v1 handles write all their lines, lines missing from a snapshot keep
+//!   their current values.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{line::LineHandle, Result};
+
+/// The values driven on the output lines of a handle, see
+/// [`LineHandle::snapshot_outputs`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputSnapshot {
+    /// Pairs of offset and value, in the order of the handle's offsets.
+    pub values: Vec<(u32, u8)>,
+}
+
+/// The output values of all the registered handles, see [`snapshot_all`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessSnapshot {
+    /// The snapshot of every registered handle with the path of its chip.
+    pub handles: Vec<(PathBuf, OutputSnapshot)>,
+}
+
+struct Entry {
+    id: u64,
+    handle: Arc<LineHandle>,
+}
+
+struct Registry {
+    next_id: u64,
+    entries: Vec<Entry>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    next_id: 0,
+    entries: Vec::new(),
+});
+
+/// Registers `handle` for [`snapshot_all`] and [`restore_all`], until the
+/// returned [`Registration`] is dropped.
+pub fn register(handle: Arc<LineHandle>) -> Registration {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.entries.push(Entry { id, handle });
+    Registration { id }
+}
+
+/// Captures the output values of every registered handle.
+pub fn snapshot_all() -> Result<ProcessSnapshot> {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let handles = registry
+        .entries
+        .iter()
+        .filter_map(|entry| Some((entry.handle.chip_path()?, &entry.handle)))
+        .map(|(chip, handle)| Ok((chip.to_path_buf(), handle.snapshot_outputs()?)))
+        .collect::<Result<_>>()?;
+    Ok(ProcessSnapshot { handles })
+}
+
+/// Drives the values of `snapshot` on the registered handles holding its
+/// lines.
+///
+/// Every handle is restored even if one fails, the first error is returned.
+pub fn restore_all(snapshot: &ProcessSnapshot) -> Result<()> {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut result = Ok(());
+    for entry in &registry.entries {
+        let Some(chip) = entry.handle.chip_path() else {
+            continue;
+        };
+        let values = snapshot
+            .handles
+            .iter()
+            .filter(|(path, _)| same_chip(path, chip))
+            .flat_map(|(_, snapshot)| &snapshot.values)
+            .filter(|(offset, _)| entry.handle.offsets().contains(offset))
+            .copied()
+            .collect();
+        let restored = entry.handle.restore(&OutputSnapshot { values });
+        if result.is_ok() {
+            result = restored;
+        }
+    }
+    result
+}
+
+/// Whether the paths `a` and `b` name the same chip.
+fn same_chip(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (fs::canonicalize(a), fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// Keeps a handle registered with [`register`], unregisters it when dropped.
+#[derive(Debug)]
+#[must_use = "the handle is unregistered when the registration is dropped"]
+pub struct Registration {
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        registry.entries.retain(|entry| entry.id != self.id);
+    }
+}
@@ -0,0 +1,470 @@
+//! A lock-free single-producer single-consumer ring for handing events from
+//! a reader thread to one consumer.
+//!
+//! [`EventRing`](crate::ring::EventRing) takes a mutex on every push and
+//! pop, so a consumer preempted while holding it delays the reader. Here
+//! both ends only touch atomics, and a blocked end sleeps on a futex that
+//! the other end wakes without locking, which keeps the worst-case hand-off
+//! latency bounded on `PREEMPT_RT` kernels.
+//!
+//! There is no [`Backpressure::DropOldest`]: only the consumer may take
+//! events out of the ring.
+//!
+//! # Examples
+//! ```rust
+//! let (mut tx, mut rx) = gpio_cdev_async::spsc::channel(2);
+//! let producer = std::thread::spawn(move || {
+//!     for i in 0..100 {
+//!         tx.push_blocking(i).unwrap();
+//!     }
+//! });
+//! let received: Vec<_> = std::iter::from_fn(|| rx.pop()).collect();
+//! producer.join().unwrap();
+//! assert_eq!(received, (0..100).collect::<Vec<_>>());
+//! ```
+
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use crate::{
+    event::LineEvent,
+    line::LineHandle,
+    ring::{Backpressure, RingStats},
+    Result,
+};
+
+/// The number of events [`spawn_reader`] reads at once.
+const READ_BATCH: usize = 16;
+
+/// Creates a ring of `capacity` events.
+///
+/// # Panics
+/// If `capacity` is `0`.
+pub fn channel<T: Send>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "the capacity of an spsc ring must not be 0");
+    let shared = Arc::new(Shared {
+        slots: (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        pushed: Waiter::default(),
+        popped: Waiter::default(),
+        producer_gone: AtomicBool::new(false),
+        consumer_gone: AtomicBool::new(false),
+        pushes: AtomicU64::new(0),
+        dropped: AtomicU64::new(0),
+        high_water: AtomicUsize::new(0),
+    });
+    (
+        Producer {
+            shared: Arc::clone(&shared),
+        },
+        Consumer { shared },
+    )
+}
+
+/// Moves `handle` to a thread that pushes its edge events into a new ring,
+/// like [`EventRing::spawn_reader`](crate::ring::EventRing::spawn_reader).
+///
+/// The thread ends when the events of the handle end, a read fails, or the
+/// consumer is dropped and the next event arrives.
+///
+/// # Panics
+/// If `capacity` is `0` or `policy` is [`Backpressure::DropOldest`].
+pub fn spawn_reader(
+    handle: LineHandle,
+    capacity: usize,
+    policy: Backpressure,
+) -> (Consumer<LineEvent>, JoinHandle<Result<()>>) {
+    assert_ne!(
+        policy,
+        Backpressure::DropOldest,
+        "only the consumer of an spsc ring can drop its oldest events"
+    );
+    let (mut tx, rx) = channel(capacity);
+    let reader = std::thread::spawn(move || {
+        let mut reader = handle.event_reader(READ_BATCH);
+        let mut events = Vec::with_capacity(READ_BATCH);
+        loop {
+            if reader.read(&mut events)? == 0 {
+                return Ok(());
+            }
+            for event in events.drain(..) {
+                let pushed = match policy {
+                    Backpressure::Block => tx.push_blocking(event),
+                    _ => tx.push(event),
+                };
+                if pushed.is_err() && tx.is_disconnected() {
+                    return Ok(());
+                }
+            }
+        }
+    });
+    (rx, reader)
+}
+
+/// The sending end of a [`channel`].
+#[derive(Debug)]
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Producer<T> {
+    /// Queues `item` if there is room, otherwise returns it back and counts
+    /// it as dropped, as [`Backpressure::DropNewest`] does.
+    ///
+    /// Also returns `item` back if the consumer is gone.
+    pub fn push(&mut self, item: T) -> std::result::Result<(), T> {
+        let shared = &*self.shared;
+        if shared.consumer_gone.load(Ordering::Acquire) {
+            return Err(item);
+        }
+        shared.pushes.fetch_add(1, Ordering::Relaxed);
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let len = tail.wrapping_sub(shared.head.load(Ordering::Acquire));
+        if len == shared.slots.len() {
+            shared.dropped.fetch_add(1, Ordering::Relaxed);
+            return Err(item);
+        }
+        // SAFETY: the slot is free as `len < capacity`, and only the
+        // producer writes slots
+        unsafe { (*shared.slot(tail)).write(item) };
+        shared.tail.store(tail.wrapping_add(1), Ordering::Release);
+        shared.high_water.fetch_max(len + 1, Ordering::Relaxed);
+        shared.pushed.notify();
+        Ok(())
+    }
+
+    /// Queues `item`, waiting for the consumer to make room.
+    ///
+    /// Returns `item` back if the consumer is gone.
+    pub fn push_blocking(&mut self, item: T) -> std::result::Result<(), T> {
+        let shared = &*self.shared;
+        shared.popped.wait_until(|| {
+            shared.consumer_gone.load(Ordering::Acquire) || shared.len() < shared.slots.len()
+        });
+        // either fails on the gone consumer or finds room, only the
+        // producer fills the ring
+        self.push(item)
+    }
+
+    /// Whether the consumer is gone, pushes fail from then on.
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.consumer_gone.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    fn drop(&mut self) {
+        self.shared.producer_gone.store(true, Ordering::Release);
+        self.shared.pushed.notify();
+    }
+}
+
+/// The receiving end of a [`channel`].
+#[derive(Debug)]
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Consumer<T> {
+    /// Takes the oldest event if there is one, without blocking.
+    pub fn try_pop(&mut self) -> Option<T> {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        if head == shared.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: the slot was written by the producer before it published
+        // `tail`, and only the consumer reads slots
+        let item = unsafe { (*shared.slot(head)).assume_init_read() };
+        shared.head.store(head.wrapping_add(1), Ordering::Release);
+        shared.popped.notify();
+        Some(item)
+    }
+
+    /// Takes the oldest event, blocking until one is available.
+    ///
+    /// Returns `None` once the producer is gone and the ring is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        loop {
+            if let Some(item) = self.try_pop() {
+                return Some(item);
+            }
+            let shared = &*self.shared;
+            if shared.producer_gone.load(Ordering::Acquire) {
+                // the producer may have pushed right before leaving
+                return self.try_pop();
+            }
+            shared
+                .pushed
+                .wait_until(|| shared.len() > 0 || shared.producer_gone.load(Ordering::Acquire));
+        }
+    }
+
+    /// The number of queued events.
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len()
+    }
+
+    /// The counters of the ring, `dropped_oldest` is always `0`.
+    pub fn stats(&self) -> RingStats {
+        let shared = &*self.shared;
+        RingStats {
+            pushed: shared.pushes.load(Ordering::Relaxed),
+            dropped_oldest: 0,
+            dropped_newest: shared.dropped.load(Ordering::Relaxed),
+            high_water: shared.high_water.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T> Drop for Consumer<T> {
+    fn drop(&mut self) {
+        self.shared.consumer_gone.store(true, Ordering::Release);
+        self.shared.popped.notify();
+    }
+}
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// The index of the next slot to pop, only stored by the consumer.
+    head: AtomicUsize,
+    /// The index of the next slot to push, only stored by the producer.
+    tail: AtomicUsize,
+    pushed: Waiter,
+    popped: Waiter,
+    producer_gone: AtomicBool,
+    consumer_gone: AtomicBool,
+    pushes: AtomicU64,
+    dropped: AtomicU64,
+    high_water: AtomicUsize,
+}
+
+// SAFETY: the slots between `head` and `tail` belong to the consumer and the
+// others to the producer, so each `T` is only ever accessed by one thread
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn slot(&self, index: usize) -> *mut MaybeUninit<T> {
+        self.slots[index % self.slots.len()].get()
+    }
+
+    fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+}
+
+impl<T> std::fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shared")
+            .field("capacity", &self.slots.len())
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        while *self.head.get_mut() != *self.tail.get_mut() {
+            let head = *self.head.get_mut();
+            // SAFETY: the slots between `head` and `tail` are initialized
+            unsafe { (*self.slot(head)).assume_init_drop() };
+            *self.head.get_mut() = head.wrapping_add(1);
+        }
+    }
+}
+
+/// A futex one end sleeps on until the other end makes progress.
+#[derive(Debug, Default)]
+struct Waiter {
+    /// Bumped on every notification, the futex word.
+    seq: AtomicU32,
+    sleeping: AtomicBool,
+}
+
+impl Waiter {
+    fn notify(&self) {
+        self.seq.fetch_add(1, Ordering::SeqCst);
+        if self.sleeping.load(Ordering::SeqCst) {
+            // SAFETY: `seq` is a valid futex word for the lifetime of `self`
+            unsafe {
+                libc::syscall(
+                    libc::SYS_futex,
+                    self.seq.as_ptr(),
+                    libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                    1,
+                )
+            };
+        }
+    }
+
+    fn wait_until(&self, mut ready: impl FnMut() -> bool) {
+        loop {
+            let seq = self.seq.load(Ordering::SeqCst);
+            if ready() {
+                return;
+            }
+            self.sleeping.store(true, Ordering::SeqCst);
+            // returns at once if `seq` changed since it was loaded, so a
+            // notification in between is not missed
+            // SAFETY: `seq` is a valid futex word for the lifetime of `self`
+            unsafe {
+                libc::syscall(
+                    libc::SYS_futex,
+                    self.seq.as_ptr(),
+                    libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                    seq,
+                    std::ptr::null::<libc::timespec>(),
+                )
+            };
+            self.sleeping.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    /// Counts its drops in the shared counter.
+    #[derive(Debug)]
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn wraps_around_at_capacity_one() {
+        let (mut tx, mut rx) = channel(1);
+        for i in 0..10 {
+            tx.push(i).unwrap();
+            assert_eq!(tx.push(i + 100), Err(i + 100));
+            assert_eq!(rx.len(), 1);
+            assert_eq!(rx.try_pop(), Some(i));
+            assert_eq!(rx.try_pop(), None);
+        }
+        let stats = rx.stats();
+        assert_eq!(stats.pushed, 20);
+        assert_eq!(stats.dropped_newest, 10);
+        assert_eq!(stats.high_water, 1);
+    }
+
+    #[test]
+    fn drops_the_queued_items() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut tx, mut rx) = channel(4);
+        for _ in 0..3 {
+            tx.push(Counted(drops.clone())).unwrap();
+        }
+        drop(rx.try_pop());
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        drop(tx);
+        drop(rx);
+        assert_eq!(drops.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn drops_the_queued_items_after_wrapping_around() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut tx, mut rx) = channel(2);
+        for _ in 0..3 {
+            tx.push(Counted(drops.clone())).unwrap();
+            drop(rx.try_pop());
+        }
+        tx.push(Counted(drops.clone())).unwrap();
+        tx.push(Counted(drops.clone())).unwrap();
+        // returned back, not queued
+        drop(tx.push(Counted(drops.clone())));
+        assert_eq!(drops.load(Ordering::Relaxed), 4);
+        drop((tx, rx));
+        assert_eq!(drops.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn pop_drains_then_ends_once_the_producer_is_gone() {
+        let (mut tx, mut rx) = channel(4);
+        tx.push(1).unwrap();
+        tx.push(2).unwrap();
+        drop(tx);
+        assert_eq!(rx.pop(), Some(1));
+        assert_eq!(rx.pop(), Some(2));
+        assert_eq!(rx.pop(), None);
+    }
+
+    #[test]
+    fn pop_wakes_up_when_the_producer_leaves() {
+        let (tx, mut rx) = channel::<u32>(4);
+        let consumer = std::thread::spawn(move || rx.pop());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        drop(tx);
+        assert_eq!(consumer.join().unwrap(), None);
+    }
+
+    #[test]
+    fn push_fails_once_the_consumer_is_gone() {
+        let (mut tx, rx) = channel(4);
+        assert!(!tx.is_disconnected());
+        drop(rx);
+        assert!(tx.is_disconnected());
+        assert_eq!(tx.push(1), Err(1));
+        assert_eq!(tx.push_blocking(2), Err(2));
+    }
+
+    #[test]
+    fn push_blocking_wakes_up_when_the_consumer_leaves() {
+        let (mut tx, rx) = channel(1);
+        tx.push(1).unwrap();
+        let producer = std::thread::spawn(move || tx.push_blocking(2));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        drop(rx);
+        assert_eq!(producer.join().unwrap(), Err(2));
+    }
+
+    #[test]
+    fn push_blocking_and_pop_across_threads() {
+        const ITEMS: u64 = 100_000;
+        for capacity in [1, 3, 64] {
+            let (mut tx, mut rx) = channel(capacity);
+            let producer = std::thread::spawn(move || {
+                for i in 0..ITEMS {
+                    tx.push_blocking(i).unwrap();
+                }
+            });
+            let mut expected = 0;
+            while let Some(i) = rx.pop() {
+                assert_eq!(i, expected);
+                expected += 1;
+            }
+            producer.join().unwrap();
+            assert_eq!(expected, ITEMS);
+            let stats = rx.stats();
+            assert_eq!(stats.pushed, ITEMS);
+            assert_eq!(stats.dropped_newest, 0);
+            assert!(stats.high_water <= capacity);
+        }
+    }
+}
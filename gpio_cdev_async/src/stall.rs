@@ -0,0 +1,178 @@
+//! Detecting inputs that stop toggling.
+//!
+//! Some inputs are expected to change all the time, e.g. the tachometer of a
+//! fan or the heartbeat of another board. A [`StallMonitor`] reads the edge
+//! events of a handle on its own thread and keeps a timer per line: when a
+//! line sees no edge within its window, the callback gets
+//! [`StallEvent::Stalled`], and [`StallEvent::Recovered`] once it toggles
+//! again.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use std::time::Duration;
+//! # use gpio_cdev_async::{chip::Chip, line::LineRequest, stall::{StallEvent, StallMonitor}};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! # let chip = Chip::new("/dev/gpiochip0")?;
+//! # let request = LineRequest::builder().set_offsets([5u32]).build()?;
+//! // a handle with edge detection enabled on the tachometer line
+//! let tach = chip.get_line(request)?;
+//! let monitor = StallMonitor::spawn(tach, [(5, Duration::from_millis(500))], |event| {
+//!     if let StallEvent::Stalled { offset, .. } = event {
+//!         eprintln!("fan on line {offset} stopped");
+//!     }
+//! })?;
+//! // ...
+//! monitor.stop()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - The handle must be backed by a file descriptor, as kernel handles are.
+//! - v1 event handles have a single line, all their events are attributed to it.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    event::LineEvent,
+    line::LineHandle,
+    worker::{self, StopFd, Wakeup, Worker},
+    Result,
+};
+
+/// The number of events read at once.
+const READ_BATCH: usize = 16;
+
+/// A change of the state of a monitored line, see [`StallMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallEvent {
+    /// The line saw no edge for `quiet`, at least its window.
+    Stalled { offset: u32, quiet: Duration },
+    /// The line toggled again after [`StallEvent::Stalled`].
+    Recovered { offset: u32 },
+}
+
+/// Watches the lines of a handle for missing edges, see the
+/// [module documentation](self).
+///
+/// The monitor thread is stopped when the monitor is dropped.
+#[derive(Debug)]
+pub struct StallMonitor {
+    worker: Worker,
+}
+
+struct Timer {
+    offset: u32,
+    window: Duration,
+    last_edge: Instant,
+    stalled: bool,
+}
+
+impl StallMonitor {
+    /// Moves `handle` to a thread calling `callback` when one of the lines of
+    /// `windows`, pairs of offset and window, sees no edge within its window.
+    ///
+    /// The windows start now. Offsets the handle does not hold are ignored.
+    pub fn spawn(
+        handle: LineHandle,
+        windows: impl IntoIterator<Item = (u32, Duration)>,
+        callback: impl FnMut(StallEvent) + Send + 'static,
+    ) -> Result<Self> {
+        if handle.kernel_fd().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the handle has no fd to poll",
+            )
+            .into());
+        }
+        let now = Instant::now();
+        let timers = windows
+            .into_iter()
+            .filter(|(offset, _)| handle.offsets().contains(offset))
+            .map(|(offset, window)| Timer {
+                offset,
+                window,
+                last_edge: now,
+                stalled: false,
+            })
+            .collect();
+
+        let stop = Arc::new(StopFd::new()?);
+        let worker = Worker::spawn(
+            {
+                let stop = stop.clone();
+                move || watch(&handle, &stop, timers, callback)
+            },
+            move || stop.signal(),
+        );
+        Ok(Self { worker })
+    }
+
+    /// Stops the monitor thread and returns its result, e.g. a failed read.
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+fn watch(
+    handle: &LineHandle,
+    stop: &StopFd,
+    mut timers: Vec<Timer>,
+    mut callback: impl FnMut(StallEvent),
+) -> Result<()> {
+    let fd = handle.kernel_fd().unwrap();
+    let mut buf: Vec<LineEvent> = (0..READ_BATCH).map(|_| LineEvent::default()).collect();
+    loop {
+        // the nearest deadline of the lines not stalled yet
+        let now = Instant::now();
+        let timeout = timers
+            .iter()
+            .filter(|timer| !timer.stalled)
+            .map(|timer| (timer.last_edge + timer.window).saturating_duration_since(now))
+            .min();
+        let wakeup = worker::poll(fd, stop, timeout)?;
+        if wakeup == Wakeup::Stop {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        if wakeup == Wakeup::Readable {
+            let len = LineEvent::read(handle, &mut buf)?;
+            if len == 0 {
+                return Ok(());
+            }
+            for event in &buf[..len] {
+                #[cfg(feature = "v2")]
+                let offset = event.offset();
+                #[cfg(feature = "v1")]
+                let offset = {
+                    let _ = event;
+                    handle.offsets()[0]
+                };
+                for timer in timers.iter_mut().filter(|timer| timer.offset == offset) {
+                    timer.last_edge = now;
+                    if std::mem::take(&mut timer.stalled) {
+                        callback(StallEvent::Recovered { offset });
+                    }
+                }
+            }
+        }
+
+        for timer in timers.iter_mut().filter(|timer| !timer.stalled) {
+            let quiet = now.duration_since(timer.last_edge);
+            if quiet >= timer.window {
+                timer.stalled = true;
+                callback(StallEvent::Stalled {
+                    offset: timer.offset,
+                    quiet,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Reconfiguring several handles as one change, rolled back on failure.
+//!
+//! A [`Transaction`] collects new configurations for line handles. On
+//! [`Transaction::apply`] it first captures the current configuration of
+//! every line from the kernel, then applies the new ones in order. If a step
+//! fails, the steps already applied are reverted to their captured
+//! configuration, last first, and the [`TransactionError`] reports what was
+//! rolled back and what could not be.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::{chip::Chip, line::{HandleFlags, LineRequest}, transaction::Transaction};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let chip = Chip::new("/dev/gpiochip0")?;
+//! let leds = chip.get_line(LineRequest::builder().set_offsets([1u32, 2]).build()?)?;
+//! let enable = chip.get_line(LineRequest::builder().set_offsets([3u32]).build()?)?;
+//!
+//! # #[cfg(feature = "v1")]
+//! # let output = HandleFlags::REQUEST_OUTPUT;
+//! # #[cfg(feature = "v2")]
+//! # let output = HandleFlags::GPIO_V2_LINE_FLAG_OUTPUT;
+//! let config = |offsets: &[u32]| {
+//!     LineRequest::builder()
+//!         .set_flags(output)
+//!         .set_offsets(offsets.iter().copied())
+//!         .build()
+//! };
+//! Transaction::new()
+//!     .update(&chip, &leds, config(&[1, 2])?)
+//!     .update(&chip, &enable, config(&[3])?)
+//!     .apply()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - The captured configuration holds the direction, drive, bias and
+//!   polarity of the lines, on v2 also their debounce period, and the
+//!   current values of outputs. Edge detection is captured on v2 only.
+//! - v1 handles have one configuration for all their lines, the first
+//!   line's is captured.
+//!
+//! This module is available under both v1 and v2 features.
+
+use crate::{
+    chip::Chip,
+    line::{LineHandle, LineRequest},
+    Error, Result,
+};
+
+#[cfg(feature = "v2")]
+use crate::line::{LineFlags, PinAttribute};
+
+/// Reconfigurations of line handles applied together, see the
+/// [module documentation](self).
+#[derive(Debug, Default)]
+pub struct Transaction<'a> {
+    steps: Vec<Step<'a>>,
+}
+
+#[derive(Debug)]
+struct Step<'a> {
+    chip: &'a Chip,
+    handle: &'a LineHandle,
+    config: LineRequest,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the reconfiguration of `handle`, requested from `chip`, to `config`.
+    ///
+    /// The offsets of `config` must be those of the handle, in order.
+    pub fn update(mut self, chip: &'a Chip, handle: &'a LineHandle, config: LineRequest) -> Self {
+        self.steps.push(Step {
+            chip,
+            handle,
+            config,
+        });
+        self
+    }
+
+    /// Captures the current configurations, then applies the new ones in
+    /// the order they were added, rolling back on failure.
+    ///
+    /// Nothing is applied if capturing fails.
+    pub fn apply(self) -> std::result::Result<(), TransactionError> {
+        let mut previous = Vec::with_capacity(self.steps.len());
+        for (step, s) in self.steps.iter().enumerate() {
+            match capture(s.chip, s.handle) {
+                Ok(config) => previous.push(config),
+                Err(source) => {
+                    return Err(TransactionError {
+                        step,
+                        source,
+                        rolled_back: Vec::new(),
+                        rollback_failed: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        let mut previous = previous.into_iter();
+        let mut applied: Vec<(usize, &LineHandle, LineRequest)> = Vec::new();
+        for (step, s) in self.steps.into_iter().enumerate() {
+            let handle = s.handle;
+            let prior = previous.next().unwrap();
+            if let Err(source) = handle.update_config(s.config) {
+                let mut rolled_back = Vec::new();
+                let mut rollback_failed = Vec::new();
+                for (step, handle, prior) in applied.into_iter().rev() {
+                    match handle.update_config(prior) {
+                        Ok(()) => rolled_back.push(step),
+                        Err(e) => rollback_failed.push((step, e)),
+                    }
+                }
+                return Err(TransactionError {
+                    step,
+                    source,
+                    rolled_back,
+                    rollback_failed,
+                });
+            }
+            applied.push((step, handle, prior));
+        }
+        Ok(())
+    }
+}
+
+/// The failure of a [`Transaction`].
+///
+/// The steps after [`TransactionError::step`] were not applied.
+#[derive(Debug, thiserror::Error)]
+#[error("step {step} of the transaction failed: {source}")]
+pub struct TransactionError {
+    /// The index of the step that failed, in the order of [`Transaction::update`].
+    pub step: usize,
+    #[source]
+    pub source: Error,
+    /// The steps applied before the failure and reverted, last first.
+    pub rolled_back: Vec<usize>,
+    /// The steps applied before the failure that could not be reverted and
+    /// keep their new configuration.
+    pub rollback_failed: Vec<(usize, Error)>,
+}
+
+impl TransactionError {
+    /// Whether the lines are back in their configuration from before the
+    /// transaction.
+    pub fn is_rolled_back(&self) -> bool {
+        self.rollback_failed.is_empty()
+    }
+}
+
+/// Builds the current configuration of the lines of `handle` from the line
+/// info of `chip`.
+#[cfg(feature = "v2")]
+fn capture(chip: &Chip, handle: &LineHandle) -> Result<LineRequest> {
+    let offsets = handle.offsets();
+    let values = handle.get_values()?;
+    let mut lines = Vec::with_capacity(offsets.len());
+    for &offset in offsets {
+        let info = chip.get_lineinfo(offset)?;
+        // `USED` is only reported, the kernel rejects it in a config
+        let flags = info.flags() - LineFlags::GPIO_V2_LINE_FLAG_USED;
+        let mut attrs = vec![PinAttribute::Flags(flags)];
+        if let Some(debounce) = info.debounce() {
+            let us = debounce.as_micros().try_into().unwrap_or(u32::MAX);
+            attrs.push(PinAttribute::DebouncePeriodUs(us));
+        }
+        if flags.contains(LineFlags::GPIO_V2_LINE_FLAG_OUTPUT)
+            && let Some(value) = values.value_of_offset(offset)
+        {
+            attrs.push(PinAttribute::Value(value));
+        }
+        lines.push((offset, attrs));
+    }
+
+    let config = LineRequest::builder().set_offsets(lines).build()?;
+    if config.offsets().len() != offsets.len() {
+        // too many distinct attributes for the 10 slots of a config
+        return Err(std::io::Error::from_raw_os_error(libc::E2BIG).into());
+    }
+    Ok(config)
+}
+
+#[cfg(feature = "v1")]
+fn capture(chip: &Chip, handle: &LineHandle) -> Result<LineRequest> {
+    use crate::line::{HandleFlags, LineFlags};
+
+    let offsets = handle.offsets();
+    let Some(&first) = offsets.first() else {
+        return LineRequest::builder().build();
+    };
+    let info = chip.get_lineinfo(first)?;
+    // the polarity, drive and bias bits of both flags are the same
+    let shared = LineFlags::ACTIVE_LOW
+        | LineFlags::OPEN_DRAIN
+        | LineFlags::OPEN_SOURCE
+        | LineFlags::BIAS_PULL_UP
+        | LineFlags::BIAS_PULL_DOWN
+        | LineFlags::BIAS_DISABLE;
+    let mut flags = HandleFlags::from_bits_retain((info.flags() & shared).bits());
+    let output = info.flags().contains(LineFlags::IS_OUT);
+    flags |= match output {
+        true => HandleFlags::REQUEST_OUTPUT,
+        false => HandleFlags::REQUEST_INPUT,
+    };
+
+    let values = handle.get_values()?;
+    LineRequest::builder()
+        .set_flags(flags)
+        .set_offsets(offsets.iter().map(|&offset| {
+            let value = values.value_of_offset(offset).filter(|_| output);
+            (offset, value.unwrap_or_default())
+        }))
+        .build()
+}
@@ -0,0 +1,222 @@
+//! Petting an external watchdog IC wired to an output.
+//!
+//! Discrete watchdogs reset the board unless their input toggles within a
+//! timeout. A [`WatchdogPetter`] toggles an output line at a fixed interval
+//! from its own thread. [`WatchdogPetter::suspend`] stops petting, e.g. when
+//! the application detects a fault and wants the watchdog to reset the board,
+//! and [`WatchdogPetter::feed`] pets immediately.
+//!
+//! A pet is missed when the thread toggles the line more than a tolerance
+//! after it was scheduled, e.g. because the system was overloaded. Missed
+//! pets are counted and reported to a callback, as the watchdog may have
+//! fired in between.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use std::time::Duration;
+//! # use gpio_cdev_async::{chip::Chip, line::LineRequest, watchdog::WatchdogPetter};
+//! # fn main() -> gpio_cdev_async::Result<()> {
+//! # let chip = Chip::new("/dev/gpiochip0")?;
+//! # let request = LineRequest::builder().set_offsets([4u32]).build()?;
+//! let wdi = chip.get_line(request)?;
+//! let interval = Duration::from_millis(200);
+//! let tolerance = Duration::from_millis(50);
+//! let petter = WatchdogPetter::spawn(wdi, 4, interval, tolerance, |missed| {
+//!     eprintln!("watchdog pet {:?} late", missed.late);
+//! })?;
+//! // ...
+//! petter.stop()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//! - v1 handles write all their lines, the other lines of the handle are driven low.
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    io,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    line::{index_of_offset, LineHandle},
+    worker::{self, Worker},
+    Result,
+};
+
+/// A pet toggled later than its tolerance, see [`WatchdogPetter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissedPet {
+    /// When the pet was due.
+    pub scheduled: Instant,
+    /// How long after `scheduled` the line was toggled.
+    pub late: Duration,
+}
+
+/// Toggles an output at a fixed interval, see the
+/// [module documentation](self).
+///
+/// The thread is stopped when the petter is dropped, the line keeps its
+/// last value.
+#[derive(Debug)]
+pub struct WatchdogPetter {
+    shared: Arc<Shared>,
+    worker: Worker,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    wakeup: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    suspended: bool,
+    feed: bool,
+    stop: bool,
+    missed: u64,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        f(&mut self.lock());
+        self.wakeup.notify_one();
+    }
+}
+
+impl WatchdogPetter {
+    /// Moves `handle` to a thread toggling the line `offset` every
+    /// `interval`, starting now, and calling `on_missed` for every pet
+    /// toggled more than `tolerance` late.
+    pub fn spawn(
+        handle: LineHandle,
+        offset: u32,
+        interval: Duration,
+        tolerance: Duration,
+        on_missed: impl FnMut(MissedPet) + Send + 'static,
+    ) -> Result<Self> {
+        let Some(index) = index_of_offset(handle.offsets(), offset) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the handle does not hold the watchdog line",
+            )
+            .into());
+        };
+        let shared = Arc::new(Shared::default());
+        let worker = Worker::spawn(
+            {
+                let shared = shared.clone();
+                move || pet(&handle, 1 << index, interval, tolerance, &shared, on_missed)
+            },
+            {
+                let shared = shared.clone();
+                move || shared.update(|state| state.stop = true)
+            },
+        );
+        Ok(Self { shared, worker })
+    }
+
+    /// Toggles the line now, the next pet is due an interval later.
+    ///
+    /// Resumes petting if it was suspended.
+    pub fn feed(&self) {
+        self.shared.update(|state| {
+            state.feed = true;
+            state.suspended = false;
+        });
+    }
+
+    /// Stops toggling the line until [`WatchdogPetter::resume`] or
+    /// [`WatchdogPetter::feed`], so the watchdog fires.
+    pub fn suspend(&self) {
+        self.shared.update(|state| state.suspended = true);
+    }
+
+    /// Resumes petting after [`WatchdogPetter::suspend`], the next pet is
+    /// due an interval later.
+    pub fn resume(&self) {
+        self.shared.update(|state| state.suspended = false);
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.shared.lock().suspended
+    }
+
+    /// The number of pets toggled later than the tolerance so far.
+    pub fn missed(&self) -> u64 {
+        self.shared.lock().missed
+    }
+
+    /// Stops the thread and returns its result, e.g. a failed write.
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+fn pet(
+    handle: &LineHandle,
+    mask: u64,
+    interval: Duration,
+    tolerance: Duration,
+    shared: &Shared,
+    mut on_missed: impl FnMut(MissedPet),
+) -> Result<()> {
+    let write_mask = worker::write_mask(handle, mask);
+
+    let mut high = false;
+    let mut due = Instant::now();
+    let mut state = shared.lock();
+    loop {
+        if state.stop {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let feed = std::mem::take(&mut state.feed);
+        if state.suspended {
+            state = shared.wakeup.wait(state).unwrap_or_else(|e| e.into_inner());
+            due = Instant::now() + interval;
+            continue;
+        }
+        if !feed && now < due {
+            state = shared
+                .wakeup
+                .wait_timeout(state, due - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+            continue;
+        }
+
+        let late = now.saturating_duration_since(due);
+        let missed = !feed && late > tolerance;
+        if missed {
+            state.missed += 1;
+        }
+        drop(state);
+
+        high = !high;
+        handle
+            .backend
+            .set_values(write_mask, if high { mask } else { 0 })?;
+        if missed {
+            on_missed(MissedPet {
+                scheduled: due,
+                late,
+            });
+        }
+        // scheduled from the previous pet unless it was missed, so the
+        // interval does not drift
+        due = match missed || feed {
+            true => now + interval,
+            false => due + interval,
+        };
+        state = shared.lock();
+    }
+}
@@ -0,0 +1,320 @@
+//! WebSocket server streaming line activity as JSON, e.g. for a browser dashboard.
+//!
+//! Every client receives:
+//! - `{"type":"edge","offset":6,"edge":"rising","timestamp_ns":1234}` for the
+//!   edges of the inputs.
+//! - `{"type":"info","offset":6,"event":"requested","consumer":"foo","timestamp_ns":1234}`
+//!   for the info changes of the watched lines, `event` is `requested`,
+//!   `released` or `config`.
+//!
+//! and may send:
+//! - `{"cmd":"get"}`, answered by `{"type":"values","values":{"6":0,"17":1}}`.
+//! - `{"cmd":"set","offset":17,"value":1}`, answered by `{"type":"ok"}`.
+//!
+//! Failed commands are answered by `{"type":"error","message":"..."}`.
+//!
+//! # Examples
+//! ```rust,no_run
+//! # use gpio_cdev_async::websocket::WsServer;
+//! WsServer::builder("/dev/gpiochip0")
+//!     .set_inputs([6])
+//!     .set_outputs([17])
+//!     .set_watched([6, 17, 22])
+//!     .build()
+//!     .unwrap()
+//!     .serve("0.0.0.0:8080")
+//!     .unwrap();
+//! ```
+//!
+//! This module is available under both v1 and v2 features.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::{
+    bridge::BridgeLines,
+    chip::Chip,
+    event::{LineChangedType, LineEventType},
+    line::LineHandle,
+    Result,
+};
+
+/// How often a client connection checks for events to send while waiting for commands.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Builds a [`WsServer`], see [`WsServer::builder`].
+#[derive(Debug, Clone)]
+pub struct WsServerBuilder {
+    chip: PathBuf,
+    consumer: String,
+    inputs: Vec<u32>,
+    outputs: Vec<u32>,
+    watched: Vec<u32>,
+}
+
+impl WsServerBuilder {
+    /// The consumer label of the requested lines, `websocket` by default.
+    pub fn set_consumer(mut self, consumer: impl Into<String>) -> Self {
+        self.consumer = consumer.into();
+        self
+    }
+
+    /// The offsets of the lines whose edges are streamed.
+    pub fn set_inputs(mut self, offsets: impl IntoIterator<Item = u32>) -> Self {
+        self.inputs = offsets.into_iter().collect();
+        self
+    }
+
+    /// The offsets of the lines set by commands, they start low.
+    pub fn set_outputs(mut self, offsets: impl IntoIterator<Item = u32>) -> Self {
+        self.outputs = offsets.into_iter().collect();
+        self
+    }
+
+    /// The offsets of the lines whose info changes are streamed.
+    pub fn set_watched(mut self, offsets: impl IntoIterator<Item = u32>) -> Self {
+        self.watched = offsets.into_iter().collect();
+        self
+    }
+
+    /// Requests the lines, nothing is streamed before [`WsServer::serve`].
+    pub fn build(self) -> Result<WsServer> {
+        let BridgeLines { inputs, outputs } =
+            BridgeLines::request(&self.chip, &self.consumer, &self.inputs, &self.outputs)?;
+
+        let watcher = if self.watched.is_empty() {
+            None
+        } else {
+            let watcher = Chip::new(&self.chip)?;
+            for &offset in &self.watched {
+                watcher.get_lineinfo_watch(offset)?;
+            }
+            Some(watcher)
+        };
+
+        Ok(WsServer {
+            inputs: inputs.into_iter().map(Arc::new).collect(),
+            outputs: outputs.map(Arc::new),
+            watcher,
+        })
+    }
+}
+
+/// A WebSocket server owning its lines, see the [module documentation](self).
+#[derive(Debug)]
+pub struct WsServer {
+    inputs: Vec<Arc<LineHandle>>,
+    outputs: Option<Arc<LineHandle>>,
+    watcher: Option<Chip>,
+}
+
+/// The senders of the connected clients, dropped once their client is gone.
+type Clients = Arc<Mutex<Vec<mpsc::Sender<String>>>>;
+
+impl WsServer {
+    /// Serves the lines of the chip at `chip`.
+    pub fn builder(chip: impl AsRef<Path>) -> WsServerBuilder {
+        WsServerBuilder {
+            chip: chip.as_ref().to_path_buf(),
+            consumer: "websocket".into(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            watched: Vec::new(),
+        }
+    }
+
+    /// Listens on `addr` and serves clients, one thread per client.
+    ///
+    /// Only returns if listening fails.
+    pub fn serve(self, addr: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let clients: Clients = Default::default();
+
+        for input in &self.inputs {
+            let (input, clients) = (input.clone(), clients.clone());
+            std::thread::spawn(move || stream_edges(&input, &clients));
+        }
+        if let Some(watcher) = self.watcher {
+            let clients = clients.clone();
+            std::thread::spawn(move || stream_info_changes(&watcher, &clients));
+        }
+
+        #[cfg(feature = "v1")]
+        let lock = Arc::new(Mutex::new(()));
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let (tx, rx) = mpsc::channel();
+            clients.lock().unwrap().push(tx);
+
+            let lines = Lines {
+                inputs: self.inputs.clone(),
+                outputs: self.outputs.clone(),
+                #[cfg(feature = "v1")]
+                lock: lock.clone(),
+            };
+            std::thread::spawn(move || {
+                let _ = serve_client(stream, rx, &lines);
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The lines shared by the client connections.
+struct Lines {
+    inputs: Vec<Arc<LineHandle>>,
+    outputs: Option<Arc<LineHandle>>,
+    /// Serializes the read-modify-write of v1 outputs.
+    #[cfg(feature = "v1")]
+    lock: Arc<Mutex<()>>,
+}
+
+fn broadcast(clients: &Clients, message: Value) {
+    let message = message.to_string();
+    clients
+        .lock()
+        .unwrap()
+        .retain(|tx| tx.send(message.clone()).is_ok());
+}
+
+fn stream_edges(input: &LineHandle, clients: &Clients) {
+    for event in input.events() {
+        let Ok(event) = event else { return };
+
+        #[cfg(feature = "v1")]
+        let offset = input.offsets()[0];
+        #[cfg(feature = "v2")]
+        let offset = event.offset();
+        let edge = match event.event_type() {
+            Ok(LineEventType::RisingEdge) => "rising",
+            Ok(LineEventType::FallingEdge) => "falling",
+            Err(_) => return,
+        };
+        broadcast(
+            clients,
+            json!({
+                "type": "edge",
+                "offset": offset,
+                "edge": edge,
+                "timestamp_ns": event.timestamp_ns(),
+            }),
+        );
+    }
+}
+
+fn stream_info_changes(watcher: &Chip, clients: &Clients) {
+    for event in watcher.lineinfo_changes() {
+        let Ok(event) = event else { return };
+
+        let info = event.lineinfo();
+        let kind = match event.event_type() {
+            LineChangedType::Requested => "requested",
+            LineChangedType::Released => "released",
+            LineChangedType::Config => "config",
+        };
+        broadcast(
+            clients,
+            json!({
+                "type": "info",
+                "offset": info.offset(),
+                "event": kind,
+                "consumer": info.consumer(),
+                "timestamp_ns": event.timestamp_ns(),
+            }),
+        );
+    }
+}
+
+fn serve_client(
+    stream: TcpStream,
+    events: mpsc::Receiver<String>,
+    lines: &Lines,
+) -> io::Result<()> {
+    let mut ws = tungstenite::accept(stream).map_err(io::Error::other)?;
+    ws.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    loop {
+        while let Ok(event) = events.try_recv() {
+            send(&mut ws, event)?;
+        }
+
+        let message = match ws.read() {
+            Ok(message) => message,
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue;
+            }
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(io::Error::other(e)),
+        };
+
+        let reply = match message {
+            Message::Text(text) => command(&text, lines),
+            Message::Close(_) => return Ok(()),
+            _ => continue,
+        };
+        send(&mut ws, reply.to_string())?;
+    }
+}
+
+fn send(ws: &mut WebSocket<TcpStream>, message: String) -> io::Result<()> {
+    ws.send(Message::text(message)).map_err(io::Error::other)
+}
+
+fn command(text: &str, lines: &Lines) -> Value {
+    let error = |message: String| json!({ "type": "error", "message": message });
+
+    let Ok(command) = serde_json::from_str::<Value>(text) else {
+        return error(format!("invalid command {text:?}"));
+    };
+    match command["cmd"].as_str() {
+        Some("get") => {
+            let mut values = BTreeMap::new();
+            for line in lines.inputs.iter().chain(&lines.outputs) {
+                match line.get_values() {
+                    Ok(line_values) => values.extend(
+                        line_values
+                            .values_iter()
+                            .map(|item| (item.offset.to_string(), item.value)),
+                    ),
+                    Err(e) => return error(e.to_string()),
+                }
+            }
+            json!({ "type": "values", "values": values })
+        }
+        Some("set") => {
+            let (Some(offset), Some(value)) =
+                (command["offset"].as_u64(), command["value"].as_u64())
+            else {
+                return error("`set` needs an `offset` and a `value`".into());
+            };
+            let Some(outputs) = lines.outputs.as_deref() else {
+                return error("no outputs".into());
+            };
+            let offset = offset as u32;
+            if !outputs.offsets().contains(&offset) {
+                return error(format!("{offset} is not an output"));
+            }
+            #[cfg(feature = "v1")]
+            let _lock = lines.lock.lock().unwrap();
+            match outputs.set_value(offset, u8::from(value != 0)) {
+                Ok(()) => json!({ "type": "ok" }),
+                Err(e) => error(e.to_string()),
+            }
+        }
+        _ => error(format!("unknown command {text:?}")),
+    }
+}
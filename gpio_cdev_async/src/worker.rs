@@ -0,0 +1,135 @@
+//! The background thread of the drivers owning a handle:
+//! [`sampler`](crate::sampler), [`buzzer`](crate::buzzer),
+//! [`sevenseg`](crate::sevenseg), [`watchdog`](crate::watchdog) and
+//! [`stall`](crate::stall), and of the threads reading edges until told to
+//! stop, see [`StopFd`].
+
+use std::{
+    fmt::Debug,
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{line::LineHandle, Result};
+
+/// A thread stopped and joined when the worker is dropped.
+pub(crate) struct Worker {
+    thread: Option<JoinHandle<Result<()>>>,
+    /// Asks the thread to return.
+    stop: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl Debug for Worker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Worker")
+            .field("thread", &self.thread)
+            .finish()
+    }
+}
+
+impl Worker {
+    /// Runs `run` on a new thread, `stop` asks it to return.
+    pub(crate) fn spawn(
+        run: impl FnOnce() -> Result<()> + Send + 'static,
+        stop: impl FnOnce() + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            thread: Some(std::thread::spawn(run)),
+            stop: Some(Box::new(stop)),
+        }
+    }
+
+    /// Stops the thread and returns its result, panics again if it
+    /// panicked.
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        let (Some(thread), Some(stop)) = (self.thread.take(), self.stop.take()) else {
+            return Ok(());
+        };
+        stop();
+        thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// The mask to write the lines of `mask` of `handle` with: v1 writes all
+/// the lines of a handle.
+pub(crate) fn write_mask(handle: &LineHandle, mask: u64) -> u64 {
+    #[cfg(feature = "v1")]
+    let mask = handle.all_mask();
+    mask
+}
+
+/// An eventfd waking up the threads [`poll`]ing it to stop. It stays
+/// readable once signalled, so it stops any number of threads.
+#[derive(Debug)]
+pub(crate) struct StopFd(OwnedFd);
+
+impl StopFd {
+    pub(crate) fn new() -> Result<Self> {
+        // SAFETY: `eventfd` has no memory safety requirements
+        match unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) } {
+            -1 => Err(io::Error::last_os_error().into()),
+            // SAFETY: the fd is new and owned here
+            fd => Ok(Self(unsafe { OwnedFd::from_raw_fd(fd) })),
+        }
+    }
+
+    pub(crate) fn signal(&self) {
+        let one = 1u64.to_ne_bytes();
+        // SAFETY: writes the 8 bytes of `one`
+        unsafe { libc::write(self.0.as_raw_fd(), one.as_ptr().cast(), one.len()) };
+    }
+}
+
+/// What [`poll`] returned for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Wakeup {
+    Readable,
+    Stop,
+    Timeout,
+}
+
+/// Waits until `fd` is readable, `stop` is signalled or `timeout` elapsed,
+/// forever without a timeout. A signalled `stop` wins over a readable `fd`.
+pub(crate) fn poll(fd: RawFd, stop: &StopFd, timeout: Option<Duration>) -> io::Result<Wakeup> {
+    let timeout = match timeout {
+        // rounded up, so the timeout has elapsed on wakeup
+        Some(timeout) => timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32,
+        None => -1,
+    };
+    let mut fds = [
+        libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: stop.0.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+    ];
+    // SAFETY: `fds` is valid for its length
+    while unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) } == -1 {
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+    Ok(if fds[1].revents != 0 {
+        Wakeup::Stop
+    } else if fds[0].revents != 0 {
+        Wakeup::Readable
+    } else {
+        Wakeup::Timeout
+    })
+}
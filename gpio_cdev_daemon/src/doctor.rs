@@ -0,0 +1,81 @@
+//! The `doctor` subcommand: explains who can open the chips and how to
+//! grant access.
+//!
+//! ```sh
+//! gpio-cdev-daemon doctor [--udev-rule GROUP]
+//! ```
+//!
+//! Without options, prints the owner, group and mode of every chip, whether
+//! the invoking user can open it, and whether they are in its group. With
+//! `--udev-rule`, prints a rule giving `GROUP` access to all the chips.
+
+use std::io;
+
+use gpio_cdev_async::permissions::{self, UDEV_RULE_PATH};
+
+pub(crate) fn run(args: &[String]) -> io::Result<()> {
+    match args {
+        [] => report(),
+        [flag, group] if flag == "--udev-rule" => {
+            let rule = permissions::udev_rule(group).map_err(io::Error::other)?;
+            if permissions::group_id(group).map_err(io::Error::other)?.is_none() {
+                eprintln!("warning: there is no group `{group}` yet, create it with `groupadd {group}`");
+            }
+            print!("{rule}");
+            eprintln!("install it at {UDEV_RULE_PATH}, then run `udevadm trigger --subsystem-match=gpio`");
+            Ok(())
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: gpio-cdev-daemon doctor [--udev-rule GROUP]",
+        )),
+    }
+}
+
+fn report() -> io::Result<()> {
+    let chips = permissions::chip_paths().map_err(io::Error::other)?;
+    if chips.is_empty() {
+        println!("no /dev/gpiochip* found, is the GPIO character device enabled in the kernel?");
+        return Ok(());
+    }
+    let groups = permissions::groups().map_err(io::Error::other)?;
+
+    let mut missing_groups = Vec::new();
+    let mut group_inaccessible = false;
+    for path in &chips {
+        let access = permissions::check(path).map_err(io::Error::other)?;
+        println!(
+            "{}: owner {}, group {}, mode {:04o}, {}",
+            path.display(),
+            access.uid,
+            group_name(access.gid),
+            access.mode,
+            if access.accessible { "accessible" } else { "not accessible" },
+        );
+        let group_access = access.mode & 0o060 == 0o060;
+        group_inaccessible |= !group_access;
+        if !access.accessible
+            && group_access
+            && !groups.contains(&access.gid)
+            && !missing_groups.contains(&access.gid)
+        {
+            missing_groups.push(access.gid);
+        }
+    }
+
+    for gid in missing_groups {
+        let group = group_name(gid);
+        println!("you are not in the group {group}, add yourself with `usermod -aG {group} $USER` and log in again");
+    }
+    if group_inaccessible {
+        println!("some chips are not group accessible, see `gpio-cdev-daemon doctor --udev-rule GROUP`");
+    }
+    Ok(())
+}
+
+fn group_name(gid: u32) -> String {
+    match permissions::group_name(gid) {
+        Ok(Some(name)) => name,
+        _ => gid.to_string(),
+    }
+}
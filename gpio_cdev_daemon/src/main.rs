@@ -0,0 +1,326 @@
+//! A GPIO broker owning line requests on behalf of unprivileged clients.
+//!
+//! ```sh
+//! gpio-cdev-daemon [SOCKET]
+//! gpio-cdev-daemon doctor [--udev-rule GROUP]
+//! ```
+//!
+//! Listens on `SOCKET` (`/run/gpio-cdev.sock` by default) and serves the
+//! protocol of `gpio_cdev_async::broker`, one thread per connection.
+//! `doctor` checks the access to the chips instead, see `doctor.rs`.
+//!
+//! # Notes
+//! - Access is controlled by the permissions of the socket, e.g. give it to a
+//!   `gpio` group.
+//! - Only chips at `/dev/gpiochip*` are opened.
+//! - Under systemd, `Type=notify`, `WatchdogSec=` and socket activation are
+//!   supported, see the units in `systemd/`. An activated socket takes
+//!   precedence over `SOCKET`.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    os::{
+        fd::AsRawFd,
+        unix::{
+            fs::FileTypeExt,
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use gpio_cdev_async::{
+    broker::{read_frame, write_frame, ChipSummary, EdgeEvent, LineSummary, Request, Response},
+    chip::Chip,
+    config::Edge,
+    event::LineEvent,
+    line::{HandleFlags, LineHandle, LineRequest},
+    permissions, Error, Result,
+};
+
+mod doctor;
+mod systemd;
+
+const DEFAULT_SOCKET: &str = "/run/gpio-cdev.sock";
+
+fn main() -> io::Result<()> {
+    if std::env::args().nth(1).as_deref() == Some("doctor") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        return doctor::run(&args);
+    }
+
+    let socket = std::env::args_os()
+        .nth(1)
+        .map_or_else(|| PathBuf::from(DEFAULT_SOCKET), PathBuf::from);
+
+    // SAFETY: no other thread is spawned yet
+    let listener = match unsafe { systemd::listener() } {
+        Some(listener) => {
+            eprintln!("listening on the activated socket");
+            listener
+        }
+        None => {
+            // a stale socket of a previous run
+            if fs::symlink_metadata(&socket).is_ok_and(|m| m.file_type().is_socket()) {
+                fs::remove_file(&socket)?;
+            }
+            let listener = UnixListener::bind(&socket)?;
+            eprintln!("listening on {}", socket.display());
+            listener
+        }
+    };
+    if let Err(e) = systemd::notify("READY=1") {
+        eprintln!("notifying systemd failed: {e}");
+    }
+    let watchdog = systemd::watchdog_interval();
+    let mut petted: Option<Instant> = None;
+
+    loop {
+        // petted by the accept loop itself, so systemd restarts it if it hangs
+        if let Some(interval) = watchdog {
+            if petted.is_none_or(|at| at.elapsed() >= interval) {
+                let _ = systemd::notify("WATCHDOG=1");
+                petted = Some(Instant::now());
+            }
+            if !wait_readable(&listener, interval)? {
+                continue;
+            }
+        }
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("accept failed: {e}");
+                continue;
+            }
+        };
+        std::thread::spawn(move || {
+            if let Err(e) = serve(stream) {
+                eprintln!("connection failed: {e}");
+            }
+        });
+    }
+}
+
+/// Waits up to `timeout` for `fd` to be readable, returns whether it is.
+fn wait_readable(fd: &impl AsRawFd, timeout: Duration) -> io::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().try_into().unwrap_or(i32::MAX);
+    if unsafe { libc::poll(&mut pollfd, 1, timeout) } < 0 {
+        let e = io::Error::last_os_error();
+        if e.kind() == io::ErrorKind::Interrupted {
+            return Ok(false);
+        }
+        return Err(e);
+    }
+    Ok(pollfd.revents != 0)
+}
+
+/// Serves the requests of one connection, its handles are released when it returns.
+fn serve(mut stream: UnixStream) -> io::Result<()> {
+    let mut handles = HashMap::new();
+    let mut next_handle = 0u32;
+
+    while let Some(payload) = read_frame(&mut stream)? {
+        let request = match Request::decode(&payload) {
+            Ok(request) => request,
+            Err(e) => {
+                let response = Response::Error {
+                    errno: 0,
+                    message: e.to_string(),
+                };
+                write_frame(&mut stream, &response.encode())?;
+                return Err(e);
+            }
+        };
+
+        next_handle = next_handle.wrapping_add(1);
+        if let Request::Subscribe {
+            chip,
+            offset,
+            flags,
+            edges,
+            consumer,
+        } = request
+        {
+            match subscribe(&chip, offset, flags, edges, &consumer) {
+                Ok(line) => {
+                    write_frame(&mut stream, &Response::Handle(next_handle).encode())?;
+                    return stream_events(stream, next_handle, line);
+                }
+                Err(e) => write_frame(&mut stream, &Response::error(&e).encode())?,
+            }
+            continue;
+        }
+
+        let response =
+            handle(request, &mut handles, next_handle).unwrap_or_else(|e| Response::error(&e));
+        write_frame(&mut stream, &response.encode())?;
+    }
+    Ok(())
+}
+
+fn handle(
+    request: Request,
+    handles: &mut HashMap<u32, LineHandle>,
+    next_handle: u32,
+) -> Result<Response> {
+    match request {
+        Request::ListChips => {
+            let mut chips = Vec::new();
+            for path in permissions::chip_paths()? {
+                let info = Chip::new(&path)?.get_chipinfo()?;
+                chips.push(ChipSummary {
+                    path: path.to_string_lossy().into_owned(),
+                    name: info.name().into_owned(),
+                    label: info.label().into_owned(),
+                    lines: info.lines(),
+                });
+            }
+            Ok(Response::Chips(chips))
+        }
+        Request::ListLines { chip } => {
+            let chip = open_chip(&chip)?;
+            let lines = (0..chip.get_chipinfo()?.lines())
+                .map(|offset| {
+                    let info = chip.get_lineinfo(offset)?;
+                    Ok(LineSummary {
+                        offset: info.offset(),
+                        name: info.name().into_owned(),
+                        consumer: info.consumer().into_owned(),
+                        flags: info.flags().bits() as _,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Ok(Response::Lines(lines))
+        }
+        Request::Request {
+            chip,
+            offsets,
+            flags,
+            values,
+            consumer,
+        } => {
+            let lines = offsets
+                .iter()
+                .enumerate()
+                .map(|(index, &offset)| (offset, values.get(index).copied()));
+            let request = LineRequest::builder()
+                .set_flags(HandleFlags::from_bits_retain(flags as _))
+                .set_consumer(consumer)
+                .set_offsets_with_values(lines)
+                .build()?;
+            let line = open_chip(&chip)?.get_line(request)?;
+            handles.insert(next_handle, line);
+            Ok(Response::Handle(next_handle))
+        }
+        Request::Get { handle } => {
+            let values = line(handles, handle)?.get_values()?;
+            Ok(Response::Values(
+                values.values_iter().map(|item| item.value).collect(),
+            ))
+        }
+        Request::Set { handle, values } => {
+            line(handles, handle)?.set_values_in_order(&values)?;
+            Ok(Response::Ok)
+        }
+        Request::Release { handle } => {
+            handles
+                .remove(&handle)
+                .ok_or_else(|| Error::Io(io::Error::from_raw_os_error(libc::EBADF)))?;
+            Ok(Response::Ok)
+        }
+        Request::Subscribe { .. } => unreachable!("handled by `serve`"),
+    }
+}
+
+fn line(handles: &HashMap<u32, LineHandle>, handle: u32) -> Result<&LineHandle> {
+    handles
+        .get(&handle)
+        .ok_or_else(|| Error::Io(io::Error::from_raw_os_error(libc::EBADF)))
+}
+
+fn subscribe(
+    chip: &str,
+    offset: u32,
+    flags: u64,
+    edges: u32,
+    consumer: &str,
+) -> Result<LineHandle> {
+    let edges = Edge::from_bits(edges)
+        .ok_or_else(|| Error::Io(io::Error::from_raw_os_error(libc::EINVAL)))?;
+    open_chip(chip)?.request_edge_events(
+        offset,
+        edges,
+        HandleFlags::from_bits_retain(flags as _),
+        consumer,
+    )
+}
+
+/// Forwards the edge events of `line` until the client closes the connection.
+fn stream_events(mut stream: UnixStream, handle: u32, line: LineHandle) -> io::Result<()> {
+    let mut events: [LineEvent; 16] = Default::default();
+    let mut discard = [0u8; 64];
+    let Some(fd) = line.fd() else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the line handle has no fd",
+        ));
+    };
+
+    loop {
+        let mut fds = [
+            libc::pollfd {
+                fd: fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: stream.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) } < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        // requests are ignored once subscribed, only a close matters
+        if fds[1].revents != 0 && io::Read::read(&mut stream, &mut discard)? == 0 {
+            return Ok(());
+        }
+
+        if fds[0].revents != 0 {
+            let n = LineEvent::read(&line, &mut events).map_err(io::Error::other)?;
+            for event in &events[..n] {
+                #[cfg(feature = "v1")]
+                let offset = line.offsets()[0];
+                #[cfg(feature = "v2")]
+                let offset = event.offset();
+                let event = Response::Event(EdgeEvent {
+                    handle,
+                    offset,
+                    event_type: event.event_type().map_err(io::Error::other)? as u32,
+                    timestamp_ns: event.timestamp_ns() as _,
+                });
+                write_frame(&mut stream, &event.encode())?;
+            }
+        }
+    }
+}
+
+fn open_chip(path: &str) -> Result<Chip> {
+    if !permissions::is_chip_path(path) {
+        return Err(Error::Io(io::Error::from_raw_os_error(libc::EACCES)));
+    }
+    Chip::new(path)
+}
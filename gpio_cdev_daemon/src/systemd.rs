@@ -0,0 +1,72 @@
+//! The systemd integration: readiness, watchdog and socket activation.
+//!
+//! See `sd_notify(3)` and `sd_listen_fds(3)`, the unit files in `systemd/`
+//! show how to deploy the daemon.
+
+use std::{
+    env, io,
+    os::{
+        fd::{FromRawFd, RawFd},
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram, UnixListener},
+    },
+    time::Duration,
+};
+
+/// The first file descriptor passed by socket activation.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Sends `state` to the service manager, does nothing when not run by systemd.
+pub(crate) fn notify(state: &str) -> io::Result<()> {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+    let path = path.as_encoded_bytes();
+    let addr = match path.strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(std::str::from_utf8(path).map_err(io::Error::other)?)?,
+    };
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
+
+/// Returns the socket passed by socket activation, if any.
+///
+/// # Safety
+/// Must be called once, before any other thread is spawned.
+pub(crate) unsafe fn listener() -> Option<UnixListener> {
+    let pid = env::var("LISTEN_PID").ok()?.parse::<u32>().ok()?;
+    let fds = env::var("LISTEN_FDS").ok()?.parse::<u32>().ok()?;
+    // SAFETY: no other thread reads the environment yet
+    unsafe {
+        env::remove_var("LISTEN_PID");
+        env::remove_var("LISTEN_FDS");
+        env::remove_var("LISTEN_FDNAMES");
+    }
+    if pid != std::process::id() || fds == 0 {
+        return None;
+    }
+
+    let fd = SD_LISTEN_FDS_START;
+    // the passed fds are inherited without `FD_CLOEXEC`
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+        return None;
+    }
+    // SAFETY: systemd passes ownership of the listening socket
+    Some(unsafe { UnixListener::from_raw_fd(fd) })
+}
+
+/// How often to send `WATCHDOG=1`, half the interval systemd expects, if the
+/// watchdog is enabled for this process.
+pub(crate) fn watchdog_interval() -> Option<Duration> {
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    let for_us = env::var("WATCHDOG_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_none_or(|pid| pid == std::process::id());
+    if !for_us || usec == 0 {
+        return None;
+    }
+    Some(Duration::from_micros(usec / 2))
+}
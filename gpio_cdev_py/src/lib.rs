@@ -0,0 +1,262 @@
+//! Python bindings for `gpio_cdev_async`.
+//!
+//! # Examples
+//! ```python
+//! import gpio_cdev
+//!
+//! chip = gpio_cdev.Chip("/dev/gpiochip0")
+//! lines = chip.request_events(6, edges=gpio_cdev.EDGE_BOTH, consumer="example")
+//! while True:
+//!     event = lines.read_event()
+//!     print(event.offset, event.event_type, event.timestamp_ns)
+//! ```
+//!
+//! # Notes
+//! - Blocking waits release the GIL, other Python threads keep running while
+//!   a thread waits for events.
+//! - `flags` are `GPIOHANDLE_REQUEST_*` flags with the v1 feature and
+//!   `GPIO_V2_LINE_FLAG_*` flags with the v2 feature.
+
+use std::path::{Path, PathBuf};
+
+use gpio_cdev_async::{
+    chip,
+    config::Edge,
+    event::{LineEvent, LineInfoChangedEvent},
+    line::{self, HandleFlags, LineRequest},
+    Error,
+};
+use pyo3::{
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+};
+
+const EDGE_RISING: u32 = 1 << 0;
+const EDGE_FALLING: u32 = 1 << 1;
+const EDGE_BOTH: u32 = EDGE_RISING | EDGE_FALLING;
+
+fn to_py_err(e: Error) -> PyErr {
+    match e.raw_os_error() {
+        Some(errno) => PyOSError::new_err((errno, e.to_string())),
+        None => PyOSError::new_err(e.to_string()),
+    }
+}
+
+/// A GPIO chip, e.g. `Chip("/dev/gpiochip0")`.
+#[pyclass(frozen, module = "gpio_cdev")]
+struct Chip {
+    inner: chip::Chip,
+}
+
+#[pymethods]
+impl Chip {
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        let inner = chip::Chip::new(path).map_err(to_py_err)?;
+        Ok(Self { inner })
+    }
+
+    #[getter]
+    fn path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    /// Get the information of the chip.
+    fn info(&self) -> PyResult<ChipInfo> {
+        let info = self.inner.get_chipinfo().map_err(to_py_err)?;
+        Ok(ChipInfo {
+            name: info.name().into_owned(),
+            label: info.label().into_owned(),
+            lines: info.lines(),
+        })
+    }
+
+    /// Get the information of the line at `offset`.
+    fn line_info(&self, offset: u32) -> PyResult<LineInfo> {
+        let info = self.inner.get_lineinfo(offset).map_err(to_py_err)?;
+        Ok(LineInfo::from(&info))
+    }
+
+    /// Request lines, `default_values` are the initial values of output lines.
+    #[pyo3(signature = (offsets, flags = 0, default_values = None, consumer = ""))]
+    fn request_lines(
+        &self,
+        offsets: Vec<u32>,
+        flags: u64,
+        default_values: Option<Vec<u8>>,
+        consumer: &str,
+    ) -> PyResult<Lines> {
+        let default_values = default_values.unwrap_or_default();
+        let lines = offsets
+            .iter()
+            .enumerate()
+            .map(|(index, &offset)| (offset, default_values.get(index).copied()));
+        let inner = LineRequest::builder()
+            .set_flags(HandleFlags::from_bits_retain(flags as _))
+            .set_consumer(consumer)
+            .set_offsets_with_values(lines)
+            .build()
+            .and_then(|request| self.inner.get_line(request))
+            .map_err(to_py_err)?;
+        Ok(Lines { inner })
+    }
+
+    /// Request edge events on the line at `offset`.
+    ///
+    /// `edges` is a combination of `EDGE_RISING` and `EDGE_FALLING`.
+    #[pyo3(signature = (offset, flags = 0, edges = EDGE_BOTH, consumer = ""))]
+    fn request_events(
+        &self,
+        offset: u32,
+        flags: u64,
+        edges: u32,
+        consumer: &str,
+    ) -> PyResult<Lines> {
+        let edges = Edge::from_bits(edges)
+            .ok_or_else(|| PyValueError::new_err("`edges` has no edge set"))?;
+        let inner = self
+            .inner
+            .request_edge_events(
+                offset,
+                edges,
+                HandleFlags::from_bits_retain(flags as _),
+                consumer,
+            )
+            .map_err(to_py_err)?;
+        Ok(Lines { inner })
+    }
+
+    /// Start watching the line at `offset` for info changes, returns its current information.
+    fn watch_line_info(&self, offset: u32) -> PyResult<LineInfo> {
+        let info = self.inner.get_lineinfo_watch(offset).map_err(to_py_err)?;
+        Ok(LineInfo::from(&info))
+    }
+
+    /// Stop watching the line at `offset`.
+    fn unwatch_line_info(&self, offset: u32) -> PyResult<()> {
+        self.inner.get_lineinfo_unwatch(offset).map_err(to_py_err)
+    }
+
+    /// Block until a watched line changes, the GIL is released while waiting.
+    fn read_line_info_change(&self, py: Python<'_>) -> PyResult<LineInfoChange> {
+        let event = py.detach(|| {
+            let mut buf = [LineInfoChangedEvent::default()];
+            LineInfoChangedEvent::read(&self.inner, &mut buf).map(|_| {
+                let [event] = buf;
+                event
+            })
+        });
+        let event = event.map_err(to_py_err)?;
+        Ok(LineInfoChange {
+            event_type: event.event_type() as u32,
+            timestamp_ns: event.timestamp_ns() as _,
+            info: LineInfo::from(event.lineinfo()),
+        })
+    }
+}
+
+/// Requested lines, returned by `Chip.request_lines` and `Chip.request_events`.
+#[pyclass(frozen, module = "gpio_cdev")]
+struct Lines {
+    inner: line::LineHandle,
+}
+
+#[pymethods]
+impl Lines {
+    #[getter]
+    fn offsets(&self) -> Vec<u32> {
+        self.inner.offsets().to_vec()
+    }
+
+    /// Get the values of the lines, in request order.
+    fn get_values(&self) -> PyResult<Vec<u8>> {
+        let values = self.inner.get_values().map_err(to_py_err)?;
+        Ok(values.values_iter().map(|item| item.value).collect())
+    }
+
+    /// Set the values of the lines, in request order.
+    fn set_values(&self, values: Vec<u8>) -> PyResult<()> {
+        self.inner.set_values_in_order(&values).map_err(to_py_err)
+    }
+
+    /// Block until an edge event is available, the GIL is released while waiting.
+    fn read_event(&self, py: Python<'_>) -> PyResult<EdgeEvent> {
+        let event = py.detach(|| {
+            let mut buf = [LineEvent::default()];
+            LineEvent::read(&self.inner, &mut buf).map(|_| {
+                let [event] = buf;
+                event
+            })
+        });
+        let event = event.map_err(to_py_err)?;
+
+        #[cfg(feature = "v1")]
+        let offset = self.inner.offsets()[0];
+        #[cfg(feature = "v2")]
+        let offset = event.offset();
+        Ok(EdgeEvent {
+            event_type: event.event_type().map_err(to_py_err)? as u32,
+            timestamp_ns: event.timestamp_ns() as _,
+            offset,
+        })
+    }
+}
+
+/// The information of a chip.
+#[pyclass(frozen, get_all, module = "gpio_cdev")]
+struct ChipInfo {
+    name: String,
+    label: String,
+    lines: u32,
+}
+
+/// The information of a line, `flags` are the raw kernel line flags.
+#[pyclass(frozen, get_all, skip_from_py_object, module = "gpio_cdev")]
+#[derive(Clone)]
+struct LineInfo {
+    offset: u32,
+    name: String,
+    consumer: String,
+    flags: u64,
+}
+
+impl From<&line::LineInfo> for LineInfo {
+    fn from(info: &line::LineInfo) -> Self {
+        Self {
+            offset: info.offset(),
+            name: info.name().into_owned(),
+            consumer: info.consumer().into_owned(),
+            flags: info.flags().bits() as _,
+        }
+    }
+}
+
+/// An edge event, `event_type` is `1` for a rising edge and `2` for a falling edge.
+#[pyclass(frozen, get_all, module = "gpio_cdev")]
+struct EdgeEvent {
+    event_type: u32,
+    timestamp_ns: u64,
+    offset: u32,
+}
+
+/// A line info change, `event_type` is `1` (requested), `2` (released) or `3` (config).
+#[pyclass(frozen, get_all, module = "gpio_cdev")]
+struct LineInfoChange {
+    event_type: u32,
+    timestamp_ns: u64,
+    info: LineInfo,
+}
+
+#[pymodule]
+fn gpio_cdev(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("EDGE_RISING", EDGE_RISING)?;
+    m.add("EDGE_FALLING", EDGE_FALLING)?;
+    m.add("EDGE_BOTH", EDGE_BOTH)?;
+    m.add_class::<Chip>()?;
+    m.add_class::<ChipInfo>()?;
+    m.add_class::<LineInfo>()?;
+    m.add_class::<Lines>()?;
+    m.add_class::<EdgeEvent>()?;
+    m.add_class::<LineInfoChange>()?;
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//! UniFFI bindings for `gpio_cdev_async`, for Kotlin/Swift host applications.
+//!
+//! Generate the foreign sources from the built library with:
+//! ```sh
+//! cargo build --release -p gpio_cdev_uniffi
+//! cargo run --features cli --bin uniffi-bindgen -- generate \
+//!     --library target/release/libgpio_cdev_uniffi.so --language kotlin --out-dir out
+//! ```
+//!
+//! # Notes
+//! - `flags` are `GPIOHANDLE_REQUEST_*` flags with the v1 feature and
+//!   `GPIO_V2_LINE_FLAG_*` flags with the v2 feature.
+//! - `edges` is a non-empty combination of `1` (rising) and `2` (falling).
+
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+
+use gpio_cdev_async::{
+    chip,
+    config::Edge,
+    event::LineEvent,
+    line::{self, HandleFlags, LineRequest},
+};
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum GpioError {
+    /// A failed system call, `errno` is `0` if it is unknown.
+    #[error("{message}")]
+    Os { errno: i32, message: String },
+}
+
+impl From<gpio_cdev_async::Error> for GpioError {
+    fn from(e: gpio_cdev_async::Error) -> Self {
+        Self::Os {
+            errno: e.raw_os_error().unwrap_or_default(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<io::Error> for GpioError {
+    fn from(e: io::Error) -> Self {
+        gpio_cdev_async::Error::from(e).into()
+    }
+}
+
+/// The information of a chip.
+#[derive(Debug, uniffi::Record)]
+pub struct ChipInfo {
+    pub name: String,
+    pub label: String,
+    pub lines: u32,
+}
+
+/// The information of a line, `flags` are the raw kernel line flags.
+#[derive(Debug, uniffi::Record)]
+pub struct LineInfo {
+    pub offset: u32,
+    pub name: String,
+    pub consumer: String,
+    pub flags: u64,
+}
+
+/// An edge event, `event_type` is `1` for a rising edge and `2` for a falling edge.
+#[derive(Debug, uniffi::Record)]
+pub struct EdgeEvent {
+    pub event_type: u32,
+    pub timestamp_ns: u64,
+    pub offset: u32,
+}
+
+/// Receives the events of [`Lines::listen`], from a dedicated thread.
+#[uniffi::export(callback_interface)]
+pub trait EdgeEventListener: Send + Sync {
+    /// Called for every edge event, return `false` to stop listening.
+    fn on_event(&self, event: EdgeEvent) -> bool;
+    /// Called once if reading events fails, listening stops afterwards.
+    fn on_error(&self, error: GpioError);
+}
+
+/// A GPIO chip.
+#[derive(Debug, uniffi::Object)]
+pub struct Chip {
+    inner: chip::Chip,
+}
+
+#[uniffi::export]
+impl Chip {
+    #[uniffi::constructor]
+    pub fn new(path: String) -> Result<Arc<Self>, GpioError> {
+        let inner = chip::Chip::new(path)?;
+        Ok(Arc::new(Self { inner }))
+    }
+
+    /// Get the information of the chip.
+    pub fn info(&self) -> Result<ChipInfo, GpioError> {
+        let info = self.inner.get_chipinfo()?;
+        Ok(ChipInfo {
+            name: info.name().into_owned(),
+            label: info.label().into_owned(),
+            lines: info.lines(),
+        })
+    }
+
+    /// Get the information of the line at `offset`.
+    pub fn line_info(&self, offset: u32) -> Result<LineInfo, GpioError> {
+        let info = self.inner.get_lineinfo(offset)?;
+        Ok(LineInfo {
+            offset: info.offset(),
+            name: info.name().into_owned(),
+            consumer: info.consumer().into_owned(),
+            flags: info.flags().bits() as _,
+        })
+    }
+
+    /// Request lines, `default_values` are the initial values of output lines.
+    pub fn request_lines(
+        &self,
+        offsets: Vec<u32>,
+        flags: u64,
+        default_values: Option<Vec<u8>>,
+        consumer: String,
+    ) -> Result<Arc<Lines>, GpioError> {
+        let default_values = default_values.unwrap_or_default();
+        let lines = offsets
+            .iter()
+            .enumerate()
+            .map(|(index, &offset)| (offset, default_values.get(index).copied()));
+        let request = LineRequest::builder()
+            .set_flags(HandleFlags::from_bits_retain(flags as _))
+            .set_consumer(consumer)
+            .set_offsets_with_values(lines)
+            .build()?;
+        let inner = self.inner.get_line(request)?;
+        Ok(Arc::new(Lines { inner }))
+    }
+
+    /// Request edge events on the line at `offset`.
+    pub fn request_events(
+        &self,
+        offset: u32,
+        flags: u64,
+        edges: u32,
+        consumer: String,
+    ) -> Result<Arc<Lines>, GpioError> {
+        let edges = Edge::from_bits(edges).ok_or_else(|| GpioError::Os {
+            errno: libc::EINVAL,
+            message: "`edges` has no edge set".into(),
+        })?;
+        let inner = self.inner.request_edge_events(
+            offset,
+            edges,
+            HandleFlags::from_bits_retain(flags as _),
+            consumer,
+        )?;
+        Ok(Arc::new(Lines { inner }))
+    }
+}
+
+/// Requested lines.
+#[derive(Debug, uniffi::Object)]
+pub struct Lines {
+    inner: line::LineHandle,
+}
+
+#[uniffi::export]
+impl Lines {
+    pub fn offsets(&self) -> Vec<u32> {
+        self.inner.offsets().to_vec()
+    }
+
+    /// Get the values of the lines, in request order.
+    pub fn get_values(&self) -> Result<Vec<u8>, GpioError> {
+        let values = self.inner.get_values()?;
+        Ok(values.values_iter().map(|item| item.value).collect())
+    }
+
+    /// Set the values of the lines, in request order.
+    pub fn set_values(&self, values: Vec<u8>) -> Result<(), GpioError> {
+        Ok(self.inner.set_values_in_order(&values)?)
+    }
+
+    /// Block until an edge event is available.
+    pub fn read_event(&self) -> Result<EdgeEvent, GpioError> {
+        let mut buf = [LineEvent::default()];
+        LineEvent::read(&self.inner, &mut buf)?;
+        let [event] = buf;
+
+        #[cfg(feature = "v1")]
+        let offset = self.inner.offsets()[0];
+        #[cfg(feature = "v2")]
+        let offset = event.offset();
+        Ok(EdgeEvent {
+            event_type: event.event_type()? as u32,
+            timestamp_ns: event.timestamp_ns() as _,
+            offset,
+        })
+    }
+
+    /// Deliver the edge events to `listener` from a dedicated thread, until
+    /// the returned [`Listening`] is stopped or destroyed.
+    pub fn listen(
+        self: Arc<Self>,
+        listener: Box<dyn EdgeEventListener>,
+    ) -> Result<Arc<Listening>, GpioError> {
+        if self.inner.fd().is_none() {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "the lines have no fd").into());
+        }
+        // SAFETY: `eventfd` has no memory safety requirements
+        let stop = match unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) } {
+            -1 => return Err(io::Error::last_os_error().into()),
+            // SAFETY: the fd is new and owned here
+            fd => unsafe { OwnedFd::from_raw_fd(fd) },
+        };
+        let stop_fd = stop.try_clone()?;
+        let thread = std::thread::spawn(move || self.deliver(&stop_fd, listener));
+        Ok(Arc::new(Listening {
+            stop,
+            thread: Mutex::new(Some(thread)),
+        }))
+    }
+}
+
+impl Lines {
+    fn deliver(&self, stop: &OwnedFd, listener: Box<dyn EdgeEventListener>) {
+        let Some(fd) = self.inner.fd() else {
+            listener.on_error(io::Error::from(io::ErrorKind::Unsupported).into());
+            return;
+        };
+        loop {
+            let mut fds = [
+                libc::pollfd {
+                    fd: fd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: stop.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            // SAFETY: `fds` is valid for its length
+            if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } == -1 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                listener.on_error(e.into());
+                return;
+            }
+            if fds[1].revents != 0 {
+                return;
+            }
+            match self.read_event() {
+                Ok(event) => {
+                    if !listener.on_event(event) {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    listener.on_error(e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// The delivery of the edge events of [`Lines::listen`], stopped when
+/// destroyed.
+#[derive(Debug, uniffi::Object)]
+pub struct Listening {
+    /// An eventfd waking the delivery thread up to stop.
+    stop: OwnedFd,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[uniffi::export]
+impl Listening {
+    /// Stop delivering events, waiting for a listener call in progress to
+    /// return unless called from the listener itself.
+    pub fn stop(&self) {
+        let Some(thread) = self.thread.lock().unwrap().take() else {
+            return;
+        };
+        let one = 1u64.to_ne_bytes();
+        // SAFETY: writes the 8 bytes of `one`
+        unsafe { libc::write(self.stop.as_raw_fd(), one.as_ptr().cast(), one.len()) };
+        if thread.thread().id() != std::thread::current().id() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Listening {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}